@@ -0,0 +1,40 @@
+#version 330 core
+
+in vec3 frag_light;
+in vec2 frag_uv;
+in float frag_distance;
+
+uniform sampler2D texture0;
+uniform float skylight;
+uniform float opacity;
+
+uniform int fog_mode;
+uniform float fog_start;
+uniform float fog_end;
+uniform float fog_density;
+uniform vec3 fog_color;
+uniform float render_distance;
+uniform float fade_distance;
+
+out vec4 color;
+
+float fog_amount() {
+    float fog = 0.0;
+    if (fog_mode == 1) {
+        fog = clamp((frag_distance - fog_start) / (fog_end - fog_start), 0.0, 1.0);
+    } else if (fog_mode == 2) {
+        float d = fog_density * frag_distance;
+        fog = 1.0 - exp(-d * d);
+    }
+    float fade = 0.0;
+    if (fade_distance > 0.0) {
+        fade = smoothstep(render_distance - fade_distance, render_distance, frag_distance);
+    }
+    return max(fog, fade);
+}
+
+void main() {
+    vec4 texel = texture(texture0, frag_uv);
+    vec3 lit = texel.rgb * max(frag_light, vec3(skylight));
+    color = vec4(mix(lit, fog_color, fog_amount()), texel.a * opacity);
+}
\ No newline at end of file
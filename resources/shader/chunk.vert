@@ -0,0 +1,19 @@
+#version 330 core
+
+layout (location = 0) in vec3 position;
+layout (location = 1) in vec3 light;
+layout (location = 2) in vec2 uv;
+
+uniform mat4 mvp;
+uniform mat4 model_view;
+
+out vec3 frag_light;
+out vec2 frag_uv;
+out float frag_distance;
+
+void main() {
+    gl_Position = mvp * vec4(position, 1.0);
+    frag_light = light;
+    frag_uv = uv;
+    frag_distance = length((model_view * vec4(position, 1.0)).xyz);
+}
\ No newline at end of file
@@ -0,0 +1,9 @@
+#version 330 core
+
+uniform vec4 overlay_color;
+
+out vec4 color;
+
+void main() {
+    color = overlay_color;
+}
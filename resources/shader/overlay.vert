@@ -0,0 +1,12 @@
+#version 330 core
+
+layout (location = 0) in vec3 position;
+
+// Left, top, width and height in pixels, from the top left corner of the screen.
+uniform vec4 rect;
+uniform vec2 screen_size;
+
+void main() {
+    vec2 pixel = rect.xy + position.xy * rect.zw;
+    gl_Position = vec4(pixel.x / screen_size.x * 2.0 - 1.0, 1.0 - pixel.y / screen_size.y * 2.0, 0.0, 1.0);
+}
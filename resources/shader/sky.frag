@@ -0,0 +1,29 @@
+#version 330 core
+
+in vec3 frag_direction;
+
+uniform vec3 sky_color;
+uniform vec3 sun_direction;
+uniform float daylight;
+
+out vec4 color;
+
+const vec3 sun_color = vec3(1.0, 0.95, 0.8);
+const vec3 moon_color = vec3(0.8, 0.85, 0.9);
+const float sun_size = 0.9995;
+const float moon_size = 0.9997;
+
+void main() {
+    vec3 direction = normalize(frag_direction);
+
+    // The zenith is a deeper shade of the horizon colour.
+    vec3 zenith = sky_color * vec3(0.45, 0.6, 0.9);
+    vec3 sky = mix(sky_color, zenith, clamp(direction.y, 0.0, 1.0));
+
+    float sun = smoothstep(sun_size - 0.0005, sun_size, dot(direction, sun_direction));
+    float moon = smoothstep(moon_size - 0.0003, moon_size, dot(direction, -sun_direction));
+    sky = mix(sky, sun_color, sun);
+    sky = mix(sky, moon_color, moon * (1.0 - daylight));
+
+    color = vec4(sky, 1.0);
+}
\ No newline at end of file
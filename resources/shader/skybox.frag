@@ -0,0 +1,11 @@
+#version 330 core
+
+in vec3 frag_direction;
+
+uniform samplerCube cubemap;
+
+out vec4 color;
+
+void main() {
+    color = texture(cubemap, frag_direction);
+}
\ No newline at end of file
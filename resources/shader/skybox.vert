@@ -0,0 +1,13 @@
+#version 330 core
+
+layout (location = 0) in vec3 position;
+
+uniform mat4 view_projection;
+
+out vec3 frag_direction;
+
+void main() {
+    // The unit cube spans 0..1, centre it on the camera.
+    frag_direction = position - vec3(0.5);
+    gl_Position = view_projection * vec4(frag_direction, 1.0);
+}
\ No newline at end of file
@@ -0,0 +1,16 @@
+#version 330 core
+
+in vec3 frag_color;
+in vec2 frag_uv;
+
+uniform sampler2D texture0;
+
+out vec4 color;
+
+void main() {
+    // The atlas is white, its alpha is the coverage of the glyphs.
+    color = vec4(frag_color, texture(texture0, frag_uv).a);
+    if (color.a < 0.01) {
+        discard;
+    }
+}
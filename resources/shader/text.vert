@@ -0,0 +1,17 @@
+#version 330 core
+
+// In pixels from the top left corner of the screen.
+layout (location = 0) in vec3 position;
+layout (location = 1) in vec3 color;
+layout (location = 2) in vec2 uv;
+
+uniform vec2 screen_size;
+
+out vec3 frag_color;
+out vec2 frag_uv;
+
+void main() {
+    gl_Position = vec4(position.x / screen_size.x * 2.0 - 1.0, 1.0 - position.y / screen_size.y * 2.0, 0.0, 1.0);
+    frag_color = color;
+    frag_uv = uv;
+}
@@ -1,3 +1,9 @@
+mod assetmanager;
+pub use assetmanager::AssetManager;
+
+mod font;
+pub use font::{Font, Glyph, REPLACEMENT_CHAR};
+
 mod shader;
 pub use shader::Shader;
 
@@ -1,27 +1,69 @@
-use std::{collections::HashMap, path::Path};
+use std::{collections::HashMap, path::Path, rc::Rc};
 
 use image::ImageError;
 
-use super::Texture;
+use super::{Font, Texture};
 
-const TEXTURE_PATH: &str = "resources/textures/";
+const TEXTURE_PATH: &str = "resources/texture/";
+const FONT_PATH: &str = "resources/font/";
 
+/// Loads textures and fonts by name from the resources directory, sharing each one
+/// between everything that asks for it.
 pub struct AssetManager {
-    textures: HashMap<String, Texture>,
+    textures: HashMap<String, Rc<Texture>>,
+    /// Fonts with the texture of their atlas.
+    fonts: HashMap<String, (Rc<Font>, Rc<Texture>)>,
 }
 
 impl AssetManager {
     pub fn new() -> Self {
-        Self { textures: HashMap::new() }
+        Self { textures: HashMap::new(), fonts: HashMap::new() }
     }
 
-    pub fn get_texture(&self, name: &str) -> Option<&Texture> {
-        self.textures.get(name)
+    pub fn get_texture(&self, name: &str) -> Option<Rc<Texture>> {
+        self.textures.get(name).cloned()
     }
 
-    pub fn load_texture(&mut self, name: &str) -> Result<(), ImageError> {
-        let texture = Texture::new(Path::new(&(TEXTURE_PATH.to_owned() + name)))?;
-        self.textures.insert(name.to_owned(), texture);
-        Ok(())
+    /// Loads the image `name` in the texture directory, unless it is already loaded.
+    pub fn load_texture(&mut self, name: &str) -> Result<Rc<Texture>, ImageError> {
+        self.load(name, Texture::new)
     }
-}
\ No newline at end of file
+
+    /// Loads the cubemap whose faces are in the directory `name` in the texture
+    /// directory, unless it is already loaded. See `Texture::cubemap_from_directory`.
+    pub fn load_cubemap(&mut self, name: &str) -> Result<Rc<Texture>, ImageError> {
+        self.load(name, Texture::cubemap_from_directory)
+    }
+
+    pub fn get_font(&self, name: &str) -> Option<(Rc<Font>, Rc<Texture>)> {
+        self.fonts.get(name).cloned()
+    }
+
+    /// Loads the `.fnt` file `name` in the font directory and its atlas, unless they are
+    /// already loaded.
+    pub fn load_font(&mut self, name: &str) -> Result<(Rc<Font>, Rc<Texture>), ImageError> {
+        if let Some(font) = self.get_font(name) {
+            return Ok(font);
+        }
+        let font = Font::load(Path::new(&(FONT_PATH.to_owned() + name)))?;
+        let atlas = Rc::new(font.load_atlas()?);
+        let font = (Rc::new(font), atlas);
+        self.fonts.insert(name.to_owned(), font.clone());
+        Ok(font)
+    }
+
+    fn load(&mut self, name: &str, load: fn(&Path) -> Result<Texture, ImageError>) -> Result<Rc<Texture>, ImageError> {
+        if let Some(texture) = self.get_texture(name) {
+            return Ok(texture);
+        }
+        let texture = Rc::new(load(Path::new(&(TEXTURE_PATH.to_owned() + name)))?);
+        self.textures.insert(name.to_owned(), texture.clone());
+        Ok(texture)
+    }
+}
+
+impl Default for AssetManager {
+    fn default() -> Self {
+        Self::new()
+    }
+}
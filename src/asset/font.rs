@@ -0,0 +1,208 @@
+use std::{collections::HashMap, fs, io, path::{Path, PathBuf}};
+
+use image::ImageError;
+
+use crate::encoding::invalid_data;
+
+use super::Texture;
+
+/// Drawn in place of characters the font has no glyph for.
+pub const REPLACEMENT_CHAR: char = '?';
+
+/// Where a character is in the atlas and how it is placed relative to the pen, in pixels.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Glyph {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+    /// Offset from the pen to the left edge of the glyph.
+    pub x_offset: i32,
+    /// Offset from the top of the line to the top edge of the glyph.
+    pub y_offset: i32,
+    /// How far the pen moves after the glyph.
+    pub x_advance: i32,
+}
+
+/// A bitmap font in the text format of AngelCode's BMFont, with a single atlas page.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Font {
+    pub line_height: i32,
+    /// Distance from the top of a line to the baseline.
+    pub base: i32,
+    pub atlas_width: u32,
+    pub atlas_height: u32,
+    /// The atlas image, relative to the font file until loaded with `load`.
+    pub page: PathBuf,
+    glyphs: HashMap<char, Glyph>,
+    kernings: HashMap<(char, char), i32>,
+}
+
+impl Font {
+    /// Reads a `.fnt` file, resolving the atlas next to it.
+    pub fn load(path: &Path) -> io::Result<Self> {
+        let mut font = Self::parse(&fs::read_to_string(path)?)?;
+        if let Some(directory) = path.parent() {
+            font.page = directory.join(&font.page);
+        }
+        Ok(font)
+    }
+
+    /// Parses the text format. Unknown tags and keys are ignored.
+    pub fn parse(text: &str) -> io::Result<Self> {
+        let mut font = Self {
+            line_height: 0,
+            base: 0,
+            atlas_width: 0,
+            atlas_height: 0,
+            page: PathBuf::new(),
+            glyphs: HashMap::new(),
+            kernings: HashMap::new(),
+        };
+        let mut has_common = false;
+        for line in text.lines() {
+            let mut tokens = tokenize(line)?.into_iter();
+            let Some((tag, _)) = tokens.next() else {
+                continue;
+            };
+            let attributes: HashMap<&str, String> = tokens.collect();
+            let value = |key: &str| -> io::Result<&String> {
+                attributes.get(key).ok_or_else(|| invalid_data(format!("'{}' is missing {}", tag, key)))
+            };
+            let number = |key: &str| -> io::Result<i32> {
+                let value = value(key)?;
+                value.parse().map_err(|_| invalid_data(format!("Invalid {} '{}'", key, value)))
+            };
+            // Sizes and positions in the atlas, which cannot be negative.
+            let unsigned = |key: &str| -> io::Result<u32> {
+                let value = value(key)?;
+                value.parse().map_err(|_| invalid_data(format!("Invalid {} '{}', expected a number of at least 0", key, value)))
+            };
+            let character = |key: &str| -> io::Result<char> {
+                let code = unsigned(key)?;
+                char::from_u32(code).ok_or_else(|| invalid_data(format!("Invalid character {}", code)))
+            };
+            match tag {
+                "common" => {
+                    if number("pages")? != 1 {
+                        return Err(invalid_data("Only fonts with a single page are supported".to_owned()));
+                    }
+                    font.line_height = number("lineHeight")?;
+                    font.base = number("base")?;
+                    font.atlas_width = unsigned("scaleW")?;
+                    font.atlas_height = unsigned("scaleH")?;
+                    has_common = true;
+                },
+                "page" => {
+                    let file = attributes.get("file").ok_or_else(|| invalid_data("'page' is missing file".to_owned()))?;
+                    font.page = PathBuf::from(file);
+                },
+                "char" => {
+                    let glyph = Glyph {
+                        x: unsigned("x")?,
+                        y: unsigned("y")?,
+                        width: unsigned("width")?,
+                        height: unsigned("height")?,
+                        x_offset: number("xoffset")?,
+                        y_offset: number("yoffset")?,
+                        x_advance: number("xadvance")?,
+                    };
+                    font.glyphs.insert(character("id")?, glyph);
+                },
+                "kerning" => {
+                    font.kernings.insert((character("first")?, character("second")?), number("amount")?);
+                },
+                _ => {},
+            }
+        }
+        if !has_common {
+            return Err(invalid_data("Font has no 'common' line".to_owned()));
+        }
+        let inside = |glyph: &Glyph| {
+            glyph.x.checked_add(glyph.width).is_some_and(|right| right <= font.atlas_width)
+                && glyph.y.checked_add(glyph.height).is_some_and(|bottom| bottom <= font.atlas_height)
+        };
+        if let Some((c, _)) = font.glyphs.iter().find(|(_, glyph)| !inside(glyph)) {
+            return Err(invalid_data(format!("Glyph of '{}' is outside the atlas", c)));
+        }
+        Ok(font)
+    }
+
+    /// Loads the atlas image as a texture.
+    pub fn load_atlas(&self) -> Result<Texture, ImageError> {
+        Texture::new(&self.page)
+    }
+
+    /// The glyph of `c`, or of `REPLACEMENT_CHAR` when the font does not have one.
+    pub fn glyph(&self, c: char) -> Option<&Glyph> {
+        self.glyphs.get(&c).or_else(|| self.glyphs.get(&REPLACEMENT_CHAR))
+    }
+
+    /// Extra pen movement between `first` and `second`, usually negative.
+    pub fn kerning(&self, first: char, second: char) -> i32 {
+        self.kernings.get(&(first, second)).copied().unwrap_or(0)
+    }
+}
+
+/// Splits a line into its tag and `key=value` pairs. Values may be quoted to contain
+/// spaces.
+fn tokenize(line: &str) -> io::Result<Vec<(&str, String)>> {
+    let mut tokens = Vec::new();
+    let mut rest = line.trim_start();
+    while !rest.is_empty() {
+        let end = rest.find([' ', '\t', '=']).unwrap_or(rest.len());
+        let key = &rest[..end];
+        rest = &rest[end..];
+        let value = match rest.strip_prefix('=') {
+            Some(after) => match after.strip_prefix('"') {
+                Some(quoted) => {
+                    let close = quoted.find('"').ok_or_else(|| invalid_data(format!("Unterminated quote in '{}'", line)))?;
+                    rest = &quoted[close + 1..];
+                    quoted[..close].to_owned()
+                },
+                None => {
+                    let end = after.find([' ', '\t']).unwrap_or(after.len());
+                    rest = &after[end..];
+                    after[..end].to_owned()
+                },
+            },
+            None => String::new(),
+        };
+        tokens.push((key, value));
+        rest = rest.trim_start();
+    }
+    Ok(tokens)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const COMMON: &str = "common lineHeight=10 base=8 scaleW=64 scaleH=64 pages=1\n";
+
+    fn parse_char(line: &str) -> io::Result<Font> {
+        Font::parse(&format!("{}{}", COMMON, line))
+    }
+
+    #[test]
+    fn parses_glyphs_inside_the_atlas() {
+        let font = parse_char("char id=97 x=60 y=0 width=4 height=6 xoffset=-1 yoffset=2 xadvance=5").unwrap();
+        let glyph = font.glyph('a').unwrap();
+        assert_eq!((glyph.x, glyph.width, glyph.x_offset), (60, 4, -1));
+    }
+
+    #[test]
+    fn rejects_negative_sizes_and_positions() {
+        assert!(parse_char("char id=97 x=-1 y=0 width=4 height=6 xoffset=0 yoffset=0 xadvance=5").is_err());
+        assert!(parse_char("char id=97 x=0 y=0 width=-4 height=6 xoffset=0 yoffset=0 xadvance=5").is_err());
+        assert!(parse_char("char id=-97 x=0 y=0 width=4 height=6 xoffset=0 yoffset=0 xadvance=5").is_err());
+        assert!(Font::parse("common lineHeight=10 base=8 scaleW=-64 scaleH=64 pages=1").is_err());
+    }
+
+    #[test]
+    fn rejects_glyphs_reaching_past_the_atlas_without_overflowing() {
+        assert!(parse_char("char id=97 x=61 y=0 width=4 height=6 xoffset=0 yoffset=0 xadvance=5").is_err());
+        assert!(parse_char("char id=97 x=4294967295 y=0 width=4 height=6 xoffset=0 yoffset=0 xadvance=5").is_err());
+        assert!(parse_char("char id=97 x=0 y=4 height=4294967295 width=4 xoffset=0 yoffset=0 xadvance=5").is_err());
+    }
+}
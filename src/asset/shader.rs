@@ -1,6 +1,6 @@
 use gl;
 use gl::types::GLuint;
-use nalgebra::Matrix4;
+use nalgebra::{Matrix4, Vector2, Vector3, Vector4};
 use std::ffi::CString;
 use std::fs;
 
@@ -33,6 +33,30 @@ impl Shader {
         }
     }
 
+    pub fn uniform_vec2(&self, name: &str, value: Vector2<f32>) {
+        unsafe {
+            let name = CString::new(name).unwrap_or_else(|_| panic!("Invalid name {}", name));
+            let location = gl::GetUniformLocation(self.id, name.as_ptr());
+            gl::Uniform2f(location, value.x, value.y);
+        }
+    }
+
+    pub fn uniform_vec3(&self, name: &str, value: Vector3<f32>) {
+        unsafe {
+            let name = CString::new(name).unwrap_or_else(|_| panic!("Invalid name {}", name));
+            let location = gl::GetUniformLocation(self.id, name.as_ptr());
+            gl::Uniform3f(location, value.x, value.y, value.z);
+        }
+    }
+
+    pub fn uniform_vec4(&self, name: &str, value: Vector4<f32>) {
+        unsafe {
+            let name = CString::new(name).unwrap_or_else(|_| panic!("Invalid name {}", name));
+            let location = gl::GetUniformLocation(self.id, name.as_ptr());
+            gl::Uniform4f(location, value.x, value.y, value.z, value.w);
+        }
+    }
+
     pub fn uniform_mat4(&self, name: &str, value: Matrix4<f32>) {
         unsafe {
             let name = CString::new(name).expect(format!("Invalid name {}", name).as_str());
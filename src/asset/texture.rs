@@ -1,10 +1,15 @@
 use std::path::Path;
 
-use gl::types::GLuint;
+use gl::types::{GLenum, GLuint};
 use image::{RgbaImage, EncodableLayout, ImageError};
 
+/// File names of the cubemap faces inside a cubemap directory, named after `Direction`
+/// and ordered like the GL cubemap targets.
+pub const CUBEMAP_FACES: [&str; 6] = ["left.png", "right.png", "up.png", "down.png", "back.png", "front.png"];
+
 pub struct Texture {
-    id: GLuint
+    id: GLuint,
+    target: GLenum,
 }
 
 impl Texture {
@@ -15,36 +20,72 @@ impl Texture {
         unsafe {
             gl::GenTextures(1, &mut id);
             gl::BindTexture(gl::TEXTURE_2D, id);
-            gl::TexImage2D(
-                gl::TEXTURE_2D,
-                0,
-                gl::RGBA as i32,
-                img.width() as i32,
-                img.height() as i32,
-                0,
-                gl::RGBA,
-                gl::UNSIGNED_BYTE,
-                img.as_bytes().as_ptr() as *const _,
-            );
+            upload_image(gl::TEXTURE_2D, &img);
             gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, gl::NEAREST as i32);
             gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, gl::NEAREST as i32);
         }
 
-        Ok(Self { id })
+        Ok(Self { id, target: gl::TEXTURE_2D })
     }
+
+    /// Loads a cubemap from six images, ordered positive X, negative X, positive Y,
+    /// negative Y, positive Z and negative Z.
+    pub fn new_cubemap(faces: [&Path; 6]) -> Result<Self, ImageError> {
+        let mut images = Vec::with_capacity(faces.len());
+        for face in faces {
+            images.push(image::open(face)?.into_rgba8());
+        }
+
+        let mut id: GLuint = 0;
+        unsafe {
+            gl::GenTextures(1, &mut id);
+            gl::BindTexture(gl::TEXTURE_CUBE_MAP, id);
+            for (i, img) in images.iter().enumerate() {
+                upload_image(gl::TEXTURE_CUBE_MAP_POSITIVE_X + i as GLenum, img);
+            }
+            gl::TexParameteri(gl::TEXTURE_CUBE_MAP, gl::TEXTURE_MAG_FILTER, gl::LINEAR as i32);
+            gl::TexParameteri(gl::TEXTURE_CUBE_MAP, gl::TEXTURE_MIN_FILTER, gl::LINEAR as i32);
+            gl::TexParameteri(gl::TEXTURE_CUBE_MAP, gl::TEXTURE_WRAP_S, gl::CLAMP_TO_EDGE as i32);
+            gl::TexParameteri(gl::TEXTURE_CUBE_MAP, gl::TEXTURE_WRAP_T, gl::CLAMP_TO_EDGE as i32);
+            gl::TexParameteri(gl::TEXTURE_CUBE_MAP, gl::TEXTURE_WRAP_R, gl::CLAMP_TO_EDGE as i32);
+        }
+
+        Ok(Self { id, target: gl::TEXTURE_CUBE_MAP })
+    }
+
+    /// Loads a cubemap from a directory containing the images named in `CUBEMAP_FACES`.
+    pub fn cubemap_from_directory(directory: &Path) -> Result<Self, ImageError> {
+        let faces = CUBEMAP_FACES.map(|face| directory.join(face));
+        Self::new_cubemap(faces.each_ref().map(|face| face.as_path()))
+    }
+
     pub fn bind(&self) {
         unsafe {
             gl::ActiveTexture(0);
-            gl::BindTexture(gl::TEXTURE_2D, self.id);
+            gl::BindTexture(self.target, self.id);
         }
     }
     pub fn unbind(&self) {
         unsafe {
-            gl::BindTexture(gl::TEXTURE_2D, 0);
+            gl::BindTexture(self.target, 0);
         }
     }
 }
 
+unsafe fn upload_image(target: GLenum, img: &RgbaImage) {
+    gl::TexImage2D(
+        target,
+        0,
+        gl::RGBA as i32,
+        img.width() as i32,
+        img.height() as i32,
+        0,
+        gl::RGBA,
+        gl::UNSIGNED_BYTE,
+        img.as_bytes().as_ptr() as *const _,
+    );
+}
+
 impl Drop for Texture {
     fn drop(&mut self) {
         unsafe {
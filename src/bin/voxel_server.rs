@@ -0,0 +1,79 @@
+//! The dedicated server. Builds without the `client` feature, so without GL or GLFW:
+//! `cargo build --bin voxel_server --no-default-features`.
+
+use std::{path::Path, sync::mpsc::TryRecvError, thread, time::{Duration, Instant}};
+
+use voxel_game::{command::{spawn_stdin_reader, CommandSender}, server::{Server, ServerProperties}, simulation::{Simulation, TICK_DELTA}};
+
+const PROPERTIES_PATH: &str = "server.properties";
+
+fn main() {
+    let properties = match ServerProperties::load_or_create(Path::new(PROPERTIES_PATH)) {
+        Ok(properties) => properties,
+        Err(error) => {
+            eprintln!("Failed to read {}: {}", PROPERTIES_PATH, error);
+            std::process::exit(1);
+        },
+    };
+    println!("Loading world from {}", properties.world.display());
+    let mut simulation = Simulation::open(&properties.world);
+    let mut server = match Server::bind(properties.clone()) {
+        Ok(server) => server,
+        Err(error) => {
+            eprintln!("Failed to listen on port {}: {}", properties.port, error);
+            std::process::exit(1);
+        },
+    };
+    println!("Server '{}' listening on port {}, type 'stop' to save and quit or 'help' for more", properties.motd, properties.port);
+
+    // Closing stdin stops the server.
+    let console = spawn_stdin_reader();
+    let tick_duration = Duration::from_secs_f32(TICK_DELTA);
+    let mut next_tick = Instant::now();
+    'running: loop {
+        loop {
+            let line = match console.try_recv() {
+                Ok(line) => line,
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => break 'running,
+            };
+            match line.trim() {
+                "stop" => break 'running,
+                "list" => println!("Players: {}", server.players().join(", ")),
+                "help" => print_help(&server),
+                "" => {},
+                line => match line.strip_prefix("say ") {
+                    Some(text) => server.broadcast_chat(&format!("[Server] {}", text.trim())),
+                    // Commands work with or without the slash players type.
+                    None => println!("{}", server.run_command(line, &mut simulation)),
+                },
+            }
+        }
+
+        simulation.tick();
+        server.tick(&mut simulation);
+        next_tick += tick_duration;
+        let now = Instant::now();
+        if next_tick > now {
+            thread::sleep(next_tick - now);
+        } else {
+            // Fell behind, skip the missed ticks instead of running them back to back.
+            next_tick = now;
+        }
+    }
+
+    server.shutdown(&mut simulation);
+    println!("Saving world");
+    if let Err(error) = simulation.save() {
+        eprintln!("Failed to save world: {}", error);
+    }
+}
+
+fn print_help(server: &Server) {
+    println!("stop: saves the world and quits");
+    println!("list: lists the players");
+    println!("say <message>: sends a message to every player");
+    for command in server.commands().available(&CommandSender::console()) {
+        println!("{}: {}", command.usage().join(", "), command.description);
+    }
+}
@@ -18,7 +18,26 @@ impl Camera {
         Self::new(screen_width, screen_height, transform)
     }
 
+    /// Moves the camera to `eye`, looking along `direction`.
+    pub fn look_from(&mut self, eye: &Point3<f32>, direction: &Vector3<f32>) {
+        self.transform = Transform::from_isometry(Isometry3::look_at_rh(eye, &(eye + direction), &Vector3::y()));
+    }
+
+    pub fn position(&self) -> Point3<f32> {
+        self.transform.view().inverse() * Point3::origin()
+    }
+
+    pub fn model_view(&self, model: &Isometry3<f32>) -> Matrix4<f32> {
+        (self.transform.view() * model).to_homogeneous()
+    }
+
     pub fn mvp(&self, model: &Isometry3<f32>) -> Matrix4<f32> {
-        self.projection.as_matrix() * (self.transform.view() * model).to_homogeneous()
+        self.projection.as_matrix() * self.model_view(model)
+    }
+
+    /// View projection without the camera translation, for geometry that stays
+    /// centred on the camera such as the sky.
+    pub fn rotation_view_projection(&self) -> Matrix4<f32> {
+        self.projection.as_matrix() * self.transform.view().rotation.to_homogeneous()
     }
 }
\ No newline at end of file
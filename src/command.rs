@@ -0,0 +1,10 @@
+mod argument;
+pub use argument::{Argument, Arguments, Parameter, ParameterKind, AIR};
+
+mod builtin;
+
+mod registry;
+pub use registry::{Command, CommandContext, CommandError, CommandHandler, CommandRegistry, CommandSender, Permission};
+
+mod stdin;
+pub use stdin::spawn_stdin_reader;
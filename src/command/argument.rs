@@ -0,0 +1,268 @@
+use nalgebra::Vector3;
+
+use crate::{ecs::{player::GameMode, Entity}, world::{block::BlockId, time::{MIDNIGHT, NOON, SUNRISE, SUNSET}}};
+
+use super::CommandContext;
+
+/// Name that clears a voxel wherever a block is expected.
+pub const AIR: &str = "air";
+/// Largest coordinate accepted on any axis, so voxel positions and the distances
+/// between them fit comfortably in an `i32`.
+const MAX_COORDINATE: f32 = 1_000_000.0;
+/// Names accepted for a time of day, besides ticks.
+const TIMES_OF_DAY: [(&str, u64); 4] = [("sunrise", SUNRISE), ("noon", NOON), ("sunset", SUNSET), ("midnight", MIDNIGHT)];
+
+/// What kind of value a parameter takes, which decides how it is parsed and completed.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ParameterKind {
+    /// One of a fixed set of words, such as the `set` of `/time set`.
+    Literal(&'static [&'static str]),
+    Integer { min: i64, max: i64 },
+    /// Three coordinates, each absolute or relative to the sender with a leading `~`.
+    Position,
+    /// A block by its name, or `air`.
+    Block,
+    /// The name of a player in the world.
+    Player,
+    GameMode,
+    /// A time of day in ticks, or one of `sunrise`, `noon`, `sunset` and `midnight`.
+    TimeOfDay,
+}
+
+impl ParameterKind {
+    /// Number of words the parameter takes.
+    pub fn width(self) -> usize {
+        match self {
+            ParameterKind::Position => 3,
+            _ => 1,
+        }
+    }
+
+    /// Parses the words of one parameter, `width` of them.
+    pub fn parse(self, words: &[&str], context: &CommandContext) -> Result<Argument, String> {
+        let word = words[0];
+        match self {
+            ParameterKind::Literal(options) => options.iter()
+                .find(|option| **option == word)
+                .map(|option| Argument::Literal(option))
+                .ok_or_else(|| format!("Expected one of {}, got '{}'", options.join(", "), word)),
+            ParameterKind::Integer { min, max } => match word.parse::<i64>() {
+                Ok(value) if (min..=max).contains(&value) => Ok(Argument::Integer(value)),
+                Ok(value) => Err(format!("{} is not between {} and {}", value, min, max)),
+                Err(_) => Err(format!("'{}' is not a whole number", word)),
+            },
+            ParameterKind::Position => {
+                let origin = context.sender_position();
+                let mut position = Vector3::zeros();
+                for (axis, word) in words.iter().enumerate() {
+                    position[axis] = match word.strip_prefix('~') {
+                        Some(offset) => {
+                            let origin = origin.ok_or("Relative coordinates need a sender with a position")?;
+                            let offset = if offset.is_empty() { 0.0 } else { parse_coordinate(offset)? };
+                            origin[axis] + offset
+                        },
+                        None => parse_coordinate(word)?,
+                    };
+                    if position[axis].abs() > MAX_COORDINATE {
+                        return Err(format!("{} is outside the world, coordinates go up to {}", position[axis], MAX_COORDINATE));
+                    }
+                }
+                Ok(Argument::Position(position))
+            },
+            ParameterKind::Block => {
+                if word == AIR {
+                    return Ok(Argument::Block(None));
+                }
+                context.simulation.world.registry().id(word)
+                    .map(|block| Argument::Block(Some(block)))
+                    .ok_or_else(|| format!("Unknown block '{}'", word))
+            },
+            ParameterKind::Player => context.find_player(word)
+                .map(Argument::Player)
+                .ok_or_else(|| format!("No player called '{}'", word)),
+            ParameterKind::GameMode => GameMode::parse(word)
+                .map(Argument::GameMode)
+                .ok_or_else(|| format!("Unknown game mode '{}'", word)),
+            ParameterKind::TimeOfDay => TIMES_OF_DAY.iter()
+                .find(|(name, _)| *name == word)
+                .map(|(_, time)| *time)
+                .or_else(|| word.parse().ok())
+                .map(Argument::TimeOfDay)
+                .ok_or_else(|| format!("'{}' is not a time of day", word)),
+        }
+    }
+
+    /// Words that fit the parameter at its `index`th word, for completion.
+    pub fn suggestions(self, index: usize, context: &CommandContext) -> Vec<String> {
+        match self {
+            ParameterKind::Literal(options) => options.iter().map(|option| option.to_string()).collect(),
+            ParameterKind::Integer { .. } => Vec::new(),
+            ParameterKind::Position => {
+                // Suggest the targeted block first, like typing its coordinates.
+                let target = context.sender_target().map(|target| target[index].to_string());
+                target.into_iter().chain(["~".to_owned()]).collect()
+            },
+            ParameterKind::Block => {
+                let registry = context.simulation.world.registry();
+                [AIR.to_owned()].into_iter().chain(registry.iter().map(|(_, block)| block.name.clone())).collect()
+            },
+            ParameterKind::Player => context.player_names(),
+            ParameterKind::GameMode => GameMode::ALL.iter().map(|mode| mode.name().to_owned()).collect(),
+            ParameterKind::TimeOfDay => TIMES_OF_DAY.iter().map(|(name, _)| name.to_string()).collect(),
+        }
+    }
+}
+
+fn parse_coordinate(word: &str) -> Result<f32, String> {
+    word.parse::<f32>().ok().filter(|value| value.is_finite()).ok_or_else(|| format!("'{}' is not a coordinate", word))
+}
+
+/// A named parameter of a command.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Parameter {
+    pub name: &'static str,
+    pub kind: ParameterKind,
+}
+
+impl Parameter {
+    pub const fn new(name: &'static str, kind: ParameterKind) -> Self {
+        Self { name, kind }
+    }
+}
+
+/// A parsed value of a parameter.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Argument {
+    Literal(&'static str),
+    Integer(i64),
+    Position(Vector3<f32>),
+    /// `None` for air.
+    Block(Option<BlockId>),
+    Player(Entity),
+    GameMode(GameMode),
+    TimeOfDay(u64),
+}
+
+/// The arguments a command was run with, by parameter name.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Arguments {
+    values: Vec<(&'static str, Argument)>,
+}
+
+impl Arguments {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn insert(&mut self, name: &'static str, argument: Argument) {
+        self.values.push((name, argument));
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Argument> {
+        self.values.iter().find(|(other, _)| *other == name).map(|(_, argument)| argument)
+    }
+
+    pub fn literal(&self, name: &str) -> Option<&'static str> {
+        match self.get(name)? {
+            Argument::Literal(word) => Some(word),
+            _ => None,
+        }
+    }
+
+    pub fn integer(&self, name: &str) -> Option<i64> {
+        match self.get(name)? {
+            Argument::Integer(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    pub fn position(&self, name: &str) -> Option<Vector3<f32>> {
+        match self.get(name)? {
+            Argument::Position(position) => Some(*position),
+            _ => None,
+        }
+    }
+
+    /// The voxel a position argument falls into.
+    pub fn voxel_position(&self, name: &str) -> Option<Vector3<i32>> {
+        self.position(name).map(|position| position.map(|c| c.floor() as i32))
+    }
+
+    pub fn block(&self, name: &str) -> Option<Option<BlockId>> {
+        match self.get(name)? {
+            Argument::Block(block) => Some(*block),
+            _ => None,
+        }
+    }
+
+    pub fn player(&self, name: &str) -> Option<Entity> {
+        match self.get(name)? {
+            Argument::Player(player) => Some(*player),
+            _ => None,
+        }
+    }
+
+    pub fn game_mode(&self, name: &str) -> Option<GameMode> {
+        match self.get(name)? {
+            Argument::GameMode(mode) => Some(*mode),
+            _ => None,
+        }
+    }
+
+    pub fn time_of_day(&self, name: &str) -> Option<u64> {
+        match self.get(name)? {
+            Argument::TimeOfDay(time) => Some(*time),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{command::{CommandSender, Permission}, ecs::{components::{spawn_player, Name}, player::PlayerData}, simulation::Simulation};
+
+    use super::*;
+
+    fn parse(kind: ParameterKind, line: &str, simulation: &mut Simulation, sender: &CommandSender) -> Result<Argument, String> {
+        let words: Vec<&str> = line.split_whitespace().collect();
+        kind.parse(&words, &CommandContext { simulation, sender })
+    }
+
+    #[test]
+    fn relative_coordinates_start_from_the_sender() {
+        let mut simulation = Simulation::replica();
+        let player = spawn_player(&mut simulation.entities, Vector3::new(1.0, 2.0, 3.0), PlayerData::new());
+        let sender = CommandSender::player("Alex", player, Permission::Operator);
+
+        assert_eq!(parse(ParameterKind::Position, "~ ~5 ~-1.5", &mut simulation, &sender), Ok(Argument::Position(Vector3::new(1.0, 7.0, 1.5))));
+        assert_eq!(parse(ParameterKind::Position, "10 ~ -4", &mut simulation, &sender), Ok(Argument::Position(Vector3::new(10.0, 2.0, -4.0))));
+        assert!(parse(ParameterKind::Position, "~x 0 0", &mut simulation, &sender).is_err());
+        assert!(parse(ParameterKind::Position, "0 2000000 0", &mut simulation, &sender).is_err());
+    }
+
+    #[test]
+    fn relative_coordinates_need_a_sender_with_a_position() {
+        let mut simulation = Simulation::replica();
+        let console = CommandSender::console();
+        assert_eq!(parse(ParameterKind::Position, "1 2 3", &mut simulation, &console), Ok(Argument::Position(Vector3::new(1.0, 2.0, 3.0))));
+        assert_eq!(
+            parse(ParameterKind::Position, "1 ~ 3", &mut simulation, &console),
+            Err("Relative coordinates need a sender with a position".to_owned()),
+        );
+    }
+
+    #[test]
+    fn parses_blocks_and_players_by_name() {
+        let mut simulation = Simulation::replica();
+        let player = spawn_player(&mut simulation.entities, Vector3::zeros(), PlayerData::new());
+        simulation.entities.insert(player, Name("Alex".to_owned()));
+        let console = CommandSender::console();
+        let dirt = simulation.world.registry().id("dirt").unwrap();
+
+        assert_eq!(parse(ParameterKind::Block, "dirt", &mut simulation, &console), Ok(Argument::Block(Some(dirt))));
+        assert_eq!(parse(ParameterKind::Block, AIR, &mut simulation, &console), Ok(Argument::Block(None)));
+        assert!(parse(ParameterKind::Block, "cheese", &mut simulation, &console).is_err());
+        assert_eq!(parse(ParameterKind::Player, "alex", &mut simulation, &console), Ok(Argument::Player(player)));
+        assert!(parse(ParameterKind::Player, "Steve", &mut simulation, &console).is_err());
+    }
+}
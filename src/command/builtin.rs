@@ -0,0 +1,134 @@
+use nalgebra::Vector3;
+
+use crate::{ecs::player, physics::PhysicsBody, world::{time::TICKS_PER_DAY, voxel::Voxel}};
+
+use super::{Arguments, Command, CommandContext, CommandError, CommandRegistry, Parameter, ParameterKind, Permission};
+
+/// Most voxels one `/fill` may change.
+const MAX_FILL_VOLUME: i64 = 32768;
+
+const DESTINATION: Parameter = Parameter::new("destination", ParameterKind::Position);
+const TARGET: Parameter = Parameter::new("target", ParameterKind::Player);
+const POSITION: Parameter = Parameter::new("position", ParameterKind::Position);
+const BLOCK: Parameter = Parameter::new("block", ParameterKind::Block);
+
+pub fn register(registry: &mut CommandRegistry) {
+    registry.register(Command::new("tp", "Teleports a player to a position or to another player", teleport)
+        .with_permission(Permission::Operator)
+        .with_form(&[DESTINATION])
+        .with_form(&[Parameter::new("player", ParameterKind::Player)])
+        .with_form(&[TARGET, DESTINATION])
+        .with_form(&[TARGET, Parameter::new("player", ParameterKind::Player)]));
+    registry.register(Command::new("setblock", "Replaces one block", set_block)
+        .with_permission(Permission::Operator)
+        .with_form(&[POSITION, BLOCK]));
+    registry.register(Command::new("fill", "Replaces every block in a box", fill)
+        .with_permission(Permission::Operator)
+        .with_form(&[Parameter::new("from", ParameterKind::Position), Parameter::new("to", ParameterKind::Position), BLOCK]));
+    registry.register(Command::new("time", "Changes the time of day", time)
+        .with_permission(Permission::Operator)
+        .with_form(&[Parameter::new("action", ParameterKind::Literal(&["set"])), Parameter::new("time", ParameterKind::TimeOfDay)])
+        .with_form(&[Parameter::new("action", ParameterKind::Literal(&["add"])), Parameter::new("ticks", ParameterKind::Integer { min: 0, max: TICKS_PER_DAY as i64 })])
+        .with_form(&[Parameter::new("action", ParameterKind::Literal(&["pause", "resume"]))]));
+    registry.register(Command::new("gamemode", "Changes the game mode of a player", game_mode)
+        .with_permission(Permission::Operator)
+        .with_form(&[Parameter::new("mode", ParameterKind::GameMode)])
+        .with_form(&[Parameter::new("mode", ParameterKind::GameMode), TARGET]));
+    registry.register(Command::new("seed", "Shows the seed of the world", seed)
+        .with_permission(Permission::Operator));
+}
+
+fn teleport(context: &mut CommandContext, arguments: &Arguments) -> Result<String, CommandError> {
+    let target = arguments.player("target").or(context.sender.player)
+        .ok_or_else(|| CommandError::Failed("Only players can teleport themselves, name a target".to_owned()))?;
+    let destination = match arguments.player("player") {
+        Some(player) => context.simulation.entities.get::<PhysicsBody>(player).map(|body| body.position)
+            .ok_or_else(|| CommandError::Failed(format!("{} has no position", context.player_name(player))))?,
+        None => arguments.position("destination").unwrap(),
+    };
+    let body = context.simulation.entities.get_mut::<PhysicsBody>(target)
+        .ok_or_else(|| CommandError::Failed("The target cannot move".to_owned()))?;
+    body.position = destination;
+    body.velocity = Vector3::zeros();
+    body.fall_distance = 0.0;
+    Ok(format!("Teleported {} to {:.1}, {:.1}, {:.1}", context.player_name(target), destination.x, destination.y, destination.z))
+}
+
+fn set_block(context: &mut CommandContext, arguments: &Arguments) -> Result<String, CommandError> {
+    let position = arguments.voxel_position("position").unwrap();
+    let voxel = arguments.block("block").unwrap().map(Voxel::new);
+    match context.simulation.world.set_voxel(position, voxel) {
+        Some(_) => Ok(format!("Changed the block at {}, {}, {}", position.x, position.y, position.z)),
+        None => Err(CommandError::Failed(format!("{}, {}, {} is not loaded", position.x, position.y, position.z))),
+    }
+}
+
+fn fill(context: &mut CommandContext, arguments: &Arguments) -> Result<String, CommandError> {
+    let (from, to) = (arguments.voxel_position("from").unwrap(), arguments.voxel_position("to").unwrap());
+    let (min, max) = (from.inf(&to), from.sup(&to));
+    let volume = (0..3).map(|axis| max[axis] as i64 - min[axis] as i64 + 1).fold(1, i64::saturating_mul);
+    if volume > MAX_FILL_VOLUME {
+        return Err(CommandError::Failed(format!("{} blocks are too many, at most {} can be filled", volume, MAX_FILL_VOLUME)));
+    }
+    let voxel = arguments.block("block").unwrap().map(Voxel::new);
+    let mut changed = 0;
+    for y in min.y..=max.y {
+        for z in min.z..=max.z {
+            for x in min.x..=max.x {
+                let position = Vector3::new(x, y, z);
+                if context.simulation.world.set_voxel(position, voxel).is_some_and(|previous| previous != voxel) {
+                    changed += 1;
+                }
+            }
+        }
+    }
+    Ok(format!("Changed {} blocks", changed))
+}
+
+fn time(context: &mut CommandContext, arguments: &Arguments) -> Result<String, CommandError> {
+    let clock = &mut context.simulation.clock;
+    match arguments.literal("action") {
+        Some("set") => clock.set_time_of_day(arguments.time_of_day("time").unwrap()),
+        Some("pause") => clock.set_paused(true),
+        Some("resume") => clock.set_paused(false),
+        _ => clock.set_ticks(clock.ticks() + arguments.integer("ticks").unwrap() as u64),
+    }
+    let state = if clock.is_paused() { "paused" } else { "running" };
+    Ok(format!("The time is now {}, {}", clock.time_of_day(), state))
+}
+
+fn game_mode(context: &mut CommandContext, arguments: &Arguments) -> Result<String, CommandError> {
+    let target = arguments.player("target").or(context.sender.player)
+        .ok_or_else(|| CommandError::Failed("Only players have a game mode, name a target".to_owned()))?;
+    let mode = arguments.game_mode("mode").unwrap();
+    player::set_game_mode(&mut context.simulation.entities, target, mode);
+    Ok(format!("Set the game mode of {} to {}", context.player_name(target), mode))
+}
+
+fn seed(context: &mut CommandContext, _: &Arguments) -> Result<String, CommandError> {
+    Ok(format!("Seed: {}", context.simulation.metadata.seed))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{command::CommandSender, simulation::Simulation, world::chunk::{Chunk, CHUNK_VOLUME}};
+
+    use super::*;
+
+    fn execute(simulation: &mut Simulation, line: &str) -> Result<String, CommandError> {
+        CommandRegistry::with_defaults().execute(line, &mut CommandContext { simulation, sender: &CommandSender::console() })
+    }
+
+    #[test]
+    fn fill_changes_loaded_blocks_and_rejects_huge_boxes() {
+        let mut simulation = Simulation::replica();
+        simulation.world.insert_chunk(Vector3::zeros(), Chunk::new(vec![None; CHUNK_VOLUME]));
+        assert_eq!(execute(&mut simulation, "/fill 0 0 0 1 1 1 cobblestone"), Ok("Changed 8 blocks".to_owned()));
+        assert_eq!(execute(&mut simulation, "/fill 1 1 1 0 0 0 cobblestone"), Ok("Changed 0 blocks".to_owned()));
+
+        let huge = execute(&mut simulation, "/fill -1000000 -1000000 -1000000 1000000 1000000 1000000 air");
+        assert!(matches!(huge, Err(CommandError::Failed(message)) if message.contains("too many")));
+        let outside = execute(&mut simulation, "/fill 0 0 0 3000000000 0 0 air");
+        assert!(matches!(outside, Err(CommandError::Syntax { message, .. }) if message.contains("outside the world")));
+    }
+}
@@ -0,0 +1,306 @@
+use std::fmt;
+
+use nalgebra::Vector3;
+
+use crate::{ecs::{components::Name, player::Target, Entity}, physics::PhysicsBody, simulation::Simulation};
+
+use super::{builtin, Arguments, Parameter};
+
+/// What a sender is allowed to do. Every level may do what the levels before it may.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Permission {
+    Player,
+    /// Players trusted with changing the world and other players.
+    Operator,
+    /// The server console, or the owner of a local game.
+    Console,
+}
+
+/// Who runs a command.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CommandSender {
+    pub name: String,
+    pub permission: Permission,
+    /// The sender's player entity. Relative coordinates start from it, and commands that
+    /// act on a player default to it.
+    pub player: Option<Entity>,
+}
+
+impl CommandSender {
+    /// The console of a dedicated server, which has no player.
+    pub fn console() -> Self {
+        Self { name: "Server".to_owned(), permission: Permission::Console, player: None }
+    }
+
+    pub fn player(name: &str, player: Entity, permission: Permission) -> Self {
+        Self { name: name.to_owned(), permission, player: Some(player) }
+    }
+}
+
+/// Everything a command may look at and change.
+pub struct CommandContext<'a> {
+    pub simulation: &'a mut Simulation,
+    pub sender: &'a CommandSender,
+}
+
+impl CommandContext<'_> {
+    /// Where the sending player stands, the origin of relative coordinates.
+    pub fn sender_position(&self) -> Option<Vector3<f32>> {
+        let player = self.sender.player?;
+        self.simulation.entities.get::<PhysicsBody>(player).map(|body| body.position)
+    }
+
+    /// The block the sending player is looking at.
+    pub fn sender_target(&self) -> Option<Vector3<i32>> {
+        let player = self.sender.player?;
+        self.simulation.entities.get::<Target>(player)?.hit.map(|hit| hit.position)
+    }
+
+    /// The player entity called `name`, ignoring case.
+    pub fn find_player(&self, name: &str) -> Option<Entity> {
+        self.simulation.entities.iter::<Name>()
+            .find(|(_, other)| other.0.eq_ignore_ascii_case(name))
+            .map(|(entity, _)| entity)
+    }
+
+    pub fn player_names(&self) -> Vec<String> {
+        let mut names: Vec<String> = self.simulation.entities.iter::<Name>().map(|(_, name)| name.0.clone()).collect();
+        names.sort();
+        names
+    }
+
+    pub fn player_name(&self, player: Entity) -> String {
+        self.simulation.entities.get::<Name>(player).map_or_else(|| "?".to_owned(), |name| name.0.clone())
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum CommandError {
+    Unknown(String),
+    NotPermitted(String),
+    /// The arguments fit none of the command's forms.
+    Syntax { message: String, usage: Vec<String> },
+    /// The command was understood but could not be carried out.
+    Failed(String),
+}
+
+impl fmt::Display for CommandError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CommandError::Unknown(name) => write!(f, "Unknown command '{}'", name),
+            CommandError::NotPermitted(name) => write!(f, "You are not allowed to use /{}", name),
+            CommandError::Syntax { message, usage } => write!(f, "{}. Usage: {}", message, usage.join(" or ")),
+            CommandError::Failed(message) => write!(f, "{}", message),
+        }
+    }
+}
+
+/// Runs a command with parsed arguments and returns the feedback for the sender.
+pub type CommandHandler = fn(&mut CommandContext, &Arguments) -> Result<String, CommandError>;
+
+/// A command and the forms its arguments may take.
+pub struct Command {
+    pub name: &'static str,
+    pub description: &'static str,
+    pub permission: Permission,
+    /// Each form is a list of parameters. The first form that parses is used.
+    pub forms: Vec<Vec<Parameter>>,
+    pub handler: CommandHandler,
+}
+
+impl Command {
+    /// A command anyone may run, without arguments until forms are added.
+    pub fn new(name: &'static str, description: &'static str, handler: CommandHandler) -> Self {
+        Self { name, description, permission: Permission::Player, forms: Vec::new(), handler }
+    }
+
+    pub fn with_permission(mut self, permission: Permission) -> Self {
+        self.permission = permission;
+        self
+    }
+
+    pub fn with_form(mut self, parameters: &[Parameter]) -> Self {
+        self.forms.push(parameters.to_vec());
+        self
+    }
+
+    /// One line per form, such as `/setblock <position> <block>`.
+    pub fn usage(&self) -> Vec<String> {
+        if self.forms.is_empty() {
+            return vec![format!("/{}", self.name)];
+        }
+        self.forms.iter().map(|form| {
+            let parameters: String = form.iter().map(|parameter| format!(" <{}>", parameter.name)).collect();
+            format!("/{}{}", self.name, parameters)
+        }).collect()
+    }
+
+    /// The forms, with a command without arguments having a single empty form.
+    fn forms(&self) -> Vec<&[Parameter]> {
+        if self.forms.is_empty() {
+            vec![&[]]
+        } else {
+            self.forms.iter().map(Vec::as_slice).collect()
+        }
+    }
+}
+
+/// The commands that can be run, by name.
+pub struct CommandRegistry {
+    commands: Vec<Command>,
+}
+
+impl CommandRegistry {
+    pub fn new() -> Self {
+        Self { commands: Vec::new() }
+    }
+
+    /// The built-in commands: `/tp`, `/setblock`, `/fill`, `/time`, `/gamemode`
+    /// and `/seed`.
+    pub fn with_defaults() -> Self {
+        let mut registry = Self::new();
+        builtin::register(&mut registry);
+        registry
+    }
+
+    /// Adds a command, replacing any command with the same name.
+    pub fn register(&mut self, command: Command) {
+        self.commands.retain(|other| other.name != command.name);
+        self.commands.push(command);
+        self.commands.sort_by_key(|command| command.name);
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Command> {
+        self.commands.iter().find(|command| command.name == name)
+    }
+
+    /// The commands `sender` may run.
+    pub fn available(&self, sender: &CommandSender) -> impl Iterator<Item = &Command> {
+        let permission = sender.permission;
+        self.commands.iter().filter(move |command| command.permission <= permission)
+    }
+
+    /// Runs a command line, with or without the leading slash.
+    pub fn execute(&self, line: &str, context: &mut CommandContext) -> Result<String, CommandError> {
+        let words: Vec<&str> = line.trim().trim_start_matches('/').split_whitespace().collect();
+        let Some((name, words)) = words.split_first() else {
+            return Err(CommandError::Unknown(String::new()));
+        };
+        let command = self.get(name).ok_or_else(|| CommandError::Unknown(name.to_string()))?;
+        if command.permission > context.sender.permission {
+            return Err(CommandError::NotPermitted(name.to_string()));
+        }
+
+        let mut error = None;
+        for form in command.forms() {
+            if form.iter().map(|parameter| parameter.kind.width()).sum::<usize>() != words.len() {
+                continue;
+            }
+            match parse_form(form, words, context) {
+                Ok(arguments) => return (command.handler)(context, &arguments),
+                Err(message) => {
+                    error.get_or_insert(message);
+                },
+            }
+        }
+        let message = error.unwrap_or_else(|| format!("Wrong number of arguments for /{}", command.name));
+        Err(CommandError::Syntax { message, usage: command.usage() })
+    }
+
+    /// Candidates for the last word of a partly typed command line, which is empty when
+    /// the line ends with a space.
+    pub fn complete(&self, line: &str, context: &CommandContext) -> Vec<String> {
+        let line = line.trim_start().trim_start_matches('/');
+        let mut words: Vec<&str> = line.split_whitespace().collect();
+        if line.is_empty() || line.ends_with(char::is_whitespace) {
+            words.push("");
+        }
+        let (current, words) = words.split_last().unwrap();
+
+        let mut candidates: Vec<String> = match words.split_first() {
+            None => self.available(context.sender).map(|command| command.name.to_owned()).collect(),
+            Some((name, done)) => match self.get(name).filter(|command| command.permission <= context.sender.permission) {
+                Some(command) => command.forms().into_iter()
+                    .flat_map(|form| suggest_in_form(form, done, context))
+                    .collect(),
+                None => Vec::new(),
+            },
+        };
+        candidates.retain(|candidate| candidate.starts_with(current));
+        candidates.sort();
+        candidates.dedup();
+        candidates
+    }
+}
+
+impl Default for CommandRegistry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn parse_form(form: &[Parameter], words: &[&str], context: &CommandContext) -> Result<Arguments, String> {
+    let mut arguments = Arguments::new();
+    let mut rest = words;
+    for parameter in form {
+        let (own, after) = rest.split_at(parameter.kind.width());
+        arguments.insert(parameter.name, parameter.kind.parse(own, context)?);
+        rest = after;
+    }
+    Ok(arguments)
+}
+
+/// Suggestions for the word after `done` in one form, if the words before it parse.
+fn suggest_in_form(form: &[Parameter], done: &[&str], context: &CommandContext) -> Vec<String> {
+    let mut rest = done;
+    for parameter in form {
+        let width = parameter.kind.width();
+        if rest.len() < width {
+            return parameter.kind.suggestions(rest.len(), context);
+        }
+        let (own, after) = rest.split_at(width);
+        if parameter.kind.parse(own, context).is_err() {
+            return Vec::new();
+        }
+        rest = after;
+    }
+    Vec::new()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ecs::{components::spawn_player, player::PlayerData};
+
+    use super::*;
+
+    #[test]
+    fn completes_command_names_and_arguments() {
+        let registry = CommandRegistry::with_defaults();
+        let mut simulation = Simulation::replica();
+        let player = spawn_player(&mut simulation.entities, Vector3::zeros(), PlayerData::new());
+        simulation.entities.insert(player, Name("Alex".to_owned()));
+        let console = CommandSender::console();
+        let context = CommandContext { simulation: &mut simulation, sender: &console };
+
+        assert_eq!(registry.complete("/t", &context), ["time", "tp"]);
+        assert_eq!(registry.complete("time ", &context), ["add", "pause", "resume", "set"]);
+        assert_eq!(registry.complete("time set n", &context), ["noon"]);
+        assert_eq!(registry.complete("gamemode creative A", &context), ["Alex"]);
+        assert!(registry.complete("setblock 0 0 0 di", &context).contains(&"dirt".to_owned()));
+        assert!(registry.complete("nothing ", &context).is_empty());
+    }
+
+    #[test]
+    fn players_may_not_run_operator_commands() {
+        let registry = CommandRegistry::with_defaults();
+        let mut simulation = Simulation::replica();
+        let player = spawn_player(&mut simulation.entities, Vector3::zeros(), PlayerData::new());
+        let sender = CommandSender::player("Alex", player, Permission::Player);
+        let mut context = CommandContext { simulation: &mut simulation, sender: &sender };
+
+        assert_eq!(registry.execute("/seed", &mut context), Err(CommandError::NotPermitted("seed".to_owned())));
+        assert!(registry.complete("", &context).is_empty());
+        assert!(registry.complete("seed ", &context).is_empty());
+        assert_eq!(registry.execute("/nothing", &mut context), Err(CommandError::Unknown("nothing".to_owned())));
+    }
+}
@@ -0,0 +1,18 @@
+use std::{io::{self, BufRead}, sync::mpsc, thread};
+
+/// Reads lines from stdin on a separate thread, so a game loop never blocks on input.
+/// The receiver disconnects once stdin is closed.
+pub fn spawn_stdin_reader() -> mpsc::Receiver<String> {
+    let (sender, receiver) = mpsc::channel();
+    thread::spawn(move || {
+        for line in io::stdin().lock().lines() {
+            let Ok(line) = line else {
+                break;
+            };
+            if sender.send(line).is_err() {
+                return;
+            }
+        }
+    });
+    receiver
+}
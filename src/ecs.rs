@@ -0,0 +1,13 @@
+mod entity;
+pub use entity::{Entities, Entity};
+
+mod schedule;
+pub use schedule::{Schedule, Stage, System, SystemContext};
+
+pub mod components;
+
+pub mod health;
+
+pub mod player;
+
+pub mod systems;
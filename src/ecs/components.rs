@@ -0,0 +1,148 @@
+use nalgebra::{UnitQuaternion, Vector3};
+
+use crate::{item::{ItemId, ItemKind, ItemRegistry, ItemStack}, physics::PhysicsBody, transform::Transform, world::{block::BlockId, falling::{falling_body, FallingBlock}, pathfinding::PathSettings, voxel::Voxel}};
+
+use super::{health::{Breath, Health}, player::{self, BreakProgress, PlayerData, PlayerInput, Target}, Entities, Entity};
+
+/// Slots in a player's inventory.
+pub const PLAYER_INVENTORY_SIZE: usize = 36;
+pub const PLAYER_HEALTH: u32 = 20;
+/// Seconds a player can hold their breath.
+pub const PLAYER_BREATH: f32 = 15.0;
+pub const MOB_HEALTH: u32 = 10;
+
+/// Marks the entity controlled by a player.
+pub struct Player;
+
+/// The name a player is known by, for commands and chat.
+pub struct Name(pub String);
+
+/// Marks a creature that is controlled by AI systems.
+pub struct Mob;
+
+/// AI that walks in a random horizontal direction and picks a new one every few seconds.
+pub struct Wander {
+    pub speed: f32,
+    pub direction: Vector3<f32>,
+    /// Seconds until the next direction is picked.
+    pub timer: f32,
+}
+
+impl Wander {
+    pub fn new(speed: f32) -> Self {
+        Self { speed, direction: Vector3::zeros(), timer: 0.0 }
+    }
+}
+
+/// AI that walks to a target voxel along a path from the pathfinder. Wandering stops
+/// while it has a target.
+pub struct PathFollower {
+    pub speed: f32,
+    pub settings: PathSettings,
+    target: Option<Vector3<i32>>,
+    /// Waypoints still to be reached, the next one first.
+    path: Vec<Vector3<i32>>,
+}
+
+impl PathFollower {
+    pub fn new(speed: f32) -> Self {
+        Self { speed, settings: PathSettings::default(), target: None, path: Vec::new() }
+    }
+
+    pub fn target(&self) -> Option<Vector3<i32>> {
+        self.target
+    }
+
+    /// Sets the voxel to walk to. The path is found on the next AI update.
+    pub fn set_target(&mut self, target: Option<Vector3<i32>>) {
+        self.target = target;
+        self.path.clear();
+    }
+
+    pub fn path(&self) -> &[Vector3<i32>] {
+        &self.path
+    }
+
+    pub(super) fn set_path(&mut self, path: Vec<Vector3<i32>>) {
+        self.path = path;
+    }
+
+    pub(super) fn next_waypoint(&self) -> Option<Vector3<i32>> {
+        self.path.first().copied()
+    }
+
+    pub(super) fn reach_waypoint(&mut self) {
+        self.path.remove(0);
+        if self.path.is_empty() {
+            self.target = None;
+        }
+    }
+}
+
+/// An item stack lying in the world, picked up by players who walk over it.
+pub struct DroppedItem {
+    pub stack: ItemStack,
+}
+
+/// What is drawn at the entity's transform.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Model {
+    Block(BlockId),
+    Item(ItemId),
+}
+
+impl Model {
+    /// Block items look like their block, other items get their own model.
+    pub fn for_item(item: ItemId, registry: &ItemRegistry) -> Self {
+        match registry.get(item).kind {
+            ItemKind::Block(block) => Model::Block(block),
+            _ => Model::Item(item),
+        }
+    }
+}
+
+pub fn spawn_player(entities: &mut Entities, position: Vector3<f32>, data: PlayerData) -> Entity {
+    let entity = entities.spawn();
+    entities.insert(entity, Player);
+    entities.insert(entity, data.inventory);
+    entities.insert(entity, PlayerInput::new());
+    entities.insert(entity, Target::default());
+    entities.insert(entity, BreakProgress::default());
+    entities.insert(entity, Health::new(PLAYER_HEALTH));
+    entities.insert(entity, Breath::new(PLAYER_BREATH));
+    entities.insert(entity, Transform::new(position, UnitQuaternion::identity()));
+    entities.insert(entity, PhysicsBody::new(position, Vector3::new(0.6, 1.8, 0.6)));
+    player::set_game_mode(entities, entity, data.game_mode);
+    entity
+}
+
+pub fn spawn_mob(entities: &mut Entities, position: Vector3<f32>, model: Model) -> Entity {
+    let entity = entities.spawn();
+    entities.insert(entity, Mob);
+    entities.insert(entity, Wander::new(1.5));
+    entities.insert(entity, PathFollower::new(3.0));
+    entities.insert(entity, Health::new(MOB_HEALTH));
+    entities.insert(entity, model);
+    entities.insert(entity, Transform::new(position, UnitQuaternion::identity()));
+    entities.insert(entity, PhysicsBody::new(position, Vector3::new(0.9, 0.9, 0.9)));
+    entity
+}
+
+pub fn spawn_falling_block(entities: &mut Entities, voxel: Voxel, position: Vector3<i32>) -> Entity {
+    let body = falling_body(position);
+    let entity = entities.spawn();
+    entities.insert(entity, FallingBlock { voxel });
+    entities.insert(entity, Model::Block(voxel.block));
+    entities.insert(entity, Transform::new(body.position, UnitQuaternion::identity()));
+    entities.insert(entity, body);
+    entity
+}
+
+pub fn spawn_dropped_item(entities: &mut Entities, stack: ItemStack, position: Vector3<f32>, registry: &ItemRegistry) -> Entity {
+    let entity = entities.spawn();
+    entities.insert(entity, DroppedItem { stack });
+    entities.insert(entity, Model::for_item(stack.item, registry));
+    entities.insert(entity, Transform::new(position, UnitQuaternion::identity()));
+    entities.insert(entity, PhysicsBody::new(position, Vector3::new(0.25, 0.25, 0.25)));
+    entity
+}
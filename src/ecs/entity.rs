@@ -0,0 +1,264 @@
+use std::{any::{Any, TypeId}, collections::HashMap};
+
+use crate::{physics::Aabb, transform::Transform};
+
+/// Handle to an entity. The generation makes handles to despawned entities invalid, even
+/// when their slot is reused.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Entity {
+    index: u32,
+    generation: u32,
+}
+
+impl Entity {
+    /// Index and generation packed into one number, for sending entities over the network.
+    pub fn to_bits(self) -> u64 {
+        (self.generation as u64) << 32 | self.index as u64
+    }
+}
+
+trait ComponentStorage {
+    fn remove_index(&mut self, index: usize);
+    fn as_any(&self) -> &dyn Any;
+    fn as_any_mut(&mut self) -> &mut dyn Any;
+}
+
+struct Storage<T> {
+    components: Vec<Option<T>>,
+}
+
+impl<T: 'static> ComponentStorage for Storage<T> {
+    fn remove_index(&mut self, index: usize) {
+        if let Some(component) = self.components.get_mut(index) {
+            *component = None;
+        }
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+/// Stores all entities and their components. Any `'static` type can be used as a component.
+pub struct Entities {
+    generations: Vec<u32>,
+    alive: Vec<bool>,
+    free: Vec<u32>,
+    storages: HashMap<TypeId, Box<dyn ComponentStorage>>,
+}
+
+impl Entities {
+    pub fn new() -> Self {
+        Self { generations: Vec::new(), alive: Vec::new(), free: Vec::new(), storages: HashMap::new() }
+    }
+
+    pub fn spawn(&mut self) -> Entity {
+        if let Some(index) = self.free.pop() {
+            self.alive[index as usize] = true;
+            Entity { index, generation: self.generations[index as usize] }
+        } else {
+            self.generations.push(0);
+            self.alive.push(true);
+            Entity { index: self.generations.len() as u32 - 1, generation: 0 }
+        }
+    }
+
+    /// Removes the entity and all of its components. Returns false if it was already gone.
+    pub fn despawn(&mut self, entity: Entity) -> bool {
+        if !self.is_alive(entity) {
+            return false;
+        }
+        let index = entity.index as usize;
+        for storage in self.storages.values_mut() {
+            storage.remove_index(index);
+        }
+        self.alive[index] = false;
+        self.generations[index] += 1;
+        self.free.push(entity.index);
+        true
+    }
+
+    pub fn is_alive(&self, entity: Entity) -> bool {
+        let index = entity.index as usize;
+        self.alive.get(index).is_some_and(|&alive| alive) && self.generations[index] == entity.generation
+    }
+
+    pub fn len(&self) -> usize {
+        self.alive.iter().filter(|&&alive| alive).count()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Adds a component to the entity, replacing any component of the same type.
+    pub fn insert<T: 'static>(&mut self, entity: Entity, component: T) {
+        if !self.is_alive(entity) {
+            return;
+        }
+        let storage = self.storage_mut::<T>();
+        let index = entity.index as usize;
+        if storage.components.len() <= index {
+            storage.components.resize_with(index + 1, || None);
+        }
+        storage.components[index] = Some(component);
+    }
+
+    pub fn remove<T: 'static>(&mut self, entity: Entity) -> Option<T> {
+        if !self.is_alive(entity) {
+            return None;
+        }
+        self.storage_mut::<T>().components.get_mut(entity.index as usize)?.take()
+    }
+
+    pub fn get<T: 'static>(&self, entity: Entity) -> Option<&T> {
+        if !self.is_alive(entity) {
+            return None;
+        }
+        self.storage::<T>()?.components.get(entity.index as usize)?.as_ref()
+    }
+
+    pub fn get_mut<T: 'static>(&mut self, entity: Entity) -> Option<&mut T> {
+        if !self.is_alive(entity) {
+            return None;
+        }
+        self.storage_mut::<T>().components.get_mut(entity.index as usize)?.as_mut()
+    }
+
+    pub fn has<T: 'static>(&self, entity: Entity) -> bool {
+        self.get::<T>(entity).is_some()
+    }
+
+    /// Every entity with a component of type `T`, together with that component.
+    pub fn iter<T: 'static>(&self) -> impl Iterator<Item = (Entity, &T)> {
+        let generations = &self.generations;
+        self.storage::<T>()
+            .into_iter()
+            .flat_map(|storage| storage.components.iter().enumerate())
+            .filter_map(move |(index, component)| {
+                let entity = Entity { index: index as u32, generation: generations[index] };
+                component.as_ref().map(|component| (entity, component))
+            })
+    }
+
+    pub fn iter_mut<T: 'static>(&mut self) -> impl Iterator<Item = (Entity, &mut T)> {
+        let generations = &self.generations;
+        let storage = self.storages
+            .get_mut(&TypeId::of::<T>())
+            .and_then(|storage| storage.as_any_mut().downcast_mut::<Storage<T>>());
+        storage
+            .into_iter()
+            .flat_map(|storage| storage.components.iter_mut().enumerate())
+            .filter_map(move |(index, component)| {
+                let entity = Entity { index: index as u32, generation: generations[index] };
+                component.as_mut().map(|component| (entity, component))
+            })
+    }
+
+    /// Entities with a component of type `T`. Collected up front, so the entities can be
+    /// changed while going through them.
+    pub fn with<T: 'static>(&self) -> Vec<Entity> {
+        self.iter::<T>().map(|(entity, _)| entity).collect()
+    }
+
+    /// Entities with a `Transform` positioned inside `region`.
+    pub fn in_region(&self, region: &Aabb) -> Vec<Entity> {
+        self.iter::<Transform>()
+            .filter(|(_, transform)| {
+                let position = transform.position();
+                (0..3).all(|axis| position[axis] >= region.min[axis] && position[axis] <= region.max[axis])
+            })
+            .map(|(entity, _)| entity)
+            .collect()
+    }
+
+    fn storage<T: 'static>(&self) -> Option<&Storage<T>> {
+        self.storages.get(&TypeId::of::<T>())?.as_any().downcast_ref::<Storage<T>>()
+    }
+
+    fn storage_mut<T: 'static>(&mut self) -> &mut Storage<T> {
+        self.storages
+            .entry(TypeId::of::<T>())
+            .or_insert_with(|| Box::new(Storage::<T> { components: Vec::new() }))
+            .as_any_mut()
+            .downcast_mut::<Storage<T>>()
+            .unwrap()
+    }
+}
+
+impl Default for Entities {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::rc::Rc;
+
+    use nalgebra::{UnitQuaternion, Vector3};
+
+    use super::*;
+
+    #[test]
+    fn handles_to_despawned_entities_stay_invalid_when_the_slot_is_reused() {
+        let mut entities = Entities::new();
+        let old = entities.spawn();
+        entities.insert(old, 1u32);
+        assert!(entities.despawn(old));
+        assert!(!entities.despawn(old));
+
+        let new = entities.spawn();
+        assert_eq!(new.index, old.index);
+        assert_ne!(new, old);
+        entities.insert(new, 2u32);
+        entities.insert(old, 3u32);
+        assert!(!entities.is_alive(old));
+        assert_eq!(entities.get::<u32>(old), None);
+        assert_eq!(entities.remove::<u32>(old), None);
+        assert_eq!(entities.get::<u32>(new), Some(&2));
+        assert_eq!(entities.len(), 1);
+    }
+
+    #[test]
+    fn despawning_drops_the_components() {
+        let mut entities = Entities::new();
+        let shared = Rc::new(());
+        let entity = entities.spawn();
+        entities.insert(entity, Rc::clone(&shared));
+        entities.insert(entity, "name");
+        assert_eq!(Rc::strong_count(&shared), 2);
+
+        entities.despawn(entity);
+        assert_eq!(Rc::strong_count(&shared), 1);
+        let reused = entities.spawn();
+        assert!(!entities.has::<Rc<()>>(reused));
+        assert!(!entities.has::<&str>(reused));
+        assert_eq!(entities.iter::<Rc<()>>().count(), 0);
+    }
+
+    #[test]
+    fn finds_exactly_the_entities_inside_a_region() {
+        let mut entities = Entities::new();
+        let mut spawn_at = |x: f32, y: f32, z: f32| {
+            let entity = entities.spawn();
+            entities.insert(entity, Transform::new(Vector3::new(x, y, z), UnitQuaternion::identity()));
+            entity
+        };
+        let inside = spawn_at(1.0, 1.0, 1.0);
+        let on_edge = spawn_at(2.0, 0.0, 2.0);
+        spawn_at(2.5, 1.0, 1.0);
+        spawn_at(1.0, -0.1, 1.0);
+        let despawned = spawn_at(1.5, 1.5, 1.5);
+        entities.despawn(despawned);
+        let without_transform = entities.spawn();
+        entities.insert(without_transform, 0u8);
+
+        let region = Aabb::new(Vector3::zeros(), Vector3::new(2.0, 2.0, 2.0));
+        assert_eq!(entities.in_region(&region), [inside, on_edge]);
+    }
+}
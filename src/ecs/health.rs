@@ -0,0 +1,314 @@
+use nalgebra::Vector3;
+
+use crate::{item::Inventory, physics::PhysicsBody, transform::Transform, world::{Spawn, World}};
+
+use super::{components::Player, player::GameMode, Entities, Entity, SystemContext};
+
+/// Distance a body can fall without taking damage. Every block beyond it costs one point.
+pub const SAFE_FALL_DISTANCE: f32 = 3.0;
+/// Seconds after taking damage during which further damage is ignored.
+pub const INVULNERABILITY_TIME: f32 = 0.5;
+
+const DROWNING_DAMAGE: u32 = 2;
+/// Seconds between drowning damage once breath ran out.
+const DROWNING_INTERVAL: f32 = 1.0;
+/// Seconds of breath regained per second out of fluid.
+const BREATH_RECOVERY: f32 = 5.0;
+const LAVA_DAMAGE: u32 = 4;
+/// Speed an attacked entity is pushed away from its attacker with.
+const KNOCKBACK_SPEED: f32 = 6.0;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DamageSource {
+    Fall,
+    Drowning,
+    Lava,
+    Attack(Entity),
+}
+
+pub struct Health {
+    pub current: u32,
+    pub max: u32,
+    /// Seconds left before damage is taken again.
+    pub invulnerable: f32,
+    pub last_damage: Option<DamageSource>,
+}
+
+impl Health {
+    pub fn new(max: u32) -> Self {
+        Self { current: max, max, invulnerable: 0.0, last_damage: None }
+    }
+
+    pub fn is_dead(&self) -> bool {
+        self.current == 0
+    }
+}
+
+/// Seconds an entity can stay with its head in fluid before it starts drowning.
+pub struct Breath {
+    pub remaining: f32,
+    pub max: f32,
+}
+
+impl Breath {
+    pub fn new(max: f32) -> Self {
+        Self { remaining: max, max }
+    }
+}
+
+/// Deals damage to an entity, unless it is dead, was damaged too recently or is a player
+/// whose game mode takes no damage. Returns whether the damage was dealt.
+pub fn damage(entities: &mut Entities, entity: Entity, amount: u32, source: DamageSource) -> bool {
+    if entities.get::<GameMode>(entity).is_some_and(|mode| !mode.takes_damage()) {
+        return false;
+    }
+    let Some(health) = entities.get_mut::<Health>(entity) else {
+        return false;
+    };
+    if amount == 0 || health.is_dead() || health.invulnerable > 0.0 {
+        return false;
+    }
+    health.current = health.current.saturating_sub(amount);
+    health.invulnerable = INVULNERABILITY_TIME;
+    health.last_damage = Some(source);
+    true
+}
+
+/// Damages `target` on behalf of `attacker` and knocks it away from the attacker.
+pub fn attack(entities: &mut Entities, attacker: Entity, target: Entity, amount: u32) -> bool {
+    if !damage(entities, target, amount, DamageSource::Attack(attacker)) {
+        return false;
+    }
+    let from = entities.get::<PhysicsBody>(attacker).map(|body| body.position);
+    if let (Some(from), Some(body)) = (from, entities.get_mut::<PhysicsBody>(target)) {
+        let away = Vector3::new(body.position.x - from.x, 0.0, body.position.z - from.z);
+        body.velocity += away.try_normalize(f32::EPSILON).unwrap_or_else(Vector3::zeros) * KNOCKBACK_SPEED;
+        body.velocity.y = KNOCKBACK_SPEED / 2.0;
+    }
+    true
+}
+
+pub fn tick_invulnerability(context: &mut SystemContext) {
+    for (_, health) in context.entities.iter_mut::<Health>() {
+        health.invulnerable = (health.invulnerable - context.delta).max(0.0);
+    }
+}
+
+/// Damages entities that landed after falling further than `SAFE_FALL_DISTANCE`. Landing
+/// in fluid breaks the fall.
+pub fn fall_damage(context: &mut SystemContext) {
+    for entity in context.entities.with::<Health>() {
+        let Some(body) = context.entities.get::<PhysicsBody>(entity) else {
+            continue;
+        };
+        let Some(distance) = body.landed else {
+            continue;
+        };
+        if is_fluid(context.world, feet_voxel(body)) {
+            continue;
+        }
+        let amount = (distance - SAFE_FALL_DISTANCE).round().max(0.0) as u32;
+        damage(context.entities, entity, amount, DamageSource::Fall);
+    }
+}
+
+/// Drowns entities with their head in fluid once their breath runs out, and burns
+/// entities touching a burning block such as lava.
+pub fn environment_damage(context: &mut SystemContext) {
+    for entity in context.entities.with::<Health>() {
+        let Some(body) = context.entities.get::<PhysicsBody>(entity) else {
+            continue;
+        };
+        let head = (body.position + Vector3::y() * body.size.y * 0.9).map(|c| c.floor() as i32);
+        let head_in_fluid = is_fluid(context.world, head) && !burns(context.world, head);
+        let touching_burning = body.aabb().voxels().any(|voxel| burns(context.world, voxel));
+
+        if touching_burning {
+            damage(context.entities, entity, LAVA_DAMAGE, DamageSource::Lava);
+        }
+
+        let Some(breath) = context.entities.get_mut::<Breath>(entity) else {
+            continue;
+        };
+        if !head_in_fluid {
+            breath.remaining = (breath.remaining + BREATH_RECOVERY * context.delta).min(breath.max);
+            continue;
+        }
+        breath.remaining -= context.delta;
+        if breath.remaining <= 0.0 {
+            breath.remaining += DROWNING_INTERVAL;
+            // Drowning is paced by its interval, so it ignores invulnerability from other damage.
+            if let Some(health) = context.entities.get_mut::<Health>(entity) {
+                health.invulnerable = 0.0;
+            }
+            damage(context.entities, entity, DROWNING_DAMAGE, DamageSource::Drowning);
+        }
+    }
+}
+
+/// Respawns dead players at the world spawn point, dropping their inventory where they
+/// died. Other dead entities are removed.
+pub fn handle_deaths(context: &mut SystemContext) {
+    for entity in context.entities.with::<Health>() {
+        if !context.entities.get::<Health>(entity).is_some_and(Health::is_dead) {
+            continue;
+        }
+        if !context.entities.has::<Player>(entity) {
+            context.entities.despawn(entity);
+            continue;
+        }
+
+        let died_at = context.entities.get::<PhysicsBody>(entity).map(|body| body.position);
+        if let (Some(inventory), Some(position)) = (context.entities.get_mut::<Inventory>(entity), died_at) {
+            for slot in 0..inventory.size() {
+                if let Some(stack) = inventory.set(slot, None) {
+                    context.world.spawn(Spawn::DroppedItem { stack, position });
+                }
+            }
+        }
+
+        let spawn_point = context.world.spawn_point();
+        let health = context.entities.get_mut::<Health>(entity).unwrap();
+        *health = Health::new(health.max);
+        if let Some(breath) = context.entities.get_mut::<Breath>(entity) {
+            breath.remaining = breath.max;
+        }
+        if let Some(body) = context.entities.get_mut::<PhysicsBody>(entity) {
+            *body = PhysicsBody::new(spawn_point, body.size);
+        }
+        if let Some(transform) = context.entities.get_mut::<Transform>(entity) {
+            transform.set_position(spawn_point);
+        }
+    }
+}
+
+fn feet_voxel(body: &PhysicsBody) -> Vector3<i32> {
+    (body.position + Vector3::y() * 0.01).map(|c| c.floor() as i32)
+}
+
+fn is_fluid(world: &World, position: Vector3<i32>) -> bool {
+    world.block_type(position).is_some_and(|block| block.fluid.is_some())
+}
+
+fn burns(world: &World, position: Vector3<i32>) -> bool {
+    world.block_type(position).is_some_and(|block| block.burns)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{ecs::{components::{self, PLAYER_BREATH, PLAYER_HEALTH}, player::PlayerData, systems::step_bodies, Schedule, Stage}, item::{ItemRegistry, ItemStack}, simulation::TICK_DELTA, world::{block::BlockRegistry, chunk::{Chunk, CHUNK_SIZE_X, CHUNK_SIZE_Z, CHUNK_VOLUME}, time::TICKS_PER_SECOND, voxel::Voxel}};
+
+    use super::*;
+
+    /// Two chunks stacked on each other with a cobblestone floor at `y = 0`.
+    fn floor_world() -> World {
+        let registry = BlockRegistry::with_defaults();
+        let items = ItemRegistry::with_defaults(&registry);
+        let floor = Some(Voxel::new(registry.id("cobblestone").unwrap()));
+        let mut world = World::new(registry, items, 0);
+        for y in 0..2 {
+            world.insert_chunk(Vector3::new(0, y, 0), Chunk::new(vec![None; CHUNK_VOLUME]));
+        }
+        for x in 0..CHUNK_SIZE_X {
+            for z in 0..CHUNK_SIZE_Z {
+                world.set_voxel_without_updates(Vector3::new(x, 0, z), floor);
+            }
+        }
+        world
+    }
+
+    fn schedule() -> Schedule {
+        let mut schedule = Schedule::new();
+        schedule.add_system(Stage::Physics, step_bodies);
+        schedule.add_system(Stage::Physics, tick_invulnerability);
+        schedule.add_system(Stage::Physics, fall_damage);
+        schedule.add_system(Stage::Physics, environment_damage);
+        schedule.add_system(Stage::Physics, handle_deaths);
+        schedule
+    }
+
+    fn run(world: &mut World, entities: &mut Entities, seconds: f32) {
+        let schedule = schedule();
+        for _ in 0..(seconds * TICKS_PER_SECOND as f32).round() as usize {
+            schedule.run(&mut SystemContext { entities, world, delta: TICK_DELTA });
+        }
+    }
+
+    fn health(entities: &Entities, entity: Entity) -> u32 {
+        entities.get::<Health>(entity).unwrap().current
+    }
+
+    #[test]
+    fn falls_lose_a_point_per_block_beyond_the_safe_distance() {
+        for extra in [0, 1, 2, 4] {
+            let mut world = floor_world();
+            let mut entities = Entities::new();
+            let height = 1.0 + SAFE_FALL_DISTANCE + extra as f32;
+            let player = components::spawn_player(&mut entities, Vector3::new(4.5, height, 4.5), PlayerData::new());
+            run(&mut world, &mut entities, 3.0);
+
+            assert!(entities.get::<PhysicsBody>(player).unwrap().on_ground);
+            assert_eq!(health(&entities, player), PLAYER_HEALTH - extra, "fell {} blocks too far", extra);
+            let expected_source = (extra > 0).then_some(DamageSource::Fall);
+            assert_eq!(entities.get::<Health>(player).unwrap().last_damage, expected_source);
+        }
+    }
+
+    #[test]
+    fn drowns_once_breath_runs_out_and_recovers_breath_out_of_water() {
+        let mut world = floor_world();
+        let water = Some(Voxel::new(world.registry().id("water").unwrap()));
+        for y in 1..4 {
+            world.set_voxel_without_updates(Vector3::new(4, y, 4), water);
+        }
+        let mut entities = Entities::new();
+        let player = components::spawn_player(&mut entities, Vector3::new(4.5, 1.0, 4.5), PlayerData::new());
+
+        run(&mut world, &mut entities, PLAYER_BREATH - 1.0);
+        assert_eq!(health(&entities, player), PLAYER_HEALTH);
+        assert!(entities.get::<Breath>(player).unwrap().remaining < 1.5);
+
+        run(&mut world, &mut entities, 1.5);
+        assert_eq!(health(&entities, player), PLAYER_HEALTH - DROWNING_DAMAGE);
+        assert_eq!(entities.get::<Health>(player).unwrap().last_damage, Some(DamageSource::Drowning));
+        run(&mut world, &mut entities, DROWNING_INTERVAL);
+        assert_eq!(health(&entities, player), PLAYER_HEALTH - 2 * DROWNING_DAMAGE);
+
+        for y in 1..4 {
+            world.set_voxel_without_updates(Vector3::new(4, y, 4), None);
+        }
+        run(&mut world, &mut entities, PLAYER_BREATH / BREATH_RECOVERY);
+        assert_eq!(entities.get::<Breath>(player).unwrap().remaining, PLAYER_BREATH);
+        assert_eq!(health(&entities, player), PLAYER_HEALTH - 2 * DROWNING_DAMAGE);
+    }
+
+    #[test]
+    fn dead_players_respawn_at_the_spawn_point_and_drop_their_items() {
+        let mut world = floor_world();
+        let spawn_point = Vector3::new(2.5, 1.0, 6.5);
+        world.set_spawn_point(spawn_point);
+        let stick = world.items().id("stick").unwrap();
+        let mut entities = Entities::new();
+        let died_at = Vector3::new(5.5, 1.0, 5.5);
+        let player = components::spawn_player(&mut entities, died_at, PlayerData::new());
+        entities.get_mut::<Inventory>(player).unwrap().set(0, Some(ItemStack::new(stick, 3)));
+        let mob = components::spawn_mob(&mut entities, Vector3::new(1.5, 1.0, 1.5), components::Model::Block(world.registry().id("dirt").unwrap()));
+
+        assert!(damage(&mut entities, player, PLAYER_HEALTH, DamageSource::Lava));
+        assert!(damage(&mut entities, mob, 1000, DamageSource::Lava));
+        handle_deaths(&mut SystemContext { entities: &mut entities, world: &mut world, delta: TICK_DELTA });
+
+        assert_eq!(health(&entities, player), PLAYER_HEALTH);
+        assert_eq!(entities.get::<PhysicsBody>(player).unwrap().position, spawn_point);
+        assert_eq!(entities.get::<Transform>(player).unwrap().position(), spawn_point);
+        assert!(entities.get::<Inventory>(player).unwrap().is_empty());
+        assert!(!entities.has::<Health>(mob));
+        match world.take_spawns().as_slice() {
+            [Spawn::DroppedItem { stack, position }] => {
+                assert_eq!(*stack, ItemStack::new(stick, 3));
+                assert_eq!(*position, died_at);
+            },
+            spawns => panic!("expected one dropped item, got {}", spawns.len()),
+        }
+    }
+}
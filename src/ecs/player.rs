@@ -0,0 +1,563 @@
+use std::{collections::HashSet, fmt, io};
+
+use nalgebra::Vector3;
+
+use crate::{encoding::{invalid_data, Reader}, item::{breaking_time, decode_inventory, encode_inventory, Inventory, ItemKind, ItemRegistry}, physics::{self, Aabb, PhysicsBody, RaycastHit, JUMP_SPEED}};
+
+use super::{components::PLAYER_INVENTORY_SIZE, Entities, Entity, SystemContext};
+
+pub const WALK_SPEED: f32 = 4.3;
+pub const FLY_SPEED: f32 = 10.0;
+/// Height of a player's eyes above their feet.
+pub const EYE_HEIGHT: f32 = 1.62;
+/// Furthest distance a player can break and place blocks at.
+pub const REACH: f32 = 5.0;
+/// Seconds between blocks broken while holding the break action in creative.
+const CREATIVE_BREAK_COOLDOWN: f32 = 0.25;
+
+const PLAYER_FORMAT_VERSION: u8 = 1;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GameMode {
+    /// Timed breaking, health and placing from the inventory.
+    Survival,
+    /// Flight, instant breaking and unlimited blocks.
+    Creative,
+    /// Flies through blocks and cannot interact with the world.
+    Spectator,
+}
+
+impl GameMode {
+    pub const ALL: [GameMode; 3] = [GameMode::Survival, GameMode::Creative, GameMode::Spectator];
+
+    pub fn parse(value: &str) -> Option<Self> {
+        GameMode::ALL.into_iter().find(|mode| mode.name() == value)
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            GameMode::Survival => "survival",
+            GameMode::Creative => "creative",
+            GameMode::Spectator => "spectator",
+        }
+    }
+
+    /// The actions players in this mode can use.
+    pub fn actions(&self) -> &'static [PlayerAction] {
+        use PlayerAction::*;
+        match self {
+            GameMode::Survival => &[Forward, Back, Left, Right, Jump, Break, Place],
+            GameMode::Creative => &[Forward, Back, Left, Right, Jump, Descend, Break, Place],
+            GameMode::Spectator => &[Forward, Back, Left, Right, Jump, Descend],
+        }
+    }
+
+    pub fn allows(&self, action: PlayerAction) -> bool {
+        self.actions().contains(&action)
+    }
+
+    pub fn takes_damage(&self) -> bool {
+        *self == GameMode::Survival
+    }
+
+    pub fn flies(&self) -> bool {
+        *self != GameMode::Survival
+    }
+
+    /// Whether the player can target blocks, which also decides if the targeted block is
+    /// outlined.
+    pub fn can_interact(&self) -> bool {
+        *self != GameMode::Spectator
+    }
+}
+
+impl fmt::Display for GameMode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum PlayerAction {
+    Forward,
+    Back,
+    Left,
+    Right,
+    /// Jumps when walking, rises when flying.
+    Jump,
+    /// Sinks when flying.
+    Descend,
+    Break,
+    Place,
+}
+
+impl PlayerAction {
+    pub const ALL: [PlayerAction; 8] = [
+        PlayerAction::Forward,
+        PlayerAction::Back,
+        PlayerAction::Left,
+        PlayerAction::Right,
+        PlayerAction::Jump,
+        PlayerAction::Descend,
+        PlayerAction::Break,
+        PlayerAction::Place,
+    ];
+
+    fn bit(self) -> u8 {
+        1 << PlayerAction::ALL.iter().position(|action| *action == self).unwrap()
+    }
+}
+
+/// A copy of a `PlayerInput` with the actions packed into bit sets, as sent from a
+/// client to the server.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct InputState {
+    pub held: u8,
+    pub pressed: u8,
+    pub yaw: f32,
+    pub pitch: f32,
+    pub selected_slot: u8,
+}
+
+/// What the player controlling the entity is doing, written by the client every frame.
+pub struct PlayerInput {
+    held: HashSet<PlayerAction>,
+    pressed: HashSet<PlayerAction>,
+    /// Rotation around the vertical axis in radians. Zero looks towards negative Z.
+    pub yaw: f32,
+    /// Rotation above the horizon in radians.
+    pub pitch: f32,
+    pub selected_slot: usize,
+}
+
+impl PlayerInput {
+    pub fn new() -> Self {
+        Self { held: HashSet::new(), pressed: HashSet::new(), yaw: 0.0, pitch: 0.0, selected_slot: 0 }
+    }
+
+    pub fn press(&mut self, action: PlayerAction) {
+        if self.held.insert(action) {
+            self.pressed.insert(action);
+        }
+    }
+
+    pub fn release(&mut self, action: PlayerAction) {
+        self.held.remove(&action);
+    }
+
+    pub fn is_held(&self, action: PlayerAction) -> bool {
+        self.held.contains(&action)
+    }
+
+    /// Whether the action started since the last tick.
+    pub fn was_pressed(&self, action: PlayerAction) -> bool {
+        self.pressed.contains(&action)
+    }
+
+    /// Turns the view, keeping the pitch short of straight up or down.
+    pub fn rotate(&mut self, yaw: f32, pitch: f32) {
+        let limit = std::f32::consts::FRAC_PI_2 - 0.01;
+        self.yaw = (self.yaw + yaw).rem_euclid(std::f32::consts::TAU);
+        self.pitch = (self.pitch + pitch).clamp(-limit, limit);
+    }
+
+    pub fn look_direction(&self) -> Vector3<f32> {
+        let (sin_yaw, cos_yaw) = self.yaw.sin_cos();
+        let (sin_pitch, cos_pitch) = self.pitch.sin_cos();
+        Vector3::new(-sin_yaw * cos_pitch, sin_pitch, -cos_yaw * cos_pitch)
+    }
+
+    pub fn state(&self) -> InputState {
+        let bits = |actions: &HashSet<PlayerAction>| actions.iter().fold(0, |bits, action| bits | action.bit());
+        InputState {
+            held: bits(&self.held),
+            pressed: bits(&self.pressed),
+            yaw: self.yaw,
+            pitch: self.pitch,
+            selected_slot: self.selected_slot as u8,
+        }
+    }
+
+    /// Takes over the actions and view of `state`. Actions pressed in `state` stay
+    /// pressed until cleared, even when they were released again.
+    pub fn apply(&mut self, state: &InputState) {
+        for action in PlayerAction::ALL {
+            if state.pressed & action.bit() != 0 {
+                self.pressed.insert(action);
+            }
+            if state.held & action.bit() != 0 {
+                self.held.insert(action);
+            } else {
+                self.held.remove(&action);
+            }
+        }
+        self.yaw = state.yaw;
+        self.pitch = state.pitch;
+        self.selected_slot = state.selected_slot as usize;
+    }
+
+    fn forward(&self) -> Vector3<f32> {
+        Vector3::new(-self.yaw.sin(), 0.0, -self.yaw.cos())
+    }
+
+    fn right(&self) -> Vector3<f32> {
+        Vector3::new(self.yaw.cos(), 0.0, -self.yaw.sin())
+    }
+}
+
+impl Default for PlayerInput {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// The block the player is looking at within reach.
+#[derive(Default)]
+pub struct Target {
+    pub hit: Option<RaycastHit>,
+}
+
+/// Time spent breaking the targeted block.
+#[derive(Default)]
+pub struct BreakProgress {
+    target: Option<Vector3<i32>>,
+    elapsed: f32,
+    /// Seconds the target takes to break.
+    duration: f32,
+    cooldown: f32,
+}
+
+impl BreakProgress {
+    /// The block being broken, if any.
+    pub fn target(&self) -> Option<Vector3<i32>> {
+        self.target
+    }
+
+    /// Progress towards breaking the target, between 0 and 1.
+    pub fn fraction(&self) -> f32 {
+        match self.target {
+            None => 0.0,
+            Some(_) if self.duration <= 0.0 => 1.0,
+            Some(_) => (self.elapsed / self.duration).min(1.0),
+        }
+    }
+
+    /// Keeps breaking `target`, starting over when the target changed or is `None`.
+    /// Returns true once the block has been broken for `duration` seconds.
+    pub fn advance(&mut self, target: Option<Vector3<i32>>, duration: f32, delta: f32) -> bool {
+        if target != self.target {
+            self.target = target;
+            self.elapsed = 0.0;
+        }
+        if self.target.is_none() {
+            return false;
+        }
+        self.duration = duration;
+        self.elapsed += delta;
+        if self.elapsed < duration {
+            return false;
+        }
+        self.reset();
+        true
+    }
+
+    pub fn reset(&mut self) {
+        self.target = None;
+        self.elapsed = 0.0;
+    }
+}
+
+/// What is stored for each player between sessions.
+pub struct PlayerData {
+    pub game_mode: GameMode,
+    pub inventory: Inventory,
+}
+
+impl PlayerData {
+    /// A survival player with an empty inventory.
+    pub fn new() -> Self {
+        Self { game_mode: GameMode::Survival, inventory: Inventory::new(PLAYER_INVENTORY_SIZE) }
+    }
+
+    /// Copies the stored state out of a player entity.
+    pub fn from_entity(entities: &Entities, player: Entity) -> Option<Self> {
+        Some(Self {
+            game_mode: entities.get::<GameMode>(player).copied().unwrap_or(GameMode::Survival),
+            inventory: entities.get::<Inventory>(player)?.clone(),
+        })
+    }
+
+    pub fn encode(&self, registry: &ItemRegistry) -> Vec<u8> {
+        let mut bytes = vec![PLAYER_FORMAT_VERSION];
+        bytes.push(GameMode::ALL.iter().position(|mode| *mode == self.game_mode).unwrap() as u8);
+        bytes.extend(encode_inventory(&self.inventory, registry));
+        bytes
+    }
+
+    pub fn decode(bytes: &[u8], registry: &ItemRegistry) -> io::Result<Self> {
+        let mut reader = Reader::new(bytes);
+        let version = reader.u8()?;
+        if version != PLAYER_FORMAT_VERSION {
+            return Err(invalid_data(format!("Unsupported player format version {}", version)));
+        }
+        let mode = reader.u8()?;
+        let game_mode = *GameMode::ALL.get(mode as usize).ok_or_else(|| invalid_data(format!("Unknown game mode {}", mode)))?;
+        let inventory = decode_inventory(&bytes[2..], registry)?;
+        Ok(Self { game_mode, inventory })
+    }
+}
+
+impl Default for PlayerData {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Switches the player's game mode and the physics that come with it.
+pub fn set_game_mode(entities: &mut Entities, player: Entity, mode: GameMode) {
+    entities.insert(player, mode);
+    if let Some(body) = entities.get_mut::<PhysicsBody>(player) {
+        body.gravity = !mode.flies();
+        body.noclip = mode == GameMode::Spectator;
+        body.velocity = Vector3::zeros();
+        body.fall_distance = 0.0;
+    }
+    if let Some(progress) = entities.get_mut::<BreakProgress>(player) {
+        progress.reset();
+    }
+    if !mode.can_interact() {
+        entities.insert(player, Target::default());
+    }
+}
+
+/// Sets the velocity of players from their held movement actions.
+pub fn move_players(context: &mut SystemContext) {
+    for player in context.entities.with::<PlayerInput>() {
+        let mode = context.entities.get::<GameMode>(player).copied().unwrap_or(GameMode::Survival);
+        let input = context.entities.get::<PlayerInput>(player).unwrap();
+        let held = |action| mode.allows(action) && input.is_held(action);
+
+        let mut direction = Vector3::zeros();
+        for (action, towards) in [
+            (PlayerAction::Forward, input.forward()),
+            (PlayerAction::Back, -input.forward()),
+            (PlayerAction::Right, input.right()),
+            (PlayerAction::Left, -input.right()),
+        ] {
+            if held(action) {
+                direction += towards;
+            }
+        }
+        let speed = if mode.flies() { FLY_SPEED } else { WALK_SPEED };
+        let horizontal = direction.try_normalize(f32::EPSILON).unwrap_or_else(Vector3::zeros) * speed;
+        let vertical = match (held(PlayerAction::Jump), held(PlayerAction::Descend)) {
+            (true, false) => 1.0,
+            (false, true) => -1.0,
+            _ => 0.0,
+        };
+
+        let Some(body) = context.entities.get_mut::<PhysicsBody>(player) else {
+            continue;
+        };
+        body.velocity.x = horizontal.x;
+        body.velocity.z = horizontal.z;
+        if mode.flies() {
+            body.velocity.y = vertical * FLY_SPEED;
+        } else if vertical > 0.0 && body.on_ground {
+            body.velocity.y = JUMP_SPEED;
+        }
+    }
+}
+
+/// Finds the block each player is looking at.
+pub fn update_targets(context: &mut SystemContext) {
+    for player in context.entities.with::<PlayerInput>() {
+        let mode = context.entities.get::<GameMode>(player).copied().unwrap_or(GameMode::Survival);
+        let Some(eye) = context.entities.get::<PhysicsBody>(player).map(|body| body.position + Vector3::y() * EYE_HEIGHT) else {
+            continue;
+        };
+        let direction = context.entities.get::<PlayerInput>(player).unwrap().look_direction();
+        let hit = if mode.can_interact() { physics::raycast(context.world, eye, direction, REACH) } else { None };
+        context.entities.insert(player, Target { hit });
+    }
+}
+
+/// Breaks and places the targeted block. Creative players break instantly and place
+/// without using up items, survival players break over the block's breaking time with
+/// the held tool.
+pub fn break_and_place(context: &mut SystemContext) {
+    for player in context.entities.with::<PlayerInput>() {
+        let mode = context.entities.get::<GameMode>(player).copied().unwrap_or(GameMode::Survival);
+        let Some(hit) = context.entities.get::<Target>(player).and_then(|target| target.hit) else {
+            if let Some(progress) = context.entities.get_mut::<BreakProgress>(player) {
+                progress.reset();
+            }
+            continue;
+        };
+        let input = context.entities.get::<PlayerInput>(player).unwrap();
+        let (breaking, placing, slot) = (
+            mode.allows(PlayerAction::Break) && input.is_held(PlayerAction::Break),
+            mode.allows(PlayerAction::Place) && input.was_pressed(PlayerAction::Place),
+            input.selected_slot,
+        );
+
+        if breaking {
+            let mut progress = context.entities.remove::<BreakProgress>(player).unwrap_or_default();
+            if mode == GameMode::Creative {
+                progress.cooldown -= context.delta;
+                if progress.cooldown <= 0.0 {
+                    progress.cooldown = CREATIVE_BREAK_COOLDOWN;
+                    context.world.set_voxel(hit.position, None);
+                }
+            } else {
+                let tool = context.entities.get::<Inventory>(player)
+                    .and_then(|inventory| inventory.get(slot))
+                    .map(|stack| context.world.items().get(stack.item));
+                let duration = context.world.block_type(hit.position).map_or(0.0, |block| breaking_time(block, tool));
+                if progress.advance(Some(hit.position), duration, context.delta) {
+                    context.world.break_voxel(hit.position);
+                    wear_tool(context, player, slot);
+                }
+            }
+            context.entities.insert(player, progress);
+        } else if let Some(progress) = context.entities.get_mut::<BreakProgress>(player) {
+            progress.reset();
+            progress.cooldown = 0.0;
+        }
+
+        if placing {
+            place(context, player, mode, slot, hit.position + hit.normal);
+        }
+    }
+}
+
+/// Forgets the actions pressed during the tick, after all systems have seen them.
+pub fn clear_pressed(context: &mut SystemContext) {
+    for (_, input) in context.entities.iter_mut::<PlayerInput>() {
+        input.pressed.clear();
+    }
+}
+
+/// Adds a use to the tool in `slot`, removing it when it breaks.
+fn wear_tool(context: &mut SystemContext, player: Entity, slot: usize) {
+    let Some(inventory) = context.entities.get_mut::<Inventory>(player) else {
+        return;
+    };
+    let Some(mut stack) = inventory.get(slot).copied() else {
+        return;
+    };
+    let broke = stack.wear(context.world.items());
+    inventory.set(slot, (!broke).then_some(stack));
+}
+
+fn place(context: &mut SystemContext, player: Entity, mode: GameMode, slot: usize, position: Vector3<i32>) {
+    let Some(inventory) = context.entities.get::<Inventory>(player) else {
+        return;
+    };
+    let Some(ItemKind::Block(block)) = inventory.get(slot).map(|stack| context.world.items().get(stack.item).kind) else {
+        return;
+    };
+    // Solid blocks cannot be placed where they would trap the player.
+    let voxel_box = Aabb::new(position.cast(), position.cast::<f32>() + Vector3::repeat(1.0));
+    let blocked = context.world.registry().get(block).solid
+        && context.entities.get::<PhysicsBody>(player).is_some_and(|body| body.aabb().intersects(&voxel_box));
+    if blocked {
+        return;
+    }
+
+    if mode == GameMode::Creative {
+        context.world.place_block(position, block);
+    } else {
+        let inventory = context.entities.get_mut::<Inventory>(player).unwrap();
+        context.world.place_item(inventory, slot, position);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{ecs::components::spawn_player, item::ItemStack, world::block::BlockRegistry};
+
+    use super::*;
+
+    const DELTA: f32 = 0.25;
+
+    #[test]
+    fn break_progress_finishes_after_the_duration() {
+        let target = Some(Vector3::new(1, 2, 3));
+        let mut progress = BreakProgress::default();
+        assert_eq!(progress.fraction(), 0.0);
+
+        assert!(!progress.advance(target, 1.0, DELTA));
+        assert_eq!(progress.target(), target);
+        assert_eq!(progress.fraction(), 0.25);
+        assert!(!progress.advance(target, 1.0, DELTA));
+        assert!(!progress.advance(target, 1.0, DELTA));
+        assert_eq!(progress.fraction(), 0.75);
+        assert!(progress.advance(target, 1.0, DELTA));
+        // Breaking starts over for the next block.
+        assert_eq!(progress.target(), None);
+        assert_eq!(progress.fraction(), 0.0);
+    }
+
+    #[test]
+    fn break_progress_starts_over_when_the_target_changes() {
+        let (first, second) = (Some(Vector3::new(0, 0, 0)), Some(Vector3::new(1, 0, 0)));
+        let mut progress = BreakProgress::default();
+        progress.advance(first, 1.0, DELTA);
+        progress.advance(first, 1.0, DELTA);
+        assert_eq!(progress.fraction(), 0.5);
+
+        assert!(!progress.advance(second, 1.0, DELTA));
+        assert_eq!(progress.target(), second);
+        assert_eq!(progress.fraction(), 0.25);
+
+        assert!(!progress.advance(None, 1.0, DELTA));
+        assert_eq!(progress.target(), None);
+        assert_eq!(progress.fraction(), 0.0);
+        assert!(!progress.advance(second, 1.0, DELTA));
+        assert_eq!(progress.fraction(), 0.25);
+    }
+
+    #[test]
+    fn blocks_without_hardness_break_at_once() {
+        let mut progress = BreakProgress::default();
+        assert!(progress.advance(Some(Vector3::zeros()), 0.0, DELTA));
+    }
+
+    #[test]
+    fn game_modes_switch_flight_and_noclip() {
+        let mut entities = Entities::new();
+        let player = spawn_player(&mut entities, Vector3::zeros(), PlayerData::new());
+        let physics = |entities: &Entities| {
+            let body = entities.get::<PhysicsBody>(player).unwrap();
+            (body.gravity, body.noclip)
+        };
+        assert_eq!(physics(&entities), (true, false));
+
+        for (mode, expected) in [(GameMode::Creative, (false, false)), (GameMode::Spectator, (false, true)), (GameMode::Survival, (true, false))] {
+            entities.get_mut::<PhysicsBody>(player).unwrap().velocity = Vector3::new(0.0, -5.0, 0.0);
+            entities.get_mut::<PhysicsBody>(player).unwrap().fall_distance = 3.0;
+            set_game_mode(&mut entities, player, mode);
+            assert_eq!(entities.get::<GameMode>(player), Some(&mode));
+            assert_eq!(physics(&entities), expected, "{:?}", mode);
+            let body = entities.get::<PhysicsBody>(player).unwrap();
+            assert_eq!((body.velocity, body.fall_distance), (Vector3::zeros(), 0.0));
+        }
+    }
+
+    #[test]
+    fn player_data_keeps_the_game_mode_and_inventory() {
+        let blocks = BlockRegistry::with_defaults();
+        let items = ItemRegistry::with_defaults(&blocks);
+        let dirt = items.id("dirt").unwrap();
+        for mode in GameMode::ALL {
+            let mut data = PlayerData { game_mode: mode, ..PlayerData::new() };
+            data.inventory.set(2, Some(ItemStack::new(dirt, 7)));
+
+            let decoded = PlayerData::decode(&data.encode(&items), &items).unwrap();
+            assert_eq!(decoded.game_mode, mode);
+            assert_eq!(decoded.inventory, data.inventory);
+        }
+        assert!(PlayerData::decode(&[PLAYER_FORMAT_VERSION, GameMode::ALL.len() as u8], &items).is_err());
+    }
+}
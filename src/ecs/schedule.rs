@@ -0,0 +1,54 @@
+use crate::world::World;
+
+use super::Entities;
+
+/// Stages run in declaration order every time a schedule runs.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Stage {
+    Physics,
+    Ai,
+    Rendering,
+}
+
+pub struct SystemContext<'a> {
+    pub entities: &'a mut Entities,
+    pub world: &'a mut World,
+    /// Seconds since the schedule last ran.
+    pub delta: f32,
+}
+
+pub type System = fn(&mut SystemContext);
+
+pub struct Schedule {
+    systems: Vec<(Stage, System)>,
+}
+
+impl Schedule {
+    pub fn new() -> Self {
+        Self { systems: Vec::new() }
+    }
+
+    /// Adds a system to a stage. Systems within a stage run in the order they were added.
+    pub fn add_system(&mut self, stage: Stage, system: System) {
+        let position = self.systems.partition_point(|(other, _)| *other <= stage);
+        self.systems.insert(position, (stage, system));
+    }
+
+    pub fn run(&self, context: &mut SystemContext) {
+        for (_, system) in &self.systems {
+            system(context);
+        }
+    }
+
+    pub fn run_stage(&self, stage: Stage, context: &mut SystemContext) {
+        for (_, system) in self.systems.iter().filter(|(other, _)| *other == stage) {
+            system(context);
+        }
+    }
+}
+
+impl Default for Schedule {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,202 @@
+use nalgebra::Vector3;
+
+use crate::{item::{Inventory, ItemStack}, physics::{Aabb, PhysicsBody, JUMP_SPEED}, transform::Transform, world::{falling::{self, FallingBlock}, pathfinding, Spawn}};
+
+use super::{components::{self, DroppedItem, PathFollower, Player, Wander}, health, player::{self, GameMode}, Schedule, Stage, SystemContext};
+
+/// Seconds a wandering entity keeps its direction.
+const WANDER_INTERVAL: f32 = 3.0;
+/// Distance from a player at which dropped items are picked up.
+const PICKUP_RADIUS: f32 = 1.5;
+/// Horizontal distance from the centre of a waypoint at which it counts as reached.
+const WAYPOINT_RADIUS: f32 = 0.2;
+
+/// The systems of the game, in the order they run each tick.
+pub fn default_schedule() -> Schedule {
+    let mut schedule = Schedule::new();
+    schedule.add_system(Stage::Physics, spawn_requested);
+    schedule.add_system(Stage::Physics, player::move_players);
+    schedule.add_system(Stage::Physics, step_bodies);
+    schedule.add_system(Stage::Physics, player::update_targets);
+    schedule.add_system(Stage::Physics, player::break_and_place);
+    schedule.add_system(Stage::Physics, land_falling_blocks);
+    schedule.add_system(Stage::Physics, pick_up_items);
+    schedule.add_system(Stage::Physics, health::tick_invulnerability);
+    schedule.add_system(Stage::Physics, health::fall_damage);
+    schedule.add_system(Stage::Physics, health::environment_damage);
+    schedule.add_system(Stage::Physics, health::handle_deaths);
+    schedule.add_system(Stage::Ai, wander);
+    schedule.add_system(Stage::Ai, follow_path);
+    schedule.add_system(Stage::Rendering, sync_transforms);
+    schedule.add_system(Stage::Rendering, player::clear_pressed);
+    schedule
+}
+
+/// Systems for a world replicated from a server, which simulates everything else and
+/// sends the results. The local player is moved ahead of the server with
+/// `prediction_schedule`.
+pub fn replica_schedule() -> Schedule {
+    let mut schedule = prediction_schedule();
+    schedule.add_system(Stage::Physics, player::update_targets);
+    schedule.add_system(Stage::Rendering, sync_transforms);
+    schedule.add_system(Stage::Rendering, player::clear_pressed);
+    schedule
+}
+
+/// Systems a client runs for its own player to predict where the server will move it.
+/// The client runs them again for every input the server has not applied yet whenever
+/// it corrects the player.
+pub fn prediction_schedule() -> Schedule {
+    let mut schedule = Schedule::new();
+    schedule.add_system(Stage::Physics, player::move_players);
+    schedule.add_system(Stage::Physics, step_bodies);
+    schedule
+}
+
+/// Creates the entities the world requested since the last tick.
+pub fn spawn_requested(context: &mut SystemContext) {
+    for spawn in context.world.take_spawns() {
+        match spawn {
+            Spawn::FallingBlock { voxel, position } => {
+                components::spawn_falling_block(context.entities, voxel, position);
+            },
+            Spawn::DroppedItem { stack, position } => {
+                components::spawn_dropped_item(context.entities, stack, position, context.world.items());
+            },
+        }
+    }
+}
+
+pub fn step_bodies(context: &mut SystemContext) {
+    for (_, body) in context.entities.iter_mut::<PhysicsBody>() {
+        body.step(context.world, context.delta);
+    }
+}
+
+/// Places falling blocks that reached the ground back into the voxel grid, or drops their
+/// item when their voxel is taken.
+pub fn land_falling_blocks(context: &mut SystemContext) {
+    for entity in context.entities.with::<FallingBlock>() {
+        let Some(body) = context.entities.get::<PhysicsBody>(entity).filter(|body| body.on_ground) else {
+            continue;
+        };
+        let (position, body_position) = (falling::voxel_position(body), body.position);
+        let voxel = context.entities.get::<FallingBlock>(entity).unwrap().voxel;
+        context.entities.despawn(entity);
+        if falling::can_land_in(context.world, position) {
+            context.world.set_voxel(position, Some(voxel));
+        } else if let Some(item) = context.world.items().block_item(voxel.block) {
+            components::spawn_dropped_item(context.entities, ItemStack::new(item, 1), body_position, context.world.items());
+        }
+    }
+}
+
+/// Moves dropped items near players into their inventories. Items that do not fit stay
+/// on the ground, and spectators leave them alone.
+pub fn pick_up_items(context: &mut SystemContext) {
+    for player in context.entities.with::<Player>() {
+        if context.entities.get::<GameMode>(player).is_some_and(|mode| !mode.can_interact()) {
+            continue;
+        }
+        let Some(position) = context.entities.get::<Transform>(player).map(|transform| transform.position()) else {
+            continue;
+        };
+        let region = Aabb::new(position - Vector3::repeat(PICKUP_RADIUS), position + Vector3::repeat(PICKUP_RADIUS));
+        for entity in context.entities.in_region(&region) {
+            let Some(stack) = context.entities.get::<DroppedItem>(entity).map(|dropped| dropped.stack) else {
+                continue;
+            };
+            let Some(inventory) = context.entities.get_mut::<Inventory>(player) else {
+                break;
+            };
+            match inventory.insert(stack, context.world.items()) {
+                Some(rest) => context.entities.get_mut::<DroppedItem>(entity).unwrap().stack = rest,
+                None => {
+                    context.entities.despawn(entity);
+                },
+            }
+        }
+    }
+}
+
+pub fn wander(context: &mut SystemContext) {
+    for entity in context.entities.with::<Wander>() {
+        if context.entities.get::<PathFollower>(entity).is_some_and(|follower| follower.target().is_some()) {
+            continue;
+        }
+        let Some(wander) = context.entities.get_mut::<Wander>(entity) else {
+            continue;
+        };
+        wander.timer -= context.delta;
+        if wander.timer <= 0.0 {
+            wander.timer = WANDER_INTERVAL;
+            // One in five picks means standing still for a while.
+            wander.direction = match context.world.random().below(5) {
+                0 => Vector3::x(),
+                1 => -Vector3::x(),
+                2 => Vector3::z(),
+                3 => -Vector3::z(),
+                _ => Vector3::zeros(),
+            };
+        }
+        let velocity = wander.direction * wander.speed;
+        if let Some(body) = context.entities.get_mut::<PhysicsBody>(entity) {
+            body.velocity.x = velocity.x;
+            body.velocity.z = velocity.z;
+        }
+    }
+}
+
+/// Finds paths for entities that were given a new target, and walks them towards their
+/// next waypoint, jumping when it is higher or across a gap.
+pub fn follow_path(context: &mut SystemContext) {
+    for entity in context.entities.with::<PathFollower>() {
+        let Some(body) = context.entities.get::<PhysicsBody>(entity) else {
+            continue;
+        };
+        let (position, on_ground) = (body.position, body.on_ground);
+        let feet = (position + Vector3::y() * 0.01).map(|c| c.floor() as i32);
+        let follower = context.entities.get_mut::<PathFollower>(entity).unwrap();
+
+        if let Some(target) = follower.target().filter(|_| follower.path().is_empty()) {
+            match pathfinding::find_path(context.world, feet, target, &follower.settings) {
+                Some(path) if path.len() > 1 => follower.set_path(path.into_iter().skip(1).collect()),
+                _ => follower.set_target(None),
+            }
+        }
+        let Some(waypoint) = follower.next_waypoint() else {
+            continue;
+        };
+
+        let mut velocity = Vector3::zeros();
+        let offset = waypoint.cast::<f32>() + Vector3::new(0.5, 0.0, 0.5) - position;
+        let horizontal = Vector3::new(offset.x, 0.0, offset.z);
+        if horizontal.norm() < WAYPOINT_RADIUS && feet.y == waypoint.y {
+            follower.reach_waypoint();
+        } else {
+            // Slow down near the waypoint instead of overshooting it.
+            let speed = follower.speed.min(horizontal.norm() / context.delta);
+            velocity = horizontal.try_normalize(f32::EPSILON).unwrap_or_else(Vector3::zeros) * speed;
+            if on_ground && (waypoint.y > feet.y || horizontal.norm() > 1.5) {
+                velocity.y = JUMP_SPEED;
+            }
+        }
+
+        let body = context.entities.get_mut::<PhysicsBody>(entity).unwrap();
+        body.velocity.x = velocity.x;
+        body.velocity.z = velocity.z;
+        if velocity.y > 0.0 {
+            body.velocity.y = velocity.y;
+        }
+    }
+}
+
+/// Moves the transform of every entity with a body to the body's position.
+pub fn sync_transforms(context: &mut SystemContext) {
+    for entity in context.entities.with::<PhysicsBody>() {
+        let position = context.entities.get::<PhysicsBody>(entity).unwrap().position;
+        if let Some(transform) = context.entities.get_mut::<Transform>(entity) {
+            transform.set_position(position);
+        }
+    }
+}
@@ -0,0 +1,14 @@
+mod crafting;
+pub use crafting::{CraftingGrid, Recipe, RecipeBook, RecipeShape};
+
+mod definition;
+pub use definition::{ItemId, ItemKind, ItemRegistry, ItemType};
+
+mod inventory;
+pub use inventory::{decode_inventory, encode_inventory, Inventory};
+
+mod stack;
+pub use stack::ItemStack;
+
+mod tool;
+pub use tool::{breaking_time, ToolKind};
@@ -0,0 +1,361 @@
+use std::{collections::HashMap, fs, io, path::Path};
+
+use crate::encoding::invalid_data;
+
+use super::{Inventory, ItemId, ItemRegistry, ItemStack};
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum RecipeShape {
+    /// Ingredients in a fixed arrangement, stored row by row. The pattern can be placed
+    /// anywhere in the grid and also matches when mirrored left to right.
+    Shaped { width: usize, height: usize, pattern: Vec<Option<ItemId>> },
+    /// Ingredients in any arrangement.
+    Shapeless(Vec<ItemId>),
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Recipe {
+    pub shape: RecipeShape,
+    pub result: ItemStack,
+}
+
+impl Recipe {
+    pub fn matches(&self, grid: &CraftingGrid) -> bool {
+        match &self.shape {
+            RecipeShape::Shaped { width, height, pattern } => {
+                let Some((grid_width, grid_height, items)) = grid.trimmed() else {
+                    return false;
+                };
+                if (grid_width, grid_height) != (*width, *height) {
+                    return false;
+                }
+                let mirrored = (0..*height).flat_map(|y| (0..*width).rev().map(move |x| pattern[y * width + x]));
+                items == *pattern || items.iter().copied().eq(mirrored)
+            },
+            RecipeShape::Shapeless(ingredients) => {
+                let mut items: Vec<ItemId> = grid.slots.iter().flatten().copied().collect();
+                let mut ingredients = ingredients.clone();
+                items.sort_by_key(|item| item.0);
+                ingredients.sort_by_key(|item| item.0);
+                items == ingredients
+            },
+        }
+    }
+
+    /// How many of each item one craft uses up.
+    pub fn ingredients(&self) -> Vec<(ItemId, u32)> {
+        let items: Vec<ItemId> = match &self.shape {
+            RecipeShape::Shaped { pattern, .. } => pattern.iter().flatten().copied().collect(),
+            RecipeShape::Shapeless(ingredients) => ingredients.clone(),
+        };
+        let mut counts: Vec<(ItemId, u32)> = Vec::new();
+        for item in items {
+            match counts.iter_mut().find(|(counted, _)| *counted == item) {
+                Some((_, count)) => *count += 1,
+                None => counts.push((item, 1)),
+            }
+        }
+        counts
+    }
+
+    pub fn can_craft_from(&self, inventory: &Inventory) -> bool {
+        self.ingredients().into_iter().all(|(item, count)| inventory.count(item) >= count)
+    }
+
+    /// Uses up the ingredients from the inventory and adds the result to it. Nothing
+    /// changes when an ingredient is missing or the result does not fit.
+    pub fn craft_from(&self, inventory: &mut Inventory, registry: &ItemRegistry) -> bool {
+        let mut crafted = inventory.clone();
+        for (item, count) in self.ingredients() {
+            if !crafted.remove(item, count) {
+                return false;
+            }
+        }
+        if crafted.insert(self.result, registry).is_some() {
+            return false;
+        }
+        *inventory = crafted;
+        true
+    }
+}
+
+/// The items laid out for crafting, row by row.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CraftingGrid {
+    width: usize,
+    height: usize,
+    slots: Vec<Option<ItemId>>,
+}
+
+impl CraftingGrid {
+    pub fn new(width: usize, height: usize) -> Self {
+        Self { width, height, slots: vec![None; width * height] }
+    }
+
+    pub fn get(&self, x: usize, y: usize) -> Option<ItemId> {
+        (x < self.width && y < self.height).then(|| self.slots[y * self.width + x]).flatten()
+    }
+
+    pub fn set(&mut self, x: usize, y: usize, item: Option<ItemId>) {
+        if x < self.width && y < self.height {
+            self.slots[y * self.width + x] = item;
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.slots.fill(None);
+    }
+
+    /// The smallest rectangle around the items in the grid, as width, height and slots.
+    fn trimmed(&self) -> Option<(usize, usize, Vec<Option<ItemId>>)> {
+        let filled = (0..self.height).flat_map(|y| (0..self.width).map(move |x| (x, y))).filter(|&(x, y)| self.get(x, y).is_some());
+        let (min_x, min_y, max_x, max_y) = filled.fold(None, |bounds, (x, y)| match bounds {
+            None => Some((x, y, x, y)),
+            Some((min_x, min_y, max_x, max_y)) => Some((min_x.min(x), min_y.min(y), max_x.max(x), max_y.max(y))),
+        })?;
+        let items = (min_y..=max_y).flat_map(|y| (min_x..=max_x).map(move |x| (x, y))).map(|(x, y)| self.get(x, y)).collect();
+        Some((max_x - min_x + 1, max_y - min_y + 1, items))
+    }
+}
+
+/// All known recipes.
+pub struct RecipeBook {
+    recipes: Vec<Recipe>,
+}
+
+impl RecipeBook {
+    pub fn new() -> Self {
+        Self { recipes: Vec::new() }
+    }
+
+    pub fn add(&mut self, recipe: Recipe) {
+        self.recipes.push(recipe);
+    }
+
+    pub fn recipes(&self) -> &[Recipe] {
+        &self.recipes
+    }
+
+    /// The recipe laid out in the grid, if any.
+    pub fn find(&self, grid: &CraftingGrid) -> Option<&Recipe> {
+        self.recipes.iter().find(|recipe| recipe.matches(grid))
+    }
+
+    /// Every recipe the inventory holds enough ingredients for.
+    pub fn craftable(&self, inventory: &Inventory) -> Vec<&Recipe> {
+        self.recipes.iter().filter(|recipe| recipe.can_craft_from(inventory)).collect()
+    }
+
+    pub fn load(path: &Path, registry: &ItemRegistry) -> io::Result<Self> {
+        Self::parse(&fs::read_to_string(path)?, registry)
+    }
+
+    /// Parses recipes from text. Every recipe starts with a `[shaped]` or `[shapeless]`
+    /// line followed by `key = value` lines:
+    ///
+    /// - `result = <item> [count]`
+    /// - `pattern = <row>` once per row of a shaped recipe, with `.` for empty slots
+    /// - `<character> = <item>` for each character used in a pattern
+    /// - `ingredients = <item>, <item>, ...` for shapeless recipes
+    pub fn parse(text: &str, registry: &ItemRegistry) -> io::Result<Self> {
+        let mut book = Self::new();
+        let mut current: Option<RecipeDefinition> = None;
+        for line in text.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            if line == "[shaped]" || line == "[shapeless]" {
+                if let Some(definition) = current.take() {
+                    book.add(definition.build(registry)?);
+                }
+                current = Some(RecipeDefinition { shaped: line == "[shaped]", ..Default::default() });
+                continue;
+            }
+
+            let Some(definition) = current.as_mut() else {
+                return Err(invalid_data(format!("Line outside of a recipe '{}'", line)));
+            };
+            let Some((key, value)) = line.split_once('=') else {
+                return Err(invalid_data(format!("Malformed line '{}'", line)));
+            };
+            let (key, value) = (key.trim(), value.trim());
+            match key {
+                "result" => definition.result = Some(value.to_owned()),
+                "pattern" => definition.pattern.push(value.to_owned()),
+                "ingredients" => definition.ingredients.extend(value.split(',').map(|item| item.trim().to_owned())),
+                _ if key.chars().count() == 1 => {
+                    definition.keys.insert(key.chars().next().unwrap(), value.to_owned());
+                },
+                _ => return Err(invalid_data(format!("Unknown recipe key '{}'", key))),
+            }
+        }
+        if let Some(definition) = current {
+            book.add(definition.build(registry)?);
+        }
+        Ok(book)
+    }
+}
+
+impl Default for RecipeBook {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[derive(Default)]
+struct RecipeDefinition {
+    shaped: bool,
+    result: Option<String>,
+    pattern: Vec<String>,
+    keys: HashMap<char, String>,
+    ingredients: Vec<String>,
+}
+
+impl RecipeDefinition {
+    fn build(self, registry: &ItemRegistry) -> io::Result<Recipe> {
+        let item = |name: &str| registry.id(name).ok_or_else(|| invalid_data(format!("Unknown item '{}'", name)));
+
+        let result = self.result.ok_or_else(|| invalid_data("Recipe without a result".to_owned()))?;
+        let mut parts = result.split_whitespace();
+        let result_item = item(parts.next().unwrap_or_default())?;
+        let count = match parts.next() {
+            Some(count) => count.parse().ok().filter(|&count| count > 0).ok_or_else(|| invalid_data(format!("Invalid result count '{}'", count)))?,
+            None => 1,
+        };
+        let result = ItemStack::new(result_item, count);
+
+        if !self.shaped {
+            if self.ingredients.is_empty() {
+                return Err(invalid_data("Shapeless recipe without ingredients".to_owned()));
+            }
+            let ingredients = self.ingredients.iter().map(|name| item(name)).collect::<io::Result<_>>()?;
+            return Ok(Recipe { shape: RecipeShape::Shapeless(ingredients), result });
+        }
+
+        let width = self.pattern.first().map_or(0, |row| row.chars().count());
+        if width == 0 || self.pattern.iter().any(|row| row.chars().count() != width) {
+            return Err(invalid_data("Shaped recipe with an empty or uneven pattern".to_owned()));
+        }
+        let mut pattern = Vec::new();
+        for character in self.pattern.iter().flat_map(|row| row.chars()) {
+            pattern.push(match character {
+                '.' => None,
+                _ => {
+                    let name = self.keys.get(&character).ok_or_else(|| invalid_data(format!("Pattern character '{}' has no item", character)))?;
+                    Some(item(name)?)
+                },
+            });
+        }
+        // Matching compares against the trimmed grid, so empty edge rows and columns
+        // would never match.
+        let grid = CraftingGrid { width, height: self.pattern.len(), slots: pattern };
+        let (width, height, pattern) = grid.trimmed().ok_or_else(|| invalid_data("Shaped recipe without ingredients".to_owned()))?;
+        Ok(Recipe { shape: RecipeShape::Shaped { width, height, pattern }, result })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::world::block::BlockRegistry;
+
+    use super::*;
+
+    fn registry() -> ItemRegistry {
+        ItemRegistry::with_defaults(&BlockRegistry::with_defaults())
+    }
+
+    fn recipe(text: &str, registry: &ItemRegistry) -> Recipe {
+        RecipeBook::parse(text, registry).unwrap().recipes()[0].clone()
+    }
+
+    /// A 3 by 3 grid with `items` at the given positions.
+    fn grid(items: &[(usize, usize, ItemId)]) -> CraftingGrid {
+        let mut grid = CraftingGrid::new(3, 3);
+        for &(x, y, item) in items {
+            grid.set(x, y, Some(item));
+        }
+        grid
+    }
+
+    #[test]
+    fn shaped_recipes_match_mirrored_and_anywhere_in_the_grid() {
+        let registry = registry();
+        let (log, stick) = (registry.id("log").unwrap(), registry.id("stick").unwrap());
+        let hook = recipe("[shaped]\nresult = torch\npattern = LS\npattern = .S\nL = log\nS = stick", &registry);
+
+        assert!(hook.matches(&grid(&[(0, 0, log), (1, 0, stick), (1, 1, stick)])));
+        assert!(hook.matches(&grid(&[(1, 1, log), (2, 1, stick), (2, 2, stick)])));
+        // Mirrored left to right.
+        assert!(hook.matches(&grid(&[(2, 0, log), (1, 0, stick), (1, 1, stick)])));
+        // Flipped upside down is a different shape.
+        assert!(!hook.matches(&grid(&[(0, 1, log), (1, 1, stick), (1, 0, stick)])));
+        assert!(!hook.matches(&grid(&[(0, 0, log), (1, 0, stick), (1, 1, stick), (2, 2, stick)])));
+    }
+
+    #[test]
+    fn shapeless_recipes_match_in_any_order() {
+        let registry = registry();
+        let (log, dirt) = (registry.id("log").unwrap(), registry.id("dirt").unwrap());
+        let mix = recipe("[shapeless]\nresult = grass 2\ningredients = dirt, log, dirt", &registry);
+        assert_eq!(mix.result, ItemStack::new(registry.id("grass").unwrap(), 2));
+
+        assert!(mix.matches(&grid(&[(0, 0, dirt), (1, 0, dirt), (2, 0, log)])));
+        assert!(mix.matches(&grid(&[(2, 2, dirt), (0, 1, log), (1, 2, dirt)])));
+        assert!(!mix.matches(&grid(&[(0, 0, dirt), (1, 0, log)])));
+        assert!(!mix.matches(&grid(&[(0, 0, dirt), (1, 0, log), (2, 0, log)])));
+    }
+
+    #[test]
+    fn padded_patterns_are_trimmed() {
+        let registry = registry();
+        let (log, stick) = (registry.id("log").unwrap(), registry.id("stick").unwrap());
+        let padded = recipe("[shaped]\nresult = torch 4\npattern = ...\npattern = .L.\npattern = .S.\nL = log\nS = stick", &registry);
+        let trimmed = recipe("[shaped]\nresult = torch 4\npattern = L\npattern = S\nL = log\nS = stick", &registry);
+
+        assert_eq!(padded, trimmed);
+        assert!(padded.matches(&grid(&[(0, 0, log), (0, 1, stick)])));
+    }
+
+    #[test]
+    fn rejects_invalid_definitions() {
+        let registry = registry();
+        for text in [
+            "[shaped]\nresult = torch 0\npattern = L\nL = log",
+            "[shaped]\nresult = torch\npattern = ..\npattern = ..",
+            "[shaped]\nresult = torch\npattern = LL\npattern = L\nL = log",
+            "[shaped]\nresult = torch\npattern = X",
+            "[shapeless]\nresult = nothing\ningredients = log",
+            "[shapeless]\nresult = torch",
+        ] {
+            let error = RecipeBook::parse(text, &registry).err().unwrap();
+            assert_eq!(error.kind(), io::ErrorKind::InvalidData, "{}", text);
+        }
+    }
+
+    #[test]
+    fn parses_the_shipped_recipes() {
+        let registry = registry();
+        let book = RecipeBook::parse(include_str!("../../resources/recipes.txt"), &registry).unwrap();
+        assert_eq!(book.recipes().len(), 10);
+        let sticks = book.recipes().iter().find(|recipe| recipe.result.item == registry.id("stick").unwrap()).unwrap();
+        assert_eq!(sticks.result.count, 4);
+    }
+
+    #[test]
+    fn lists_only_recipes_the_inventory_has_ingredients_for() {
+        let registry = registry();
+        let book = RecipeBook::parse(include_str!("../../resources/recipes.txt"), &registry).unwrap();
+        let mut inventory = Inventory::new(9);
+        inventory.insert(ItemStack::new(registry.id("log").unwrap(), 3), &registry);
+        inventory.insert(ItemStack::new(registry.id("stick").unwrap(), 1), &registry);
+
+        let mut craftable: Vec<&str> = book.craftable(&inventory).iter().map(|recipe| registry.get(recipe.result.item).name.as_str()).collect();
+        craftable.sort();
+        // The pickaxe needs a second stick, the chest and sign more logs.
+        assert_eq!(craftable, vec!["stick", "torch"]);
+
+        inventory.insert(ItemStack::new(registry.id("stick").unwrap(), 1), &registry);
+        assert!(book.craftable(&inventory).iter().any(|recipe| recipe.result.item == registry.id("wooden_pickaxe").unwrap()));
+    }
+}
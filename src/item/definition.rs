@@ -0,0 +1,101 @@
+use std::collections::HashMap;
+
+use crate::world::block::{BlockId, BlockRegistry};
+
+use super::ToolKind;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct ItemId(pub u16);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ItemKind {
+    /// Places the block when used on the world.
+    Block(BlockId),
+    /// Wears down with use and breaks after `durability` uses. Breaks blocks of its kind
+    /// `speed` times faster than a hand.
+    Tool { kind: ToolKind, durability: u16, speed: u16 },
+    /// Only used for crafting.
+    Material,
+}
+
+pub struct ItemType {
+    pub name: String,
+    pub kind: ItemKind,
+    pub max_stack: u32,
+}
+
+impl ItemType {
+    pub fn new(name: &str, kind: ItemKind) -> Self {
+        let max_stack = match kind {
+            ItemKind::Tool { .. } => 1,
+            _ => 64,
+        };
+        Self { name: name.to_owned(), kind, max_stack }
+    }
+
+    pub fn with_max_stack(mut self, max_stack: u32) -> Self {
+        self.max_stack = max_stack.max(1);
+        self
+    }
+}
+
+pub struct ItemRegistry {
+    items: Vec<ItemType>,
+    names: HashMap<String, ItemId>,
+    block_items: HashMap<BlockId, ItemId>,
+}
+
+impl ItemRegistry {
+    pub fn new() -> Self {
+        Self { items: Vec::new(), names: HashMap::new(), block_items: HashMap::new() }
+    }
+
+    /// A block item for every block that is not a fluid, named after the block, followed
+    /// by the standalone items.
+    pub fn with_defaults(blocks: &BlockRegistry) -> Self {
+        let mut registry = Self::new();
+        for (id, block) in blocks.iter() {
+            if block.fluid.is_none() {
+                registry.register(ItemType::new(&block.name, ItemKind::Block(id)));
+            }
+        }
+        registry.register(ItemType::new("stick", ItemKind::Material));
+        registry.register(ItemType::new("wooden_pickaxe", ItemKind::Tool { kind: ToolKind::Pickaxe, durability: 60, speed: 2 }));
+        registry.register(ItemType::new("stone_pickaxe", ItemKind::Tool { kind: ToolKind::Pickaxe, durability: 132, speed: 4 }));
+        registry
+    }
+
+    pub fn register(&mut self, item: ItemType) -> ItemId {
+        let id = ItemId(self.items.len() as u16);
+        self.names.insert(item.name.clone(), id);
+        if let ItemKind::Block(block) = item.kind {
+            self.block_items.entry(block).or_insert(id);
+        }
+        self.items.push(item);
+        id
+    }
+
+    pub fn get(&self, id: ItemId) -> &ItemType {
+        &self.items[id.0 as usize]
+    }
+
+    /// Like `get`, for ids that come from outside, such as save files.
+    pub fn try_get(&self, id: ItemId) -> Option<&ItemType> {
+        self.items.get(id.0 as usize)
+    }
+
+    pub fn id(&self, name: &str) -> Option<ItemId> {
+        self.names.get(name).copied()
+    }
+
+    /// The item a block drops when it is broken.
+    pub fn block_item(&self, block: BlockId) -> Option<ItemId> {
+        self.block_items.get(&block).copied()
+    }
+}
+
+impl Default for ItemRegistry {
+    fn default() -> Self {
+        Self::with_defaults(&BlockRegistry::default())
+    }
+}
@@ -0,0 +1,265 @@
+use std::io;
+
+use crate::encoding::{invalid_data, Reader};
+
+use super::{ItemId, ItemRegistry, ItemStack};
+
+const INVENTORY_FORMAT_VERSION: u8 = 1;
+
+/// A fixed number of slots that each hold one stack or nothing.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Inventory {
+    slots: Vec<Option<ItemStack>>,
+}
+
+impl Inventory {
+    pub fn new(size: usize) -> Self {
+        Self { slots: vec![None; size] }
+    }
+
+    pub fn size(&self) -> usize {
+        self.slots.len()
+    }
+
+    pub fn get(&self, slot: usize) -> Option<&ItemStack> {
+        self.slots.get(slot)?.as_ref()
+    }
+
+    pub fn slots(&self) -> &[Option<ItemStack>] {
+        &self.slots
+    }
+
+    /// Replaces the contents of a slot and returns what was in it.
+    pub fn set(&mut self, slot: usize, stack: Option<ItemStack>) -> Option<ItemStack> {
+        std::mem::replace(self.slots.get_mut(slot)?, stack)
+    }
+
+    /// Adds the stack to the inventory, first merging it into matching stacks and then
+    /// filling empty slots. Returns what did not fit.
+    pub fn insert(&mut self, stack: ItemStack, registry: &ItemRegistry) -> Option<ItemStack> {
+        let max_stack = registry.get(stack.item).max_stack;
+        let mut remaining = Some(stack);
+        for slot in self.slots.iter_mut().flatten() {
+            remaining = slot.merge(remaining?, max_stack);
+        }
+        for slot in self.slots.iter_mut().filter(|slot| slot.is_none()) {
+            let mut stack = remaining?;
+            let rest = stack.split(stack.count.saturating_sub(max_stack));
+            *slot = Some(stack);
+            remaining = rest;
+        }
+        remaining
+    }
+
+    /// Adds the stack to one slot, merging with what is there. Returns what did not fit.
+    pub fn insert_into(&mut self, slot: usize, mut stack: ItemStack, registry: &ItemRegistry) -> Option<ItemStack> {
+        let max_stack = registry.get(stack.item).max_stack;
+        match self.slots.get_mut(slot) {
+            None => Some(stack),
+            Some(Some(existing)) => existing.merge(stack, max_stack),
+            Some(empty) => {
+                let rest = stack.split(stack.count.saturating_sub(max_stack));
+                *empty = Some(stack);
+                rest
+            },
+        }
+    }
+
+    /// Takes up to `count` items out of a slot.
+    pub fn take(&mut self, slot: usize, count: u32) -> Option<ItemStack> {
+        let stack = self.slots.get_mut(slot)?;
+        let taken = stack.as_mut()?.split(count);
+        if stack.is_some_and(|stack| stack.is_empty()) {
+            *stack = None;
+        }
+        taken
+    }
+
+    /// Takes the larger half of a slot, leaving the rest behind.
+    pub fn split(&mut self, slot: usize) -> Option<ItemStack> {
+        let count = self.get(slot)?.count;
+        self.take(slot, count.div_ceil(2))
+    }
+
+    /// Moves a slot into another inventory. Whatever does not fit stays in the slot.
+    /// Returns true if anything was moved.
+    pub fn transfer(&mut self, slot: usize, other: &mut Inventory, registry: &ItemRegistry) -> bool {
+        let Some(stack) = self.set(slot, None) else {
+            return false;
+        };
+        let rest = other.insert(stack, registry);
+        self.slots[slot] = rest;
+        rest != Some(stack)
+    }
+
+    /// Total number of an item over all slots.
+    pub fn count(&self, item: ItemId) -> u32 {
+        self.slots.iter().flatten().filter(|stack| stack.item == item).map(|stack| stack.count).sum()
+    }
+
+    /// Removes `count` of an item, taking from the last slots first. Nothing is removed
+    /// when there are fewer than `count`.
+    pub fn remove(&mut self, item: ItemId, mut count: u32) -> bool {
+        if self.count(item) < count {
+            return false;
+        }
+        for slot in (0..self.slots.len()).rev() {
+            if count == 0 {
+                break;
+            }
+            if self.get(slot).is_some_and(|stack| stack.item == item) {
+                count -= self.take(slot, count).map_or(0, |taken| taken.count);
+            }
+        }
+        true
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.slots.iter().all(|slot| slot.is_none())
+    }
+}
+
+/// Encodes an inventory, storing items by name so saves do not depend on the order
+/// items are registered in.
+pub fn encode_inventory(inventory: &Inventory, registry: &ItemRegistry) -> Vec<u8> {
+    let mut bytes = vec![INVENTORY_FORMAT_VERSION];
+    bytes.extend((inventory.size() as u32).to_le_bytes());
+    for slot in inventory.slots() {
+        // An empty name means an empty slot.
+        let (name, count, damage) = slot.map_or(("", 0, 0), |stack| (registry.get(stack.item).name.as_str(), stack.count, stack.damage));
+        bytes.extend((name.len() as u16).to_le_bytes());
+        bytes.extend(name.as_bytes());
+        bytes.extend(count.to_le_bytes());
+        bytes.extend(damage.to_le_bytes());
+    }
+    bytes
+}
+
+/// Decodes an inventory, checking the items against `registry`.
+pub fn decode_inventory(bytes: &[u8], registry: &ItemRegistry) -> io::Result<Inventory> {
+    let mut reader = Reader::new(bytes);
+    let version = reader.u8()?;
+    if version != INVENTORY_FORMAT_VERSION {
+        return Err(invalid_data(format!("Unsupported inventory format version {}", version)));
+    }
+
+    let size = reader.u32()? as usize;
+    let mut inventory = Inventory::new(0);
+    for _ in 0..size {
+        let item = decode_item_name(&mut reader, registry)?;
+        let count = reader.u32()?;
+        let damage = reader.u16()?;
+        match item {
+            Some(item) if count > 0 => inventory.slots.push(Some(ItemStack { item, count, damage })),
+            _ => inventory.slots.push(None),
+        }
+    }
+    Ok(inventory)
+}
+
+fn decode_item_name(reader: &mut Reader, registry: &ItemRegistry) -> io::Result<Option<ItemId>> {
+    let length = reader.u16()? as usize;
+    let name = std::str::from_utf8(reader.bytes(length)?)
+        .map_err(|_| invalid_data("Item name is not valid UTF-8".to_owned()))?;
+    if name.is_empty() {
+        return Ok(None);
+    }
+    registry.id(name).map(Some).ok_or_else(|| invalid_data(format!("Unknown item '{}'", name)))
+}
+
+#[cfg(test)]
+mod tests {
+    use nalgebra::Vector3;
+
+    use crate::{item::{ItemKind, ItemType}, world::{block::BlockRegistry, chunk::{Chunk, CHUNK_VOLUME}, World}};
+
+    use super::*;
+
+    #[test]
+    fn saved_items_survive_a_different_registration_order() {
+        let blocks = BlockRegistry::with_defaults();
+        let defaults = ItemRegistry::with_defaults(&blocks);
+        let mut inventory = Inventory::new(3);
+        inventory.set(0, Some(ItemStack::new(defaults.id("cobblestone").unwrap(), 5)));
+        inventory.set(2, Some(ItemStack::new(defaults.id("stick").unwrap(), 1)));
+        let bytes = encode_inventory(&inventory, &defaults);
+
+        let mut reordered = ItemRegistry::new();
+        let stick = reordered.register(ItemType::new("stick", ItemKind::Material));
+        let cobblestone = reordered.register(ItemType::new("cobblestone", ItemKind::Block(blocks.id("cobblestone").unwrap())));
+        let decoded = decode_inventory(&bytes, &reordered).unwrap();
+        assert_eq!(decoded.get(0), Some(&ItemStack::new(cobblestone, 5)));
+        assert_eq!(decoded.get(1), None);
+        assert_eq!(decoded.get(2), Some(&ItemStack::new(stick, 1)));
+
+        inventory.set(1, Some(ItemStack::new(defaults.id("wooden_pickaxe").unwrap(), 1)));
+        let error = decode_inventory(&encode_inventory(&inventory, &defaults), &reordered).err().unwrap();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+    }
+
+    /// Pebbles stack to 10, the default tools to 1.
+    fn registry() -> (ItemRegistry, ItemId) {
+        let mut registry = ItemRegistry::with_defaults(&BlockRegistry::with_defaults());
+        let pebble = registry.register(ItemType::new("pebble", ItemKind::Material).with_max_stack(10));
+        (registry, pebble)
+    }
+
+    #[test]
+    fn inserts_into_partial_stacks_before_empty_slots_up_to_the_max_stack() {
+        let (registry, pebble) = registry();
+        let mut inventory = Inventory::new(3);
+        inventory.set(1, Some(ItemStack::new(pebble, 7)));
+        assert_eq!(inventory.insert(ItemStack::new(pebble, 5), &registry), None);
+        assert_eq!(inventory.slots(), [Some(ItemStack::new(pebble, 2)), Some(ItemStack::new(pebble, 10)), None]);
+
+        assert_eq!(inventory.insert(ItemStack::new(pebble, 25), &registry), Some(ItemStack::new(pebble, 7)));
+        assert_eq!(inventory.count(pebble), 30);
+        assert!(inventory.slots().iter().flatten().all(|stack| stack.count == 10));
+
+        let pickaxe = registry.id("wooden_pickaxe").unwrap();
+        let mut tools = Inventory::new(2);
+        assert_eq!(tools.insert(ItemStack::new(pickaxe, 3), &registry), Some(ItemStack::new(pickaxe, 1)));
+        assert_eq!(tools.slots(), [Some(ItemStack::new(pickaxe, 1)), Some(ItemStack::new(pickaxe, 1))]);
+    }
+
+    #[test]
+    fn splits_stacks_and_transfers_what_fits() {
+        let (registry, pebble) = registry();
+        let mut inventory = Inventory::new(2);
+        inventory.set(0, Some(ItemStack::new(pebble, 7)));
+        assert_eq!(inventory.split(0), Some(ItemStack::new(pebble, 4)));
+        assert_eq!(inventory.get(0), Some(&ItemStack::new(pebble, 3)));
+        assert_eq!(inventory.split(1), None);
+
+        let mut chest = Inventory::new(1);
+        chest.set(0, Some(ItemStack::new(pebble, 9)));
+        assert!(inventory.transfer(0, &mut chest, &registry));
+        assert_eq!(inventory.get(0), Some(&ItemStack::new(pebble, 2)));
+        assert_eq!(chest.get(0), Some(&ItemStack::new(pebble, 10)));
+        // Nothing fits any more, so nothing moves.
+        assert!(!inventory.transfer(0, &mut chest, &registry));
+        assert_eq!(inventory.get(0), Some(&ItemStack::new(pebble, 2)));
+    }
+
+    #[test]
+    fn placing_a_block_uses_up_exactly_one_item() {
+        let blocks = BlockRegistry::with_defaults();
+        let items = ItemRegistry::with_defaults(&blocks);
+        let (cobblestone, stick) = (items.id("cobblestone").unwrap(), items.id("stick").unwrap());
+        let mut world = World::new(blocks, items, 0);
+        world.insert_chunk(Vector3::zeros(), Chunk::new(vec![None; CHUNK_VOLUME]));
+        let mut inventory = Inventory::new(2);
+        inventory.set(0, Some(ItemStack::new(cobblestone, 2)));
+        inventory.set(1, Some(ItemStack::new(stick, 1)));
+
+        let position = Vector3::new(1, 1, 1);
+        assert!(world.place_item(&mut inventory, 0, position));
+        assert_eq!(inventory.get(0), Some(&ItemStack::new(cobblestone, 1)));
+        assert_eq!(world.get_voxel(position).copied().flatten().map(|voxel| voxel.block), world.registry().id("cobblestone"));
+        // The spot is taken now, and sticks are no blocks.
+        assert!(!world.place_item(&mut inventory, 0, position));
+        assert!(!world.place_item(&mut inventory, 1, Vector3::new(2, 1, 1)));
+        assert!(world.place_item(&mut inventory, 0, Vector3::new(2, 1, 1)));
+        assert_eq!(inventory.slots(), [None, Some(ItemStack::new(stick, 1))]);
+    }
+}
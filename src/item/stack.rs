@@ -0,0 +1,55 @@
+use super::{ItemId, ItemKind, ItemRegistry};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ItemStack {
+    pub item: ItemId,
+    pub count: u32,
+    /// Uses a tool has taken. Stacks only merge when their damage is equal.
+    pub damage: u16,
+}
+
+impl ItemStack {
+    pub fn new(item: ItemId, count: u32) -> Self {
+        Self { item, count, damage: 0 }
+    }
+
+    pub fn can_merge(&self, other: &ItemStack) -> bool {
+        self.item == other.item && self.damage == other.damage
+    }
+
+    /// Moves as much of `other` into this stack as fits below `max_stack`. Returns what
+    /// is left of `other`, or `None` when all of it was merged.
+    pub fn merge(&mut self, mut other: ItemStack, max_stack: u32) -> Option<ItemStack> {
+        if !self.can_merge(&other) {
+            return Some(other);
+        }
+        let moved = other.count.min(max_stack.saturating_sub(self.count));
+        self.count += moved;
+        other.count -= moved;
+        (other.count > 0).then_some(other)
+    }
+
+    /// Takes up to `count` items off this stack as a new stack. The caller removes this
+    /// stack when it is left empty.
+    pub fn split(&mut self, count: u32) -> Option<ItemStack> {
+        let taken = count.min(self.count);
+        if taken == 0 {
+            return None;
+        }
+        self.count -= taken;
+        Some(ItemStack { count: taken, ..*self })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.count == 0
+    }
+
+    /// Adds a use to a tool. Returns true when the tool broke and the stack should be removed.
+    pub fn wear(&mut self, registry: &ItemRegistry) -> bool {
+        let ItemKind::Tool { durability, .. } = registry.get(self.item).kind else {
+            return false;
+        };
+        self.damage += 1;
+        self.damage >= durability
+    }
+}
@@ -0,0 +1,51 @@
+use crate::world::block::BlockType;
+
+use super::{ItemKind, ItemType};
+
+/// The group of blocks a tool is made for.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ToolKind {
+    Pickaxe,
+    Axe,
+    Shovel,
+}
+
+/// Seconds it takes to break `block` while holding `tool`, or with an empty hand when
+/// `tool` is `None`. Tools only speed up the blocks they are made for.
+pub fn breaking_time(block: &BlockType, tool: Option<&ItemType>) -> f32 {
+    let speed = match tool.map(|tool| tool.kind) {
+        Some(ItemKind::Tool { kind, speed, .. }) if block.tool == Some(kind) => speed.max(1),
+        _ => 1,
+    };
+    block.hardness / speed as f32
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{item::ItemRegistry, world::block::BlockRegistry};
+
+    use super::*;
+
+    #[test]
+    fn matching_tools_divide_the_hardness_by_their_speed() {
+        let blocks = BlockRegistry::with_defaults();
+        let items = ItemRegistry::with_defaults(&blocks);
+        let block = |name| blocks.get(blocks.id(name).unwrap());
+        let item = |name| items.get(items.id(name).unwrap());
+
+        let cobblestone = block("cobblestone");
+        assert_eq!(breaking_time(cobblestone, None), cobblestone.hardness);
+        assert_eq!(breaking_time(cobblestone, Some(item("wooden_pickaxe"))), cobblestone.hardness / 2.0);
+        assert_eq!(breaking_time(cobblestone, Some(item("stone_pickaxe"))), cobblestone.hardness / 4.0);
+        // Items that are not tools break blocks like a hand.
+        assert_eq!(breaking_time(cobblestone, Some(item("stick"))), cobblestone.hardness);
+
+        // A pickaxe is no faster than a hand on blocks made for another tool.
+        let log = block("log");
+        assert_eq!(log.tool, Some(ToolKind::Axe));
+        assert_eq!(breaking_time(log, Some(item("stone_pickaxe"))), log.hardness);
+
+        assert_eq!(breaking_time(block("torch"), None), 0.0);
+        assert_eq!(breaking_time(block("torch"), Some(item("stone_pickaxe"))), 0.0);
+    }
+}
@@ -1,11 +1,31 @@
+#[cfg(feature = "client")]
 pub mod asset;
 
 pub mod camera;
 
+pub mod command;
+
+pub mod ecs;
+
+mod encoding;
+
+pub mod item;
+
 pub mod math;
 
+pub mod network;
+
+pub mod physics;
+
+#[cfg(feature = "client")]
 pub mod rendering;
 
+pub mod server;
+
+pub mod simulation;
+
 pub mod transform;
 
+pub mod ui;
+
 pub mod world;
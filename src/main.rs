@@ -1,10 +1,23 @@
-use std::{path::Path, time::Instant};
+use std::{collections::HashMap, path::Path, thread, time::{Duration, Instant}};
 
 use glfw::Context;
 use gl;
-use nalgebra::{Isometry3, Point3};
+use nalgebra::{Isometry3, Point3, Vector3};
 
-use voxel_game::{asset::{Shader, Texture}, camera::Camera, rendering::{MeshRenderer, Mesh}, world::{chunk::{Chunk, CHUNK_SIZE_X, CHUNK_SIZE_Y, CHUNK_SIZE_Z}, voxel::Voxel}};
+use voxel_game::{asset::{AssetManager, Shader}, camera::Camera, command::{spawn_stdin_reader, CommandContext, CommandRegistry, CommandSender, Permission}, network::{Client, Discovery, ANNOUNCE_INTERVAL}, ecs::{components::{self, Model, Name}, player::{BreakProgress, GameMode, PlayerAction, PlayerData, PlayerInput, Target, EYE_HEIGHT}, Entities, Entity}, physics::PhysicsBody, rendering::{primitives, ChunkMesh, ConsoleRenderer, CrackRenderer, MeshRenderer, OutlineRenderer, OverlayRenderer, SkyRenderer, TextRenderer, text_width}, simulation::{Simulation, TICK_DELTA}, transform::Transform, ui::Console, world::{chunk::{CHUNK_SIZE_X, CHUNK_SIZE_Y, CHUNK_SIZE_Z}, to_chunk_coordinates, to_world_coordinates, time::WorldClock}};
+
+const WORLD_PATH: &str = "saves/world";
+const DEFAULT_NAME: &str = "Player";
+/// How long to listen for servers on the local network, a bit more than one announcement.
+const DISCOVERY_WAIT: Duration = Duration::from_millis(ANNOUNCE_INTERVAL.as_millis() as u64 + 500);
+const RENDER_DISTANCE: f32 = 48.0;
+const FADE_DISTANCE: f32 = 8.0;
+/// Chunks loaded around the player in local games, enough to cover `RENDER_DISTANCE`.
+const LOAD_DISTANCE: i32 = 6;
+/// Radians the view turns per pixel of mouse movement.
+const MOUSE_SENSITIVITY: f32 = 0.003;
+/// Distance of the frame rate from the top right corner of the screen in pixels.
+const FPS_MARGIN: f32 = 6.0;
 
 struct WindowSettings {
     wireframe: bool,
@@ -27,12 +40,45 @@ impl WindowSettings {
     }
 }
 
+/// Where the cursor was at the last mouse movement, so movements can be turned into rotations.
+struct MouseLook {
+    last_position: Option<(f64, f64)>,
+}
+
 fn print_usage() {
+    println!("Usage: voxel_game [server address | --lan] [name]");
+    println!("Plays the local world, or joins a server when an address is given. --lan joins");
+    println!("the first server found on the local network.");
     println!("Controls:");
+    println!("WASD - move");
+    println!("Space - jump, or rise when flying");
+    println!("Left Shift - sink when flying");
+    println!("Mouse - look around");
+    println!("Left mouse button - break block");
+    println!("Right mouse button - place block");
+    println!("1-9 - select inventory slot");
+    println!("G - cycle game mode");
     println!("Y - toggle wireframe mode");
+    println!("P - pause or resume time");
+    println!("N - set time to noon");
+    println!("M - set time to midnight");
+    println!("` - open the console for chat messages and /commands, also read from this terminal");
 }
 
 fn main() {
+    let mut args = std::env::args().skip(1);
+    let address = match args.next().as_deref() {
+        Some("--lan") => match discover_server() {
+            Some(address) => Some(address),
+            None => {
+                eprintln!("No server found on the local network");
+                return;
+            },
+        },
+        address => address.map(str::to_owned),
+    };
+    let remote = address.map(|address| (address, args.next().unwrap_or_else(|| DEFAULT_NAME.to_owned())));
+
     let mut glfw = glfw::init(glfw::fail_on_errors).unwrap();
     glfw.window_hint(glfw::WindowHint::ContextVersion(3, 3));
     glfw.window_hint(glfw::WindowHint::OpenGlProfile(glfw::OpenGlProfileHint::Core));
@@ -47,6 +93,11 @@ fn main() {
 
     window.make_current();
     window.set_key_polling(true);
+    window.set_cursor_pos_polling(true);
+    window.set_mouse_button_polling(true);
+    window.set_char_polling(true);
+    window.set_scroll_polling(true);
+    window.set_cursor_mode(glfw::CursorMode::Disabled);
     gl::load_with(|ptr| window.get_proc_address(ptr) as *const _);
 
     unsafe {
@@ -59,40 +110,72 @@ fn main() {
 
     let mut window_settings = WindowSettings::new();
 
+    let mut simulation = match remote {
+        Some(_) => Simulation::replica(),
+        None => Simulation::open(Path::new(WORLD_PATH)),
+    };
+
     // let texture = Texture::new(&Path::new("resources/texture/cobblestone.png"));
-    let shader = Shader::from_file("resources/shader/textured.vert", "resources/shader/textured.frag");
-    
-    let mut chunks: Vec<Vec<Chunk>> = Vec::new();
-    for _ in 0..4 {
-        let mut chunk_row = Vec::new();
-        for _ in 0..4 {
-            // let mut voxels = vec![None; (CHUNK_SIZE_X*CHUNK_SIZE_Y*CHUNK_SIZE_Z) as usize];
-            // voxels[0] = Some(Voxel{});
-            // voxels[1] = Some(Voxel{});
-            // voxels[CHUNK_SIZE_X as usize] = Some(Voxel{});
-            // voxels[(CHUNK_SIZE_X*CHUNK_SIZE_Y) as usize] = Some(Voxel{});
-            // let chunk = Chunk::new(voxels);
-            let chunk = Chunk::new(vec![Some(Voxel{}); (CHUNK_SIZE_X*CHUNK_SIZE_Y*CHUNK_SIZE_Z) as usize]);
-            chunk_row.push(chunk);
-        }
-        chunks.push(chunk_row);
+    let shader = Shader::from_file("resources/shader/chunk.vert", "resources/shader/chunk.frag");
+
+    let mut client = match &remote {
+        Some((address, name)) => match Client::connect(address.as_str(), name, &mut simulation) {
+            Ok(client) => Some(client),
+            Err(error) => {
+                eprintln!("Failed to join {}: {}", address, error);
+                return;
+            },
+        },
+        None => None,
+    };
+    let player = match &client {
+        Some(client) => client.player().expect("connecting waits for the login"),
+        None => {
+            let player_data = simulation.load_player(DEFAULT_NAME).unwrap_or_else(|error| {
+                eprintln!("Failed to load player: {}", error);
+                PlayerData::new()
+            });
+            let player = components::spawn_player(&mut simulation.entities, simulation.world.spawn_point(), player_data);
+            simulation.entities.insert(player, Name(DEFAULT_NAME.to_owned()));
+            player
+        },
+    };
+    if let Some(input) = simulation.entities.get_mut::<PlayerInput>(player) {
+        // Face the middle of the generated area from the spawn point.
+        input.yaw = -3.0 * std::f32::consts::FRAC_PI_4;
     }
+    let mut mouse_look = MouseLook { last_position: None };
+    // Remote games run commands on the server instead.
+    let commands = CommandRegistry::with_defaults();
+    let stdin = spawn_stdin_reader();
+    let mut console = Console::new();
 
-    let meshes: Vec<Vec<Mesh>> = chunks
-        .iter()
-        .map(|chunk_row| 
-            chunk_row
-                .iter()
-                .map(|chunk| {
-                    let texture = Texture::new(&Path::new("resources/texture/cobblestone.png")).unwrap();
-                    chunk.generate_mesh(texture)
-                }).collect()).collect();
+    let mut assets = AssetManager::new();
+    let texture = assets.load_texture("cobblestone.png").unwrap();
+    let mut meshes: HashMap<Vector3<i32>, ChunkMesh> = HashMap::new();
+    let block_mesh = primitives::cube_mesh(texture.clone());
 
     // let chunk = Chunk::new(voxels);
-    // let chunk_mesh = chunk.generate_mesh(texture);
-    let renderer = MeshRenderer::new(shader);
+    // let chunk_mesh = ChunkMesh::generate(&chunk, world.registry(), texture);
+    let mut renderer = MeshRenderer::new(shader);
+    renderer.set_fog(simulation.metadata.fog);
+    renderer.set_render_distance(RENDER_DISTANCE, FADE_DISTANCE);
+
+    let sky_renderer = match assets.load_cubemap("skybox") {
+        Ok(cubemap) => SkyRenderer::cubemap(cubemap),
+        Err(_) => SkyRenderer::procedural(),
+    };
+
+    let outline_renderer = OutlineRenderer::new(Shader::from_file("resources/shader/default.vert", "resources/shader/default.frag"));
+    let crack_texture = assets.load_texture("crack.png").unwrap();
+    let crack_renderer = CrackRenderer::new(Shader::from_file("resources/shader/crack.vert", "resources/shader/crack.frag"), crack_texture);
 
-    let camera = Camera::new_look_at(
+    let overlay_renderer = OverlayRenderer::new(Shader::from_file("resources/shader/overlay.vert", "resources/shader/overlay.frag"), screen_width, screen_height);
+    let (font, font_atlas) = assets.load_font("default.fnt").unwrap();
+    let mut text_renderer = TextRenderer::new(Shader::from_file("resources/shader/text.vert", "resources/shader/text.frag"), font, font_atlas, screen_width, screen_height);
+    let console_renderer = ConsoleRenderer::new(overlay_renderer, text_renderer.font().line_height as f32);
+
+    let mut camera = Camera::new_look_at(
         screen_width, screen_height,
         &Point3::new(4.0, 12.0, -8.0),
         // &Point3::new(-2.0, 4.0, -3.0),
@@ -104,36 +187,273 @@ fn main() {
 
     let mut instant = Instant::now();
     let mut fps = 0.0;
+    let mut tick_time = 0.0;
     while !window.should_close() {
         let elapsed = instant.elapsed();
         let delta = elapsed.as_secs_f32();
         fps = 0.95 * fps + 0.05 * (1.0/delta);
-        window.set_title(format!("{}", fps).as_str());
         instant = Instant::now();
 
         glfw.poll_events();
         for (_, event) in glfw::flush_messages(&events) {
-            glfw_handle_event(&mut window, event, &mut window_settings);
+            let was_open = console.is_open();
+            if let Some(line) = console_handle_event(&mut console, &event, console_renderer.visible_line_count()) {
+                submit_console_line(&line, &mut console, client.as_mut(), &commands, &mut simulation, player);
+            }
+            if !was_open && !console.is_open() {
+                let command = glfw_handle_event(&mut window, event, &mut window_settings, &simulation.clock, &mut simulation.entities, player, &mut mouse_look);
+                if let Some(line) = command {
+                    submit_console_line(&line, &mut console, client.as_mut(), &commands, &mut simulation, player);
+                }
+                continue;
+            }
+            // The player stops while typing, and the view does not jump once the console closes.
+            if !was_open {
+                if let Some(input) = simulation.entities.get_mut::<PlayerInput>(player) {
+                    for action in PlayerAction::ALL {
+                        input.release(action);
+                    }
+                }
+            }
+            mouse_look.last_position = None;
+        }
+        console.end_events();
+
+        while let Ok(line) = stdin.try_recv() {
+            submit_console_line(line.trim(), &mut console, client.as_mut(), &commands, &mut simulation, player);
+        }
+        if let Some(line) = console.take_completion_request() {
+            match &mut client {
+                Some(client) => client.request_completions(&line),
+                None => {
+                    let sender = CommandSender::player(DEFAULT_NAME, player, Permission::Console);
+                    let candidates = commands.complete(&line, &CommandContext { simulation: &mut simulation, sender: &sender });
+                    console.set_suggestions(&line, candidates);
+                },
+            }
         }
 
+        tick_time += delta;
+        while tick_time >= TICK_DELTA {
+            match &mut client {
+                Some(connection) => if let Err(error) = connection.tick(&mut simulation) {
+                    eprintln!("Disconnected: {}", error);
+                    window.set_should_close(true);
+                    break;
+                },
+                None => {
+                    if let Some(body) = simulation.entities.get::<PhysicsBody>(player) {
+                        let (centre, _) = to_chunk_coordinates(body.position.map(|c| c.floor() as i32));
+                        simulation.load_chunks_around(&[centre], LOAD_DISTANCE);
+                    }
+                    simulation.tick();
+                },
+            }
+            tick_time -= TICK_DELTA;
+        }
+        if let Some(client) = &mut client {
+            for line in client.take_chat() {
+                log(&mut console, &line);
+            }
+            if let Some((line, candidates)) = client.take_completions() {
+                console.set_suggestions(&line, candidates);
+            }
+        }
+        renderer.set_skylight(simulation.clock.skylight());
+
+        if let (Some(body), Some(input)) = (simulation.entities.get::<PhysicsBody>(player), simulation.entities.get::<PlayerInput>(player)) {
+            let eye = Point3::from(body.position + Vector3::y() * EYE_HEIGHT);
+            camera.look_from(&eye, &input.look_direction());
+        }
+
+        let sky_color = simulation.clock.sky_color();
+        renderer.set_fog_color(sky_color);
         unsafe {
+            gl::ClearColor(sky_color.x, sky_color.y, sky_color.z, 1.0);
             gl::Clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT);
         }
 
-        for (z, mesh_row) in meshes.iter().enumerate() {
-            for (x, mesh) in mesh_row.iter().enumerate() {
-                renderer.render(&Isometry3::translation((x*CHUNK_SIZE_X as usize) as f32, 0.0, (z*CHUNK_SIZE_Z as usize) as f32), mesh, &camera);
+        sky_renderer.render(&camera, &simulation.clock);
+
+        for position in simulation.world.take_dirty_chunks() {
+            match ChunkMesh::generate(&simulation.world, position, texture.clone()) {
+                Some(mesh) => {
+                    meshes.insert(position, mesh);
+                },
+                None => {
+                    meshes.remove(&position);
+                },
+            }
+        }
+
+        let camera_position = camera.position();
+        let chunk_extent = Vector3::new(CHUNK_SIZE_X as f32, CHUNK_SIZE_Y as f32, CHUNK_SIZE_Z as f32);
+        let mut translucent_chunks = Vec::new();
+        for (position, mesh) in meshes.iter_mut() {
+            let transform = Isometry3::from(to_world_coordinates(*position, Vector3::zeros()).cast::<f32>());
+            let centre = transform * Point3::from(chunk_extent / 2.0);
+            let distance = nalgebra::distance(&centre, &camera_position);
+            if distance - chunk_extent.norm() / 2.0 > RENDER_DISTANCE {
+                continue;
             }
+            renderer.render(&transform, &mesh.opaque, &camera);
+            if !mesh.translucent.is_empty() {
+                translucent_chunks.push((distance, transform, &mut mesh.translucent));
+            }
+        }
+
+        // Every model is drawn as a block until there are more meshes.
+        for (entity, _) in simulation.entities.iter::<Model>() {
+            let Some(transform) = simulation.entities.get::<Transform>(entity) else {
+                continue;
+            };
+            let corner = transform.position() - Vector3::new(0.5, 0.0, 0.5);
+            renderer.render(&Isometry3::translation(corner.x, corner.y, corner.z), &block_mesh, &camera);
+        }
+
+        let can_interact = simulation.entities.get::<GameMode>(player).is_some_and(|mode| mode.can_interact());
+        if let Some(hit) = simulation.entities.get::<Target>(player).and_then(|target| target.hit).filter(|_| can_interact) {
+            outline_renderer.render(hit.position, &camera);
+            if let Some(progress) = simulation.entities.get::<BreakProgress>(player).filter(|progress| progress.target() == Some(hit.position)) {
+                crack_renderer.render(hit.position, hit.normal, progress.fraction(), &camera);
+            }
+        }
+
+        translucent_chunks.sort_by(|(a, _, _), (b, _, _)| b.total_cmp(a));
+        for (_, transform, mesh) in translucent_chunks {
+            mesh.sort(&transform.inverse_transform_point(&camera_position));
+            renderer.render_translucent(&transform, &mesh.mesh, &camera);
         }
 
+        let fps_text = format!("{:.0} FPS", fps);
+        let fps_width = text_width(text_renderer.font(), &fps_text) as f32;
+        text_renderer.queue(&fps_text, screen_width as f32 - fps_width - FPS_MARGIN, FPS_MARGIN, Vector3::new(1.0, 1.0, 1.0), None);
+        console_renderer.render(&console, &mut text_renderer);
+        text_renderer.render();
+
         window.swap_buffers();
     }
+
+    // The server keeps the player and the world of remote games.
+    if client.is_some() {
+        return;
+    }
+    if let Some(data) = PlayerData::from_entity(&simulation.entities, player) {
+        if let Err(error) = simulation.save_player(DEFAULT_NAME, &data) {
+            eprintln!("Failed to save player: {}", error);
+        }
+    }
+    if let Err(error) = simulation.save() {
+        eprintln!("Failed to save world: {}", error);
+    }
+}
+
+/// Sends a chat message or command to the server, or handles it in the local game, where
+/// the player may run every command.
+fn submit_console_line(line: &str, console: &mut Console, client: Option<&mut Client>, commands: &CommandRegistry, simulation: &mut Simulation, player: Entity) {
+    if line.is_empty() {
+        return;
+    }
+    if let Some(client) = client {
+        client.send_chat(line);
+        return;
+    }
+    if !line.starts_with('/') {
+        log(console, &format!("<{}> {}", DEFAULT_NAME, line));
+        return;
+    }
+    let sender = CommandSender::player(DEFAULT_NAME, player, Permission::Console);
+    match commands.execute(line, &mut CommandContext { simulation, sender: &sender }) {
+        Ok(feedback) => log(console, &feedback),
+        Err(error) => log(console, &error.to_string()),
+    }
+}
+
+/// Shows output in the console and the terminal.
+fn log(console: &mut Console, text: &str) {
+    println!("{}", text);
+    console.log(text);
+}
+
+/// Handles the keys that open and close the console, and every event while it is open.
+/// Returns a line once it is submitted.
+fn console_handle_event(console: &mut Console, event: &glfw::WindowEvent, page: usize) -> Option<String> {
+    use glfw::WindowEvent as Event;
+    use glfw::Key;
+    use glfw::Action;
+
+    match *event {
+        Event::Key(Key::GraveAccent, _, Action::Press, _) => console.toggle(),
+        _ if !console.is_open() => {},
+        Event::Key(Key::Escape, _, Action::Press, _) => console.set_open(false),
+        Event::Key(key, _, Action::Press | Action::Repeat, _) => match key {
+            Key::Enter | Key::KpEnter => return console.submit(),
+            Key::Backspace => console.backspace(),
+            Key::Delete => console.delete(),
+            Key::Left => console.cursor_left(),
+            Key::Right => console.cursor_right(),
+            Key::Home => console.cursor_home(),
+            Key::End => console.cursor_end(),
+            Key::Up => console.history_previous(),
+            Key::Down => console.history_next(),
+            Key::PageUp => console.scroll_up(page),
+            Key::PageDown => console.scroll_down(page),
+            Key::Tab => console.complete(),
+            _ => {},
+        },
+        Event::Char(c) => console.type_char(c),
+        Event::Scroll(_, y) if y > 0.0 => console.scroll_up(y.ceil() as usize),
+        Event::Scroll(_, y) if y < 0.0 => console.scroll_down((-y).ceil() as usize),
+        _ => {},
+    }
+    None
+}
+
+/// Listens for servers on the local network for a moment, lists them and returns the
+/// address of the first one this build can join.
+fn discover_server() -> Option<String> {
+    let mut discovery = match Discovery::new() {
+        Ok(discovery) => discovery,
+        Err(error) => {
+            eprintln!("Failed to listen for servers: {}", error);
+            return None;
+        },
+    };
+    println!("Looking for servers on the local network");
+    let start = Instant::now();
+    while start.elapsed() < DISCOVERY_WAIT {
+        if let Err(error) = discovery.poll() {
+            eprintln!("Failed to listen for servers: {}", error);
+            return None;
+        }
+        thread::sleep(Duration::from_millis(50));
+    }
+    for server in discovery.servers() {
+        let announcement = &server.announcement;
+        let compatibility = if announcement.is_compatible() { "" } else { ", incompatible version" };
+        println!("{} - {} ({}/{} players{})", server.address, announcement.motd, announcement.players, announcement.max_players, compatibility);
+    }
+    discovery.servers().into_iter().find(|server| server.announcement.is_compatible()).map(|server| server.address.to_string())
 }
 
-fn glfw_handle_event(window: &mut glfw::Window, event: glfw::WindowEvent, window_settings: &mut WindowSettings) {
+/// Handles an event for the game. Keys that change the world return the command that does
+/// it, so remote games ask the server instead of changing their replica.
+fn glfw_handle_event(window: &mut glfw::Window, event: glfw::WindowEvent, window_settings: &mut WindowSettings, clock: &WorldClock, entities: &mut Entities, player: Entity, mouse_look: &mut MouseLook) -> Option<String> {
     use glfw::WindowEvent as Event;
     use glfw::Key;
     use glfw::Action;
+    use glfw::MouseButton;
+
+    let action_for_key = |key| match key {
+        Key::W => Some(PlayerAction::Forward),
+        Key::S => Some(PlayerAction::Back),
+        Key::A => Some(PlayerAction::Left),
+        Key::D => Some(PlayerAction::Right),
+        Key::Space => Some(PlayerAction::Jump),
+        Key::LeftShift => Some(PlayerAction::Descend),
+        _ => None,
+    };
+    let slot_keys = [Key::Num1, Key::Num2, Key::Num3, Key::Num4, Key::Num5, Key::Num6, Key::Num7, Key::Num8, Key::Num9];
 
     match event {
         Event::Key(Key::Escape, _, Action::Press, _) => {
@@ -142,6 +462,54 @@ fn glfw_handle_event(window: &mut glfw::Window, event: glfw::WindowEvent, window
         Event::Key(Key::Y, _, Action::Press, _) => {
             window_settings.toggle_wireframe();
         }
+        Event::Key(Key::P, _, Action::Press, _) => {
+            return Some(if clock.is_paused() { "/time resume" } else { "/time pause" }.to_owned());
+        }
+        Event::Key(Key::N, _, Action::Press, _) => {
+            return Some("/time set noon".to_owned());
+        }
+        Event::Key(Key::M, _, Action::Press, _) => {
+            return Some("/time set midnight".to_owned());
+        }
+        Event::Key(Key::G, _, Action::Press, _) => {
+            let mode = entities.get::<GameMode>(player).copied().unwrap_or(GameMode::Survival);
+            let next = GameMode::ALL[(GameMode::ALL.iter().position(|m| *m == mode).unwrap() + 1) % GameMode::ALL.len()];
+            return Some(format!("/gamemode {}", next));
+        }
+        Event::Key(key, _, Action::Press, _) if slot_keys.contains(&key) => {
+            if let Some(input) = entities.get_mut::<PlayerInput>(player) {
+                input.selected_slot = slot_keys.iter().position(|k| *k == key).unwrap();
+            }
+        }
+        Event::Key(key, _, action, _) if action_for_key(key).is_some() => {
+            if let Some(input) = entities.get_mut::<PlayerInput>(player) {
+                let player_action = action_for_key(key).unwrap();
+                match action {
+                    Action::Release => input.release(player_action),
+                    _ => input.press(player_action),
+                }
+            }
+        }
+        Event::MouseButton(button, action, _) => {
+            let player_action = match button {
+                MouseButton::Button1 => PlayerAction::Break,
+                MouseButton::Button2 => PlayerAction::Place,
+                _ => return None,
+            };
+            if let Some(input) = entities.get_mut::<PlayerInput>(player) {
+                match action {
+                    Action::Release => input.release(player_action),
+                    _ => input.press(player_action),
+                }
+            }
+        }
+        Event::CursorPos(x, y) => {
+            if let (Some((last_x, last_y)), Some(input)) = (mouse_look.last_position, entities.get_mut::<PlayerInput>(player)) {
+                input.rotate(-(x - last_x) as f32 * MOUSE_SENSITIVITY, -(y - last_y) as f32 * MOUSE_SENSITIVITY);
+            }
+            mouse_look.last_position = Some((x, y));
+        }
         _ => {},
     }
+    None
 }
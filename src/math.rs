@@ -1,2 +1,5 @@
 mod direction;
 pub use direction::Direction;
+
+mod random;
+pub use random::Random;
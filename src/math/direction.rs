@@ -1,6 +1,6 @@
 use nalgebra::Vector3;
 
-#[derive(PartialEq, Eq)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum Direction {
     Left, // Positive X
     Right, // Negative X
@@ -11,6 +11,15 @@ pub enum Direction {
 }
 
 impl Direction {
+    pub const ALL: [Direction; 6] = [
+        Direction::Left,
+        Direction::Right,
+        Direction::Up,
+        Direction::Down,
+        Direction::Back,
+        Direction::Front,
+    ];
+
     pub fn facing(&self) -> Vector3<i32> {
         match self {
             Direction::Left => Vector3::x(),
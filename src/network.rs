@@ -0,0 +1,17 @@
+mod client;
+pub use client::Client;
+
+mod connection;
+pub use connection::Connection;
+
+mod discovery;
+pub use discovery::{Announcement, Announcer, DiscoveredServer, Discovery, ANNOUNCE_INTERVAL, DISCOVERY_PORT, DISCOVERY_TIMEOUT};
+
+mod local;
+pub use local::{LinkConditions, LocalNetwork, LocalTransport};
+
+mod protocol;
+pub use protocol::{decode_message, encode_frame, encode_message, FrameDecoder, Message, MAX_MESSAGE_SIZE, PROTOCOL_VERSION};
+
+mod transport;
+pub use transport::Transport;
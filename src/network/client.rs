@@ -0,0 +1,247 @@
+use std::{collections::{HashMap, VecDeque}, io, net::ToSocketAddrs, thread, time::{Duration, Instant}};
+
+use nalgebra::{UnitQuaternion, Vector3};
+
+use crate::{ecs::{components, player::{self, InputState, PlayerData, PlayerInput}, systems, Entity, Schedule, SystemContext}, physics::PhysicsBody, simulation::{Simulation, TICK_DELTA}, transform::Transform, world::{storage::decode_chunk_voxels, to_world_coordinates}};
+
+use super::{protocol::{Message, PROTOCOL_VERSION}, Connection, Transport};
+
+/// How long the client waits for the server to accept the login.
+const LOGIN_TIMEOUT: Duration = Duration::from_secs(10);
+/// Most inputs kept for replaying. Older ones are forgotten when the server stops
+/// answering, which only makes corrections less smooth.
+const MAX_PENDING_INPUTS: usize = 256;
+
+/// The connection of a game client to a server. Keeps a replica `Simulation` in sync
+/// with the server and sends the player's input.
+///
+/// The player is moved locally as soon as the input is sent. Whenever the server reports
+/// where its inputs took the player, the client starts over from there and replays the
+/// inputs the server has not applied yet.
+pub struct Client {
+    connection: Box<dyn Transport>,
+    /// The local player entity, once the server accepted the login.
+    player: Option<Entity>,
+    /// The server's id of the player entity, which is not replicated like other entities.
+    server_player: u64,
+    /// Local entities by the id the server knows them by.
+    entities: HashMap<u64, Entity>,
+    /// Number of the last input sent.
+    sequence: u32,
+    /// Inputs sent but not yet applied by the server, oldest first.
+    pending: VecDeque<(u32, InputState)>,
+    prediction: Schedule,
+    prediction_error: f32,
+    /// Chat lines received since the last `take_chat`.
+    chat: Vec<String>,
+    /// The latest completions, with the line they complete.
+    completions: Option<(String, Vec<String>)>,
+}
+
+impl Client {
+    /// Connects over TCP and logs in as `name`, spawning the player in `simulation` once
+    /// the server accepted. `simulation` should be a replica.
+    pub fn connect(address: impl ToSocketAddrs, name: &str, simulation: &mut Simulation) -> io::Result<Self> {
+        let mut client = Self::login(Connection::connect(address)?, name);
+        let start = Instant::now();
+        while client.player.is_none() {
+            client.receive(simulation)?;
+            client.connection.flush()?;
+            if start.elapsed() > LOGIN_TIMEOUT {
+                return Err(io::ErrorKind::TimedOut.into());
+            }
+            thread::sleep(Duration::from_millis(1));
+        }
+        Ok(client)
+    }
+
+    /// Starts logging in as `name` over any transport. The player is spawned during the
+    /// `tick` that receives the server's answer.
+    pub fn login(transport: impl Transport + 'static, name: &str) -> Self {
+        let mut connection: Box<dyn Transport> = Box::new(transport);
+        connection.send(&Message::Handshake { version: PROTOCOL_VERSION });
+        connection.send(&Message::Login { name: name.to_owned() });
+        Self {
+            connection,
+            player: None,
+            server_player: 0,
+            entities: HashMap::new(),
+            sequence: 0,
+            pending: VecDeque::new(),
+            prediction: systems::prediction_schedule(),
+            prediction_error: 0.0,
+            chat: Vec::new(),
+            completions: None,
+        }
+    }
+
+    /// The local player, `None` until the server accepted the login.
+    pub fn player(&self) -> Option<Entity> {
+        self.player
+    }
+
+    /// Inputs the server has not confirmed yet.
+    pub fn pending_inputs(&self) -> usize {
+        self.pending.len()
+    }
+
+    /// Distance the player was moved by the last correction from the server.
+    pub fn prediction_error(&self) -> f32 {
+        self.prediction_error
+    }
+
+    /// Sends a chat message, or a command when it starts with `/`. Its feedback arrives
+    /// as chat.
+    pub fn send_chat(&mut self, text: &str) {
+        self.connection.send(&Message::Chat { text: text.to_owned() });
+    }
+
+    /// Asks the server to complete the last word of a partly typed command, see
+    /// `take_completions`.
+    pub fn request_completions(&mut self, line: &str) {
+        self.connection.send(&Message::CompletionRequest { line: line.to_owned() });
+    }
+
+    /// Chat lines received since the last call, oldest first.
+    pub fn take_chat(&mut self) -> Vec<String> {
+        std::mem::take(&mut self.chat)
+    }
+
+    /// The completions received since the last call, with the line they complete.
+    pub fn take_completions(&mut self) -> Option<(String, Vec<String>)> {
+        self.completions.take()
+    }
+
+    /// Applies everything the server sent, sends the player's input and advances the
+    /// replica by one tick, which moves the player ahead of the server. Fails once the
+    /// server disconnected.
+    pub fn tick(&mut self, simulation: &mut Simulation) -> io::Result<()> {
+        self.receive(simulation)?;
+        if let Some(input) = self.player.and_then(|player| simulation.entities.get::<PlayerInput>(player)) {
+            self.sequence += 1;
+            let state = input.state();
+            self.connection.send(&Message::Input { sequence: self.sequence, state });
+            self.pending.push_back((self.sequence, state));
+            if self.pending.len() > MAX_PENDING_INPUTS {
+                self.pending.pop_front();
+            }
+        }
+        self.connection.flush()?;
+        simulation.tick();
+        Ok(())
+    }
+
+    fn receive(&mut self, simulation: &mut Simulation) -> io::Result<()> {
+        while let Some(message) = self.connection.receive()? {
+            self.handle(message, simulation)?;
+        }
+        if self.connection.is_closed() {
+            return Err(io::Error::new(io::ErrorKind::ConnectionAborted, "Server closed the connection"));
+        }
+        Ok(())
+    }
+
+    fn handle(&mut self, message: Message, simulation: &mut Simulation) -> io::Result<()> {
+        match message {
+            Message::LoginAccepted { player, position, game_mode, time } => {
+                if self.player.is_some() {
+                    return Ok(());
+                }
+                simulation.clock.set_ticks(time);
+                let data = PlayerData { game_mode, ..PlayerData::new() };
+                self.player = Some(components::spawn_player(&mut simulation.entities, position, data));
+                self.server_player = player;
+            },
+            Message::ChunkData { position, data } => {
+                let chunk = decode_chunk_voxels(&data, simulation.world.registry())?;
+                simulation.world.insert_chunk(position, chunk);
+            },
+            Message::ChunkUnload { position } => {
+                simulation.world.remove_chunk(position);
+            },
+            Message::BlockChanges { chunk, changes } => {
+                for (local, voxel) in changes {
+                    simulation.world.set_voxel_without_updates(to_world_coordinates(chunk, local), voxel);
+                }
+            },
+            Message::EntitySpawn { entity, model, position } => {
+                if entity == self.server_player {
+                    return Ok(());
+                }
+                if let Some(previous) = self.entities.remove(&entity) {
+                    simulation.entities.despawn(previous);
+                }
+                let local = simulation.entities.spawn();
+                simulation.entities.insert(local, Transform::new(position, UnitQuaternion::identity()));
+                if let Some(model) = model {
+                    simulation.entities.insert(local, model);
+                }
+                self.entities.insert(entity, local);
+            },
+            Message::EntityMove { entity, position } => {
+                let local = self.entities.get(&entity).copied();
+                if let Some(transform) = local.and_then(|local| simulation.entities.get_mut::<Transform>(local)) {
+                    transform.set_position(position);
+                }
+            },
+            Message::EntityDespawn { entity } => {
+                if let Some(local) = self.entities.remove(&entity) {
+                    simulation.entities.despawn(local);
+                }
+            },
+            Message::PlayerState { input, position, velocity, on_ground } => self.reconcile(input, position, velocity, on_ground, simulation),
+            Message::TimeUpdate { time, paused } => {
+                simulation.clock.set_ticks(time);
+                simulation.clock.set_paused(paused);
+            },
+            Message::KeepAlive { id } => self.connection.send(&Message::KeepAlive { id }),
+            Message::Chat { text } => self.chat.push(text),
+            Message::Completions { line, candidates } => self.completions = Some((line, candidates)),
+            Message::GameModeChange { game_mode } => {
+                if let Some(player) = self.player {
+                    player::set_game_mode(&mut simulation.entities, player, game_mode);
+                }
+            },
+            Message::Disconnect { reason } => {
+                let kind = if self.player.is_some() { io::ErrorKind::ConnectionAborted } else { io::ErrorKind::ConnectionRefused };
+                return Err(io::Error::new(kind, reason));
+            },
+            // Messages only servers receive.
+            Message::Handshake { .. } | Message::Login { .. } | Message::Input { .. } | Message::CompletionRequest { .. } => {},
+        }
+        Ok(())
+    }
+
+    /// Moves the player to where the server put it after applying `input`, then replays
+    /// the inputs sent after it.
+    fn reconcile(&mut self, input: u32, position: Vector3<f32>, velocity: Vector3<f32>, on_ground: bool, simulation: &mut Simulation) {
+        while self.pending.front().is_some_and(|(sequence, _)| *sequence <= input) {
+            self.pending.pop_front();
+        }
+        let Some(player) = self.player else {
+            return;
+        };
+        let Some(live) = simulation.entities.get::<PlayerInput>(player).map(PlayerInput::state) else {
+            return;
+        };
+        let Some(body) = simulation.entities.get_mut::<PhysicsBody>(player) else {
+            return;
+        };
+        let predicted = body.position;
+        body.position = position;
+        body.velocity = velocity;
+        body.on_ground = on_ground;
+
+        for (_, state) in &self.pending {
+            simulation.entities.get_mut::<PlayerInput>(player).unwrap().apply(state);
+            self.prediction.run(&mut SystemContext { entities: &mut simulation.entities, world: &mut simulation.world, delta: TICK_DELTA });
+        }
+        // Go back to what the player is doing right now, which is sent with the next tick.
+        let current = simulation.entities.get_mut::<PlayerInput>(player).unwrap();
+        *current = PlayerInput::new();
+        current.apply(&live);
+
+        let body = simulation.entities.get::<PhysicsBody>(player).unwrap();
+        self.prediction_error = (body.position - predicted).norm();
+    }
+}
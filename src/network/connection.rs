@@ -0,0 +1,139 @@
+use std::{io::{self, Read, Write}, net::{Shutdown, SocketAddr, TcpStream, ToSocketAddrs}, thread, time::{Duration, Instant}};
+
+use super::{protocol::{encode_frame, FrameDecoder, Message, MAX_MESSAGE_SIZE}, Transport};
+
+/// Bytes read from the socket at once.
+const READ_SIZE: usize = 16 * 1024;
+/// Bytes waiting to be written before the connection is closed, so a peer that stops
+/// reading cannot make the sender buffer forever.
+const MAX_OUTGOING: usize = 8 * MAX_MESSAGE_SIZE;
+
+/// A non-blocking TCP stream of messages. Received bytes are buffered until they form a
+/// whole message.
+pub struct Connection {
+    stream: TcpStream,
+    incoming: FrameDecoder,
+    outgoing: Vec<u8>,
+    closed: bool,
+}
+
+impl Connection {
+    pub fn new(stream: TcpStream) -> io::Result<Self> {
+        stream.set_nonblocking(true)?;
+        stream.set_nodelay(true)?;
+        Ok(Self { stream, incoming: FrameDecoder::new(), outgoing: Vec::new(), closed: false })
+    }
+
+    pub fn connect(address: impl ToSocketAddrs) -> io::Result<Self> {
+        Self::new(TcpStream::connect(address)?)
+    }
+
+    pub fn peer_address(&self) -> io::Result<SocketAddr> {
+        self.stream.peer_addr()
+    }
+
+    /// Waits for the next message, giving up with `TimedOut` after `timeout`.
+    pub fn receive_blocking(&mut self, timeout: Duration) -> io::Result<Message> {
+        let start = Instant::now();
+        loop {
+            self.flush()?;
+            if let Some(message) = self.receive()? {
+                return Ok(message);
+            }
+            if self.closed {
+                return Err(io::ErrorKind::ConnectionAborted.into());
+            }
+            if start.elapsed() > timeout {
+                return Err(io::ErrorKind::TimedOut.into());
+            }
+            thread::sleep(Duration::from_millis(1));
+        }
+    }
+
+    fn close(&mut self, error: io::Error) -> io::Error {
+        self.closed = true;
+        error
+    }
+}
+
+impl Transport for Connection {
+    /// Buffers the message until the next flush. Closes the connection when the peer
+    /// has fallen more than `MAX_OUTGOING` bytes behind.
+    fn send(&mut self, message: &Message) {
+        if self.closed {
+            return;
+        }
+        let frame = encode_frame(message);
+        if self.outgoing.len() + frame.len() > MAX_OUTGOING {
+            self.outgoing = Vec::new();
+            self.closed = true;
+            let _ = self.stream.shutdown(Shutdown::Both);
+            return;
+        }
+        self.outgoing.extend(frame);
+    }
+
+    /// Writes as much of the buffered messages as the socket takes without blocking.
+    fn flush(&mut self) -> io::Result<()> {
+        while !self.outgoing.is_empty() {
+            match self.stream.write(&self.outgoing) {
+                Ok(0) => return Err(self.close(io::ErrorKind::WriteZero.into())),
+                Ok(written) => {
+                    self.outgoing.drain(..written);
+                },
+                Err(error) if error.kind() == io::ErrorKind::WouldBlock => break,
+                Err(error) if error.kind() == io::ErrorKind::Interrupted => {},
+                Err(error) => return Err(self.close(error)),
+            }
+        }
+        Ok(())
+    }
+
+    fn receive(&mut self) -> io::Result<Option<Message>> {
+        if let Some(message) = self.incoming.next_message()? {
+            return Ok(Some(message));
+        }
+        let mut buffer = [0; READ_SIZE];
+        loop {
+            match self.stream.read(&mut buffer) {
+                Ok(0) => {
+                    self.closed = true;
+                    break;
+                },
+                Ok(read) => self.incoming.push(&buffer[..read]),
+                Err(error) if error.kind() == io::ErrorKind::WouldBlock => break,
+                Err(error) if error.kind() == io::ErrorKind::Interrupted => {},
+                Err(error) => return Err(self.close(error)),
+            }
+        }
+        self.incoming.next_message()
+    }
+
+    fn is_closed(&self) -> bool {
+        self.closed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::TcpListener;
+
+    use super::*;
+
+    #[test]
+    fn closes_when_the_peer_stops_reading() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let mut connection = Connection::connect(listener.local_addr().unwrap()).unwrap();
+        let (_peer, _) = listener.accept().unwrap();
+
+        let message = Message::Chat { text: "x".repeat(1000) };
+        let frame_size = encode_frame(&message).len();
+        for _ in 0..MAX_OUTGOING / frame_size {
+            connection.send(&message);
+        }
+        assert!(!connection.is_closed());
+        connection.send(&message);
+        assert!(connection.is_closed());
+        assert!(connection.outgoing.is_empty());
+    }
+}
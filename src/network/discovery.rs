@@ -0,0 +1,216 @@
+use std::{collections::HashMap, io, net::{Ipv4Addr, SocketAddr, UdpSocket}, time::{Duration, Instant}};
+
+use crate::encoding::{invalid_data, Reader};
+
+use super::protocol::PROTOCOL_VERSION;
+
+/// UDP port servers announce themselves to.
+pub const DISCOVERY_PORT: u16 = 24681;
+/// Time between two announcements of a server.
+pub const ANNOUNCE_INTERVAL: Duration = Duration::from_millis(1500);
+/// Time after its last announcement that a server is forgotten.
+pub const DISCOVERY_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Marks announcement packets, so other traffic on the port is ignored.
+const MAGIC: &[u8; 4] = b"VXLA";
+/// Largest announcement, which keeps the packet well within one datagram.
+const MAX_ANNOUNCEMENT_SIZE: usize = 512;
+
+/// What a server tells the local network about itself.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Announcement {
+    pub motd: String,
+    /// TCP port the server accepts clients on.
+    pub port: u16,
+    pub players: u16,
+    pub max_players: u16,
+    pub version: u16,
+}
+
+impl Announcement {
+    /// Whether a client of this build can join the server.
+    pub fn is_compatible(&self) -> bool {
+        self.version == PROTOCOL_VERSION
+    }
+
+    pub fn encode(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        bytes.extend(MAGIC);
+        bytes.extend(self.version.to_le_bytes());
+        bytes.extend(self.port.to_le_bytes());
+        bytes.extend(self.players.to_le_bytes());
+        bytes.extend(self.max_players.to_le_bytes());
+        // The message of the day is cut short rather than making the packet too large.
+        let mut length = self.motd.len().min(MAX_ANNOUNCEMENT_SIZE - bytes.len() - 2);
+        while !self.motd.is_char_boundary(length) {
+            length -= 1;
+        }
+        bytes.extend((length as u16).to_le_bytes());
+        bytes.extend(&self.motd.as_bytes()[..length]);
+        bytes
+    }
+
+    pub fn decode(bytes: &[u8]) -> io::Result<Self> {
+        let mut reader = Reader::new(bytes);
+        if reader.bytes(MAGIC.len())? != MAGIC {
+            return Err(invalid_data("Not a server announcement".to_owned()));
+        }
+        let version = reader.u16()?;
+        let port = reader.u16()?;
+        let players = reader.u16()?;
+        let max_players = reader.u16()?;
+        let length = reader.u16()? as usize;
+        let motd = String::from_utf8(reader.bytes(length)?.to_vec()).map_err(|_| invalid_data("Text is not valid UTF-8".to_owned()))?;
+        Ok(Self { motd, port, players, max_players, version })
+    }
+}
+
+/// Sends a server's announcements, by default as broadcasts to the whole local network.
+pub struct Announcer {
+    socket: UdpSocket,
+    target: SocketAddr,
+    last_announcement: Option<Instant>,
+}
+
+impl Announcer {
+    /// Announces to every host on the local network.
+    pub fn broadcast() -> io::Result<Self> {
+        Self::new(SocketAddr::from((Ipv4Addr::BROADCAST, DISCOVERY_PORT)))
+    }
+
+    /// Announces to a single address, such as a `Discovery` on the loopback interface.
+    pub fn new(target: SocketAddr) -> io::Result<Self> {
+        let socket = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0))?;
+        socket.set_broadcast(true)?;
+        socket.set_nonblocking(true)?;
+        Ok(Self { socket, target, last_announcement: None })
+    }
+
+    /// Sends the announcement if `ANNOUNCE_INTERVAL` has passed since the last one.
+    /// Returns whether it was sent.
+    pub fn announce(&mut self, announcement: &Announcement) -> io::Result<bool> {
+        if self.last_announcement.is_some_and(|last| last.elapsed() < ANNOUNCE_INTERVAL) {
+            return Ok(false);
+        }
+        self.last_announcement = Some(Instant::now());
+        self.socket.send_to(&announcement.encode(), self.target)?;
+        Ok(true)
+    }
+}
+
+/// A server found on the local network.
+#[derive(Clone, Debug)]
+pub struct DiscoveredServer {
+    /// Where clients connect to, the sender's address with the announced port.
+    pub address: SocketAddr,
+    pub announcement: Announcement,
+    pub last_seen: Instant,
+}
+
+/// Listens for announcements and keeps the servers heard from within the timeout.
+pub struct Discovery {
+    socket: UdpSocket,
+    servers: HashMap<SocketAddr, DiscoveredServer>,
+    timeout: Duration,
+}
+
+impl Discovery {
+    /// Listens on `DISCOVERY_PORT`, where servers broadcast to.
+    pub fn new() -> io::Result<Self> {
+        Self::bind(DISCOVERY_PORT)
+    }
+
+    /// Listens on any port. Zero picks a free one, see `local_address`.
+    pub fn bind(port: u16) -> io::Result<Self> {
+        let socket = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, port))?;
+        socket.set_nonblocking(true)?;
+        Ok(Self { socket, servers: HashMap::new(), timeout: DISCOVERY_TIMEOUT })
+    }
+
+    pub fn local_address(&self) -> io::Result<SocketAddr> {
+        self.socket.local_addr()
+    }
+
+    pub fn set_timeout(&mut self, timeout: Duration) {
+        self.timeout = timeout;
+    }
+
+    /// Reads the announcements that arrived and forgets servers that went quiet.
+    pub fn poll(&mut self) -> io::Result<()> {
+        let mut buffer = [0; MAX_ANNOUNCEMENT_SIZE];
+        loop {
+            match self.socket.recv_from(&mut buffer) {
+                Ok((length, sender)) => {
+                    // Anything else sent to the port is not our business.
+                    let Ok(announcement) = Announcement::decode(&buffer[..length]) else {
+                        continue;
+                    };
+                    let address = SocketAddr::new(sender.ip(), announcement.port);
+                    self.servers.insert(address, DiscoveredServer { address, announcement, last_seen: Instant::now() });
+                },
+                Err(error) if error.kind() == io::ErrorKind::WouldBlock => break,
+                Err(error) if error.kind() == io::ErrorKind::Interrupted => {},
+                // Errors caused by earlier packets, such as an unreachable port on Windows.
+                Err(error) if error.kind() == io::ErrorKind::ConnectionReset => {},
+                Err(error) => return Err(error),
+            }
+        }
+        let timeout = self.timeout;
+        self.servers.retain(|_, server| server.last_seen.elapsed() <= timeout);
+        Ok(())
+    }
+
+    /// The servers heard from, sorted by address.
+    pub fn servers(&self) -> Vec<&DiscoveredServer> {
+        let mut servers: Vec<&DiscoveredServer> = self.servers.values().collect();
+        servers.sort_by_key(|server| server.address);
+        servers
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+
+    use super::*;
+
+    fn announcement(motd: &str) -> Announcement {
+        Announcement { motd: motd.to_owned(), port: 25000, players: 1, max_players: 8, version: PROTOCOL_VERSION }
+    }
+
+    #[test]
+    fn finds_announced_servers_and_forgets_quiet_ones() {
+        let mut discovery = Discovery::bind(0).unwrap();
+        let port = discovery.local_address().unwrap().port();
+        let mut announcer = Announcer::new(SocketAddr::from((Ipv4Addr::LOCALHOST, port))).unwrap();
+        assert!(announcer.announce(&announcement("Hello")).unwrap());
+        assert!(!announcer.announce(&announcement("Hello")).unwrap());
+
+        let start = Instant::now();
+        while discovery.servers().is_empty() {
+            assert!(start.elapsed() < Duration::from_secs(5), "no announcement arrived");
+            thread::sleep(Duration::from_millis(1));
+            discovery.poll().unwrap();
+        }
+        let servers = discovery.servers();
+        assert_eq!(servers.len(), 1);
+        assert_eq!(servers[0].address, SocketAddr::from((Ipv4Addr::LOCALHOST, 25000)));
+        assert_eq!(servers[0].announcement, announcement("Hello"));
+
+        discovery.set_timeout(Duration::from_millis(10));
+        thread::sleep(Duration::from_millis(20));
+        discovery.poll().unwrap();
+        assert!(discovery.servers().is_empty());
+    }
+
+    #[test]
+    fn cuts_long_messages_of_the_day_between_characters() {
+        let motd = "🦀".repeat(MAX_ANNOUNCEMENT_SIZE);
+        let bytes = announcement(&motd).encode();
+        assert!(bytes.len() <= MAX_ANNOUNCEMENT_SIZE);
+        let decoded = Announcement::decode(&bytes).unwrap();
+        assert!(!decoded.motd.is_empty() && motd.starts_with(&decoded.motd));
+        assert_eq!(decoded.port, 25000);
+        assert!(Announcement::decode(b"VXLB").is_err());
+    }
+}
@@ -0,0 +1,203 @@
+use std::{cell::{Cell, RefCell}, collections::VecDeque, io, rc::Rc};
+
+use crate::math::Random;
+
+use super::{protocol::{decode_message, encode_message, Message}, Transport};
+
+/// How a simulated link delays and drops messages. Times are in ticks of the
+/// `LocalNetwork` clock.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct LinkConditions {
+    /// Ticks every message takes to arrive.
+    pub latency: u64,
+    /// Most ticks a message may take on top of the latency. Messages still arrive in the
+    /// order they were sent, like on a TCP stream.
+    pub jitter: u64,
+    /// Chance of a message getting lost, from 0 to 1. Only inputs and player states can
+    /// get lost, as the next one replaces them. Losing any other message would break the
+    /// client's copy of the world, which a real stream never does.
+    pub loss: f32,
+}
+
+/// Messages travelling in one direction, encoded and with the tick they arrive at.
+#[derive(Default)]
+struct Queue {
+    messages: VecDeque<(u64, Vec<u8>)>,
+}
+
+struct Link {
+    random: Random,
+    /// One queue towards each end.
+    queues: [Queue; 2],
+    closed: bool,
+}
+
+/// A network inside the process, with its own clock, to run a server and its clients in
+/// one thread under reproducible latency and packet loss.
+pub struct LocalNetwork {
+    time: Rc<Cell<u64>>,
+    conditions: Rc<Cell<LinkConditions>>,
+    links: u64,
+}
+
+impl LocalNetwork {
+    /// A network that delivers every message at the next tick.
+    pub fn new() -> Self {
+        Self { time: Rc::new(Cell::new(0)), conditions: Rc::new(Cell::new(LinkConditions::default())), links: 0 }
+    }
+
+    pub fn time(&self) -> u64 {
+        self.time.get()
+    }
+
+    pub fn conditions(&self) -> LinkConditions {
+        self.conditions.get()
+    }
+
+    /// Changes the conditions of every link, for messages sent from now on.
+    pub fn set_conditions(&self, conditions: LinkConditions) {
+        self.conditions.set(conditions);
+    }
+
+    /// Advances the clock by one tick, delivering the messages that are due.
+    pub fn advance(&self) {
+        self.time.set(self.time.get() + 1);
+    }
+
+    /// Creates a link and returns its two ends, one for the client and one for the server.
+    pub fn connect(&mut self) -> (LocalTransport, LocalTransport) {
+        self.links += 1;
+        let link = Rc::new(RefCell::new(Link {
+            random: Random::new(self.links),
+            queues: [Queue::default(), Queue::default()],
+            closed: false,
+        }));
+        let end = |side| LocalTransport {
+            link: link.clone(),
+            side,
+            time: self.time.clone(),
+            conditions: self.conditions.clone(),
+            outgoing: Vec::new(),
+        };
+        (end(0), end(1))
+    }
+}
+
+impl Default for LocalNetwork {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// One end of a link of a `LocalNetwork`. The link closes when either end is dropped.
+pub struct LocalTransport {
+    link: Rc<RefCell<Link>>,
+    side: usize,
+    time: Rc<Cell<u64>>,
+    conditions: Rc<Cell<LinkConditions>>,
+    outgoing: Vec<Message>,
+}
+
+impl LocalTransport {
+    pub fn close(&self) {
+        self.link.borrow_mut().closed = true;
+    }
+}
+
+impl Transport for LocalTransport {
+    fn send(&mut self, message: &Message) {
+        self.outgoing.push(message.clone());
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        let mut link = self.link.borrow_mut();
+        if link.closed {
+            self.outgoing.clear();
+            return Err(io::ErrorKind::BrokenPipe.into());
+        }
+        let conditions = self.conditions.get();
+        for message in self.outgoing.drain(..) {
+            if is_replaceable(&message) && link.random.next_f32() < conditions.loss {
+                continue;
+            }
+            let delay = conditions.latency + link.random.below(conditions.jitter as u32 + 1) as u64;
+            let queue = &mut link.queues[1 - self.side].messages;
+            let arrival = queue.back().map_or(0, |(arrival, _)| *arrival).max(self.time.get() + delay);
+            queue.push_back((arrival, encode_message(&message)));
+        }
+        Ok(())
+    }
+
+    fn receive(&mut self) -> io::Result<Option<Message>> {
+        let mut link = self.link.borrow_mut();
+        let queue = &mut link.queues[self.side].messages;
+        if queue.front().is_none_or(|(arrival, _)| *arrival > self.time.get()) {
+            return Ok(None);
+        }
+        let (_, bytes) = queue.pop_front().unwrap();
+        decode_message(&bytes).map(Some)
+    }
+
+    /// Messages that were on their way when the link closed still arrive first.
+    fn is_closed(&self) -> bool {
+        let link = self.link.borrow();
+        link.closed && link.queues[self.side].messages.is_empty()
+    }
+}
+
+impl Drop for LocalTransport {
+    fn drop(&mut self) {
+        self.close();
+    }
+}
+
+/// Whether a later message of the same kind makes up for losing this one.
+fn is_replaceable(message: &Message) -> bool {
+    matches!(message, Message::Input { .. } | Message::PlayerState { .. })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ecs::player::InputState;
+
+    use super::*;
+
+    fn receive_all(transport: &mut LocalTransport) -> Vec<Message> {
+        std::iter::from_fn(|| transport.receive().unwrap()).collect()
+    }
+
+    #[test]
+    fn delays_messages_in_order_and_only_loses_replaceable_ones() {
+        let mut network = LocalNetwork::new();
+        let (mut client, mut server) = network.connect();
+        network.set_conditions(LinkConditions { latency: 2, jitter: 3, loss: 0.5 });
+        for sequence in 0..100 {
+            client.send(&Message::Chat { text: sequence.to_string() });
+            client.send(&Message::Input { sequence, state: InputState::default() });
+        }
+        client.flush().unwrap();
+
+        let mut received = Vec::new();
+        for _ in 0..2 {
+            network.advance();
+            assert!(receive_all(&mut server).is_empty());
+        }
+        for _ in 0..4 {
+            network.advance();
+            received.extend(receive_all(&mut server));
+        }
+        assert!(receive_all(&mut server).is_empty());
+
+        let chat: Vec<String> = received.iter().filter_map(|message| match message {
+            Message::Chat { text } => Some(text.clone()),
+            _ => None,
+        }).collect();
+        assert_eq!(chat, (0..100).map(|sequence: u32| sequence.to_string()).collect::<Vec<_>>());
+        let inputs: Vec<u32> = received.iter().filter_map(|message| match message {
+            Message::Input { sequence, .. } => Some(*sequence),
+            _ => None,
+        }).collect();
+        assert!(inputs.len() > 20 && inputs.len() < 80, "{} inputs arrived", inputs.len());
+        assert!(inputs.windows(2).all(|pair| pair[0] < pair[1]));
+    }
+}
@@ -0,0 +1,452 @@
+use std::io;
+
+use nalgebra::Vector3;
+
+use crate::{ecs::{components::Model, player::{GameMode, InputState}}, encoding::{invalid_data, Reader}, item::ItemId, world::{block::BlockId, chunk::{chunk_coordinates, chunk_index, CHUNK_VOLUME}, voxel::Voxel}};
+
+/// Clients and servers only talk to each other when their versions match. Bumped
+/// whenever a message changes.
+pub const PROTOCOL_VERSION: u16 = 4;
+/// Largest message accepted, so a corrupt length cannot make the receiver buffer forever.
+pub const MAX_MESSAGE_SIZE: usize = 1 << 20;
+
+const HANDSHAKE: u8 = 0;
+const LOGIN: u8 = 1;
+const INPUT: u8 = 2;
+const LOGIN_ACCEPTED: u8 = 3;
+const DISCONNECT: u8 = 4;
+const CHUNK_DATA: u8 = 5;
+const BLOCK_CHANGES: u8 = 6;
+const ENTITY_SPAWN: u8 = 7;
+const ENTITY_MOVE: u8 = 8;
+const ENTITY_DESPAWN: u8 = 9;
+const PLAYER_STATE: u8 = 10;
+const TIME_UPDATE: u8 = 11;
+const KEEP_ALIVE: u8 = 12;
+const CHUNK_UNLOAD: u8 = 13;
+const CHAT: u8 = 14;
+const COMPLETION_REQUEST: u8 = 15;
+const COMPLETIONS: u8 = 16;
+const GAME_MODE_CHANGE: u8 = 17;
+
+/// Everything a client and a server send each other. Entities are identified by the
+/// bits of the server's `Entity` handle.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Message {
+    /// First message of a client, before anything else is accepted.
+    Handshake { version: u16 },
+    Login { name: String },
+    /// The player's input for one tick, numbered so the server can tell which inputs its
+    /// player state includes.
+    Input { sequence: u32, state: InputState },
+    LoginAccepted { player: u64, position: Vector3<f32>, game_mode: GameMode, time: u64 },
+    /// Sent before the server closes the connection.
+    Disconnect { reason: String },
+    /// The voxels of a chunk, see `encode_chunk_voxels`.
+    ChunkData { position: Vector3<i32>, data: Vec<u8> },
+    /// The client should forget a chunk it is too far away from.
+    ChunkUnload { position: Vector3<i32> },
+    /// Voxels of one chunk that changed during a tick, by their position in the chunk.
+    BlockChanges { chunk: Vector3<i32>, changes: Vec<(Vector3<i32>, Option<Voxel>)> },
+    EntitySpawn { entity: u64, model: Option<Model>, position: Vector3<f32> },
+    EntityMove { entity: u64, position: Vector3<f32> },
+    EntityDespawn { entity: u64 },
+    /// The receiving player as simulated by the server, after applying every input up to
+    /// the one numbered `input`.
+    PlayerState { input: u32, position: Vector3<f32>, velocity: Vector3<f32>, on_ground: bool },
+    TimeUpdate { time: u64, paused: bool },
+    /// Sent by the server at an interval and echoed by the client, so both sides notice
+    /// connections that went quiet.
+    KeepAlive { id: u64 },
+    /// From a client, a chat message or a command starting with `/`. From a server, a
+    /// line for the chat, such as another player's message or a command's feedback.
+    Chat { text: String },
+    /// Asks for completions of the last word of a partly typed command.
+    CompletionRequest { line: String },
+    /// The answer to a `CompletionRequest` for `line`.
+    Completions { line: String, candidates: Vec<String> },
+    GameModeChange { game_mode: GameMode },
+}
+
+/// Encodes a message with its length in front, ready to be written to a stream.
+pub fn encode_frame(message: &Message) -> Vec<u8> {
+    let payload = encode_message(message);
+    let mut frame = Vec::with_capacity(4 + payload.len());
+    frame.extend((payload.len() as u32).to_le_bytes());
+    frame.extend(payload);
+    frame
+}
+
+pub fn encode_message(message: &Message) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    match message {
+        Message::Handshake { version } => {
+            bytes.push(HANDSHAKE);
+            bytes.extend(version.to_le_bytes());
+        },
+        Message::Login { name } => {
+            bytes.push(LOGIN);
+            put_string(&mut bytes, name);
+        },
+        Message::Input { sequence, state } => {
+            bytes.push(INPUT);
+            bytes.extend(sequence.to_le_bytes());
+            bytes.extend([state.held, state.pressed]);
+            bytes.extend(state.yaw.to_le_bytes());
+            bytes.extend(state.pitch.to_le_bytes());
+            bytes.push(state.selected_slot);
+        },
+        Message::LoginAccepted { player, position, game_mode, time } => {
+            bytes.push(LOGIN_ACCEPTED);
+            bytes.extend(player.to_le_bytes());
+            put_position(&mut bytes, position);
+            put_game_mode(&mut bytes, *game_mode);
+            bytes.extend(time.to_le_bytes());
+        },
+        Message::Disconnect { reason } => {
+            bytes.push(DISCONNECT);
+            put_string(&mut bytes, reason);
+        },
+        Message::ChunkData { position, data } => {
+            bytes.push(CHUNK_DATA);
+            put_voxel_position(&mut bytes, position);
+            bytes.extend((data.len() as u32).to_le_bytes());
+            bytes.extend(data);
+        },
+        Message::ChunkUnload { position } => {
+            bytes.push(CHUNK_UNLOAD);
+            put_voxel_position(&mut bytes, position);
+        },
+        Message::BlockChanges { chunk, changes } => {
+            bytes.push(BLOCK_CHANGES);
+            put_voxel_position(&mut bytes, chunk);
+            bytes.extend((changes.len() as u16).to_le_bytes());
+            for (local, voxel) in changes {
+                bytes.extend((chunk_index(*local).unwrap() as u16).to_le_bytes());
+                // Block ids are shifted by one so zero can mean air.
+                let (block, state) = voxel.map_or((0, 0), |voxel| (voxel.block.0 + 1, voxel.state));
+                bytes.extend(block.to_le_bytes());
+                bytes.push(state);
+            }
+        },
+        Message::EntitySpawn { entity, model, position } => {
+            bytes.push(ENTITY_SPAWN);
+            bytes.extend(entity.to_le_bytes());
+            match model {
+                None => bytes.push(0),
+                Some(Model::Block(block)) => {
+                    bytes.push(1);
+                    bytes.extend(block.0.to_le_bytes());
+                },
+                Some(Model::Item(item)) => {
+                    bytes.push(2);
+                    bytes.extend(item.0.to_le_bytes());
+                },
+            }
+            put_position(&mut bytes, position);
+        },
+        Message::EntityMove { entity, position } => {
+            bytes.push(ENTITY_MOVE);
+            bytes.extend(entity.to_le_bytes());
+            put_position(&mut bytes, position);
+        },
+        Message::EntityDespawn { entity } => {
+            bytes.push(ENTITY_DESPAWN);
+            bytes.extend(entity.to_le_bytes());
+        },
+        Message::PlayerState { input, position, velocity, on_ground } => {
+            bytes.push(PLAYER_STATE);
+            bytes.extend(input.to_le_bytes());
+            put_position(&mut bytes, position);
+            put_position(&mut bytes, velocity);
+            bytes.push(*on_ground as u8);
+        },
+        Message::TimeUpdate { time, paused } => {
+            bytes.push(TIME_UPDATE);
+            bytes.extend(time.to_le_bytes());
+            bytes.push(*paused as u8);
+        },
+        Message::KeepAlive { id } => {
+            bytes.push(KEEP_ALIVE);
+            bytes.extend(id.to_le_bytes());
+        },
+        Message::Chat { text } => {
+            bytes.push(CHAT);
+            put_string(&mut bytes, text);
+        },
+        Message::CompletionRequest { line } => {
+            bytes.push(COMPLETION_REQUEST);
+            put_string(&mut bytes, line);
+        },
+        Message::Completions { line, candidates } => {
+            bytes.push(COMPLETIONS);
+            put_string(&mut bytes, line);
+            bytes.extend((candidates.len() as u16).to_le_bytes());
+            for candidate in candidates {
+                put_string(&mut bytes, candidate);
+            }
+        },
+        Message::GameModeChange { game_mode } => {
+            bytes.push(GAME_MODE_CHANGE);
+            put_game_mode(&mut bytes, *game_mode);
+        },
+    }
+    bytes
+}
+
+/// Decodes a message without its length prefix. Fails on unknown messages and on
+/// bytes left over after the message.
+pub fn decode_message(bytes: &[u8]) -> io::Result<Message> {
+    let mut reader = Reader::new(bytes);
+    let message = match reader.u8()? {
+        HANDSHAKE => Message::Handshake { version: reader.u16()? },
+        LOGIN => Message::Login { name: read_string(&mut reader)? },
+        INPUT => Message::Input {
+            sequence: reader.u32()?,
+            state: InputState {
+                held: reader.u8()?,
+                pressed: reader.u8()?,
+                yaw: reader.f32()?,
+                pitch: reader.f32()?,
+                selected_slot: reader.u8()?,
+            },
+        },
+        LOGIN_ACCEPTED => {
+            let player = reader.u64()?;
+            let position = read_position(&mut reader)?;
+            let game_mode = read_game_mode(&mut reader)?;
+            Message::LoginAccepted { player, position, game_mode, time: reader.u64()? }
+        },
+        DISCONNECT => Message::Disconnect { reason: read_string(&mut reader)? },
+        CHUNK_DATA => {
+            let position = read_voxel_position(&mut reader)?;
+            let length = reader.u32()? as usize;
+            Message::ChunkData { position, data: reader.bytes(length)?.to_vec() }
+        },
+        CHUNK_UNLOAD => Message::ChunkUnload { position: read_voxel_position(&mut reader)? },
+        BLOCK_CHANGES => {
+            let chunk = read_voxel_position(&mut reader)?;
+            let count = reader.u16()?;
+            let mut changes = Vec::with_capacity(count as usize);
+            for _ in 0..count {
+                let index = reader.u16()? as usize;
+                if index >= CHUNK_VOLUME {
+                    return Err(invalid_data(format!("Voxel index {} is outside the chunk", index)));
+                }
+                let block = reader.u16()?;
+                let state = reader.u8()?;
+                changes.push((chunk_coordinates(index), (block != 0).then(|| Voxel::with_state(BlockId(block - 1), state))));
+            }
+            Message::BlockChanges { chunk, changes }
+        },
+        ENTITY_SPAWN => {
+            let entity = reader.u64()?;
+            let model = match reader.u8()? {
+                0 => None,
+                1 => Some(Model::Block(BlockId(reader.u16()?))),
+                2 => Some(Model::Item(ItemId(reader.u16()?))),
+                kind => return Err(invalid_data(format!("Unknown model kind {}", kind))),
+            };
+            Message::EntitySpawn { entity, model, position: read_position(&mut reader)? }
+        },
+        ENTITY_MOVE => Message::EntityMove { entity: reader.u64()?, position: read_position(&mut reader)? },
+        ENTITY_DESPAWN => Message::EntityDespawn { entity: reader.u64()? },
+        PLAYER_STATE => Message::PlayerState {
+            input: reader.u32()?,
+            position: read_position(&mut reader)?,
+            velocity: read_position(&mut reader)?,
+            on_ground: reader.u8()? != 0,
+        },
+        TIME_UPDATE => Message::TimeUpdate { time: reader.u64()?, paused: reader.u8()? != 0 },
+        KEEP_ALIVE => Message::KeepAlive { id: reader.u64()? },
+        CHAT => Message::Chat { text: read_string(&mut reader)? },
+        COMPLETION_REQUEST => Message::CompletionRequest { line: read_string(&mut reader)? },
+        COMPLETIONS => {
+            let line = read_string(&mut reader)?;
+            let count = reader.u16()?;
+            let candidates = (0..count).map(|_| read_string(&mut reader)).collect::<io::Result<_>>()?;
+            Message::Completions { line, candidates }
+        },
+        GAME_MODE_CHANGE => Message::GameModeChange { game_mode: read_game_mode(&mut reader)? },
+        kind => return Err(invalid_data(format!("Unknown message kind {}", kind))),
+    };
+    if !reader.is_empty() {
+        return Err(invalid_data("Bytes left over after message".to_owned()));
+    }
+    Ok(message)
+}
+
+/// Splits a stream of bytes back into the messages written with `encode_frame`.
+#[derive(Default)]
+pub struct FrameDecoder {
+    buffer: Vec<u8>,
+}
+
+impl FrameDecoder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push(&mut self, bytes: &[u8]) {
+        self.buffer.extend_from_slice(bytes);
+    }
+
+    /// The next complete message, or `None` until more bytes arrive.
+    pub fn next_message(&mut self) -> io::Result<Option<Message>> {
+        let Some(prefix) = self.buffer.get(..4) else {
+            return Ok(None);
+        };
+        let length = u32::from_le_bytes(prefix.try_into().unwrap()) as usize;
+        if length > MAX_MESSAGE_SIZE {
+            return Err(invalid_data(format!("Message of {} bytes is too large", length)));
+        }
+        if self.buffer.len() < 4 + length {
+            return Ok(None);
+        }
+        let message = decode_message(&self.buffer[4..4 + length]);
+        self.buffer.drain(..4 + length);
+        message.map(Some)
+    }
+}
+
+fn put_string(bytes: &mut Vec<u8>, text: &str) {
+    bytes.extend((text.len() as u32).to_le_bytes());
+    bytes.extend(text.as_bytes());
+}
+
+fn put_position(bytes: &mut Vec<u8>, position: &Vector3<f32>) {
+    for value in position.iter() {
+        bytes.extend(value.to_le_bytes());
+    }
+}
+
+fn put_voxel_position(bytes: &mut Vec<u8>, position: &Vector3<i32>) {
+    for value in position.iter() {
+        bytes.extend(value.to_le_bytes());
+    }
+}
+
+fn put_game_mode(bytes: &mut Vec<u8>, game_mode: GameMode) {
+    bytes.push(GameMode::ALL.iter().position(|mode| *mode == game_mode).unwrap() as u8);
+}
+
+fn read_string(reader: &mut Reader) -> io::Result<String> {
+    let length = reader.u32()? as usize;
+    String::from_utf8(reader.bytes(length)?.to_vec()).map_err(|_| invalid_data("Text is not valid UTF-8".to_owned()))
+}
+
+fn read_position(reader: &mut Reader) -> io::Result<Vector3<f32>> {
+    Ok(Vector3::new(reader.f32()?, reader.f32()?, reader.f32()?))
+}
+
+fn read_voxel_position(reader: &mut Reader) -> io::Result<Vector3<i32>> {
+    Ok(Vector3::new(reader.i32()?, reader.i32()?, reader.i32()?))
+}
+
+fn read_game_mode(reader: &mut Reader) -> io::Result<GameMode> {
+    let mode = reader.u8()?;
+    GameMode::ALL.get(mode as usize).copied().ok_or_else(|| invalid_data(format!("Unknown game mode {}", mode)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// One message of every kind, with values that do not round trip by accident.
+    fn every_message() -> Vec<Message> {
+        vec![
+            Message::Handshake { version: PROTOCOL_VERSION },
+            Message::Login { name: "Steve".to_owned() },
+            Message::Input { sequence: 7, state: InputState { held: 0b1010, pressed: 0b0001, yaw: 1.25, pitch: -0.5, selected_slot: 3 } },
+            Message::LoginAccepted { player: 42, position: Vector3::new(1.5, 2.0, -3.25), game_mode: GameMode::Creative, time: 6000 },
+            Message::Disconnect { reason: "Server full — try later".to_owned() },
+            Message::ChunkData { position: Vector3::new(-1, 0, 5), data: vec![1, 2, 3, 255] },
+            Message::ChunkUnload { position: Vector3::new(3, 0, -7) },
+            Message::BlockChanges {
+                chunk: Vector3::new(2, 0, 2),
+                changes: vec![(Vector3::new(1, 2, 3), Some(Voxel::with_state(BlockId(4), 9))), (Vector3::new(7, 7, 7), None)],
+            },
+            Message::EntitySpawn { entity: 9, model: Some(Model::Block(BlockId(2))), position: Vector3::new(0.5, 1.0, 0.5) },
+            Message::EntitySpawn { entity: 10, model: Some(Model::Item(ItemId(17))), position: Vector3::zeros() },
+            Message::EntitySpawn { entity: 11, model: None, position: Vector3::new(-4.0, 8.0, 2.5) },
+            Message::EntityMove { entity: 9, position: Vector3::new(0.75, 1.0, 0.5) },
+            Message::EntityDespawn { entity: 9 },
+            Message::PlayerState { input: 12, position: Vector3::new(4.0, 5.0, 6.0), velocity: Vector3::new(0.0, -9.5, 1.0), on_ground: true },
+            Message::TimeUpdate { time: 12345, paused: true },
+            Message::KeepAlive { id: u64::MAX },
+            Message::Chat { text: "/time set noon".to_owned() },
+            Message::CompletionRequest { line: "/gamemode cr".to_owned() },
+            Message::Completions { line: "/gamemode cr".to_owned(), candidates: vec!["creative".to_owned()] },
+            Message::Completions { line: "/xyz".to_owned(), candidates: Vec::new() },
+            Message::GameModeChange { game_mode: GameMode::Spectator },
+        ]
+    }
+
+    /// A frame holding `payload` as is.
+    fn frame(payload: &[u8]) -> Vec<u8> {
+        let mut frame = (payload.len() as u32).to_le_bytes().to_vec();
+        frame.extend(payload);
+        frame
+    }
+
+    #[test]
+    fn every_message_round_trips() {
+        let messages = every_message();
+        let mut decoder = FrameDecoder::new();
+        for message in &messages {
+            decoder.push(&encode_frame(message));
+        }
+        for message in &messages {
+            assert_eq!(decoder.next_message().unwrap().as_ref(), Some(message));
+        }
+        assert_eq!(decoder.next_message().unwrap(), None);
+    }
+
+    #[test]
+    fn frames_split_across_pushes_wait_for_the_rest() {
+        let message = Message::Chat { text: "hello".to_owned() };
+        let frame = encode_frame(&message);
+        let mut decoder = FrameDecoder::new();
+        for split in [2, frame.len() - 1] {
+            decoder.push(&frame[..split]);
+            assert_eq!(decoder.next_message().unwrap(), None);
+            decoder.push(&frame[split..]);
+            assert_eq!(decoder.next_message().unwrap(), Some(message.clone()));
+        }
+    }
+
+    #[test]
+    fn rejects_trailing_bytes() {
+        let mut payload = encode_message(&Message::KeepAlive { id: 1 });
+        payload.push(0);
+        let mut decoder = FrameDecoder::new();
+        decoder.push(&frame(&payload));
+        assert_eq!(decoder.next_message().unwrap_err().kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn rejects_unknown_kinds() {
+        let mut decoder = FrameDecoder::new();
+        decoder.push(&frame(&[u8::MAX, 1, 2]));
+        assert_eq!(decoder.next_message().unwrap_err().kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn rejects_oversized_frames_before_they_arrive() {
+        let mut decoder = FrameDecoder::new();
+        decoder.push(&(MAX_MESSAGE_SIZE as u32 + 1).to_le_bytes());
+        assert_eq!(decoder.next_message().unwrap_err().kind(), io::ErrorKind::InvalidData);
+
+        let mut decoder = FrameDecoder::new();
+        decoder.push(&(MAX_MESSAGE_SIZE as u32).to_le_bytes());
+        assert_eq!(decoder.next_message().unwrap(), None);
+    }
+
+    #[test]
+    fn rejects_truncated_messages() {
+        let payload = encode_message(&Message::LoginAccepted { player: 1, position: Vector3::zeros(), game_mode: GameMode::Survival, time: 0 });
+        let mut decoder = FrameDecoder::new();
+        decoder.push(&frame(&payload[..payload.len() - 1]));
+        assert_eq!(decoder.next_message().unwrap_err().kind(), io::ErrorKind::UnexpectedEof);
+    }
+}
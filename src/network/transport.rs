@@ -0,0 +1,18 @@
+use std::io;
+
+use super::protocol::Message;
+
+/// A two-way, ordered stream of messages between a client and a server. Sent messages
+/// are buffered until `flush`.
+pub trait Transport {
+    fn send(&mut self, message: &Message);
+
+    /// Hands the buffered messages to the other side, as far as it can without blocking.
+    fn flush(&mut self) -> io::Result<()>;
+
+    /// The next message that arrived, or `None` when there is none yet.
+    fn receive(&mut self) -> io::Result<Option<Message>>;
+
+    /// Whether the other side closed the connection or it failed.
+    fn is_closed(&self) -> bool;
+}
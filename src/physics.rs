@@ -0,0 +1,196 @@
+use nalgebra::Vector3;
+
+use crate::world::World;
+
+pub const GRAVITY: f32 = 28.0;
+pub const TERMINAL_VELOCITY: f32 = 60.0;
+/// Upwards speed of a jump, enough to get on top of a ledge one voxel high.
+pub const JUMP_SPEED: f32 = 9.0;
+
+/// Largest distance moved in a single collision step, so fast bodies cannot pass
+/// through a voxel.
+const MAX_STEP: f32 = 0.45;
+/// Gap kept between a body and the voxel it collides with.
+const SKIN: f32 = 1e-4;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Aabb {
+    pub min: Vector3<f32>,
+    pub max: Vector3<f32>,
+}
+
+impl Aabb {
+    pub fn new(min: Vector3<f32>, max: Vector3<f32>) -> Self { Self { min, max } }
+
+    pub fn intersects(&self, other: &Aabb) -> bool {
+        self.min.x < other.max.x && self.max.x > other.min.x &&
+            self.min.y < other.max.y && self.max.y > other.min.y &&
+            self.min.z < other.max.z && self.max.z > other.min.z
+    }
+
+    /// Every voxel position the box overlaps.
+    pub fn voxels(&self) -> impl Iterator<Item = Vector3<i32>> {
+        let min = self.min.map(|c| c.floor() as i32);
+        let max = self.max.map(|c| c.ceil() as i32 - 1);
+        (min.x..=max.x).flat_map(move |x| {
+            (min.y..=max.y).flat_map(move |y| (min.z..=max.z).map(move |z| Vector3::new(x, y, z)))
+        })
+    }
+}
+
+/// An axis aligned box that moves through the world and collides with solid voxels.
+/// `position` is the centre of the bottom face.
+#[derive(Clone, Debug)]
+pub struct PhysicsBody {
+    pub position: Vector3<f32>,
+    pub velocity: Vector3<f32>,
+    /// Width along X and Z, and height along Y.
+    pub size: Vector3<f32>,
+    pub gravity: bool,
+    pub on_ground: bool,
+    /// Distance fallen since the body last stood on the ground.
+    pub fall_distance: f32,
+    /// Distance fallen before landing during the last step, if the body landed.
+    pub landed: Option<f32>,
+    /// Moves through voxels without colliding.
+    pub noclip: bool,
+}
+
+impl PhysicsBody {
+    pub fn new(position: Vector3<f32>, size: Vector3<f32>) -> Self {
+        Self {
+            position,
+            velocity: Vector3::zeros(),
+            size,
+            gravity: true,
+            on_ground: false,
+            fall_distance: 0.0,
+            landed: None,
+            noclip: false,
+        }
+    }
+
+    pub fn aabb(&self) -> Aabb {
+        let half = Vector3::new(self.size.x / 2.0, 0.0, self.size.z / 2.0);
+        Aabb::new(self.position - half, self.position + half + Vector3::y() * self.size.y)
+    }
+
+    /// Integrates the body over `delta` seconds. Returns the distance fallen when the
+    /// body landed during this step, which is also kept in `landed`.
+    pub fn step(&mut self, world: &World, delta: f32) -> Option<f32> {
+        self.landed = self.integrate(world, delta);
+        self.landed
+    }
+
+    fn integrate(&mut self, world: &World, delta: f32) -> Option<f32> {
+        if self.gravity {
+            self.velocity.y = (self.velocity.y - GRAVITY * delta).max(-TERMINAL_VELOCITY);
+        }
+
+        let movement = self.velocity * delta;
+        let steps = (movement.abs().max() / MAX_STEP).ceil().max(1.0) as usize;
+        let was_on_ground = self.on_ground;
+        let start_height = self.position.y;
+        self.on_ground = false;
+        for _ in 0..steps {
+            for axis in [1, 0, 2] {
+                self.move_axis(world, axis, movement[axis] / steps as f32);
+            }
+        }
+
+        self.fall_distance += (start_height - self.position.y).max(0.0);
+        if self.on_ground {
+            let fallen = self.fall_distance;
+            self.fall_distance = 0.0;
+            (!was_on_ground).then_some(fallen)
+        } else {
+            None
+        }
+    }
+
+    fn move_axis(&mut self, world: &World, axis: usize, distance: f32) {
+        if distance == 0.0 {
+            return;
+        }
+        self.position[axis] += distance;
+        if self.noclip {
+            return;
+        }
+
+        let aabb = self.aabb();
+        let mut blocked = None;
+        for voxel in aabb.voxels() {
+            if !is_solid(world, voxel) {
+                continue;
+            }
+            let voxel_box = Aabb::new(voxel.cast(), voxel.cast::<f32>() + Vector3::repeat(1.0));
+            if !aabb.intersects(&voxel_box) {
+                continue;
+            }
+            let limit = if distance > 0.0 { voxel_box.min[axis] } else { voxel_box.max[axis] };
+            blocked = Some(blocked.map_or(limit, |blocked: f32| {
+                if distance > 0.0 { blocked.min(limit) } else { blocked.max(limit) }
+            }));
+        }
+
+        if let Some(limit) = blocked {
+            let offset = self.position[axis] - aabb.min[axis];
+            self.position[axis] = if distance > 0.0 {
+                limit - (aabb.max[axis] - aabb.min[axis]) + offset - SKIN
+            } else {
+                limit + offset + SKIN
+            };
+            self.velocity[axis] = 0.0;
+            if axis == 1 && distance < 0.0 {
+                self.on_ground = true;
+            }
+        }
+    }
+}
+
+/// Whether a voxel blocks movement. Unloaded chunks are treated as solid so bodies do
+/// not fall out of the world.
+pub fn is_solid(world: &World, position: Vector3<i32>) -> bool {
+    match world.get_voxel(position) {
+        None => true,
+        Some(None) => false,
+        Some(Some(voxel)) => world.registry().get(voxel.block).solid,
+    }
+}
+
+/// The voxel a ray hit, and the direction of the face it entered through.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RaycastHit {
+    pub position: Vector3<i32>,
+    pub normal: Vector3<i32>,
+}
+
+/// Walks the voxels along a ray and returns the first one holding a block that can be
+/// targeted. Fluids and unloaded voxels are passed through.
+pub fn raycast(world: &World, origin: Vector3<f32>, direction: Vector3<f32>, max_distance: f32) -> Option<RaycastHit> {
+    let direction = direction.try_normalize(f32::EPSILON)?;
+    let mut position = origin.map(|c| c.floor() as i32);
+    let step = direction.map(|c| if c > 0.0 { 1 } else { -1 });
+    // Distance along the ray between voxel boundaries, and to the next boundary, per axis.
+    let delta = direction.map(|c| if c == 0.0 { f32::INFINITY } else { 1.0 / c.abs() });
+    let mut next = Vector3::from_fn(|axis, _| {
+        let boundary = if step[axis] > 0 { position[axis] as f32 + 1.0 } else { position[axis] as f32 };
+        (boundary - origin[axis]).abs() * delta[axis]
+    });
+    let mut normal = Vector3::zeros();
+
+    let mut distance = 0.0;
+    while distance <= max_distance {
+        let targetable = world.block_type(position).is_some_and(|block| block.fluid.is_none());
+        if targetable {
+            return Some(RaycastHit { position, normal });
+        }
+        let axis = next.imin();
+        distance = next[axis];
+        next[axis] += delta[axis];
+        position[axis] += step[axis];
+        normal = Vector3::zeros();
+        normal[axis] = -step[axis];
+    }
+    None
+}
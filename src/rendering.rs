@@ -1,7 +1,28 @@
+pub mod chunk_mesh;
+pub use chunk_mesh::{ChunkMesh, TranslucentMesh};
+
+pub mod console;
+pub use console::ConsoleRenderer;
+
+pub mod crack;
+pub use crack::CrackRenderer;
+
 pub mod mesh;
 pub use mesh::Mesh;
 
+pub mod outline;
+pub use outline::OutlineRenderer;
+
+pub mod overlay;
+pub use overlay::OverlayRenderer;
+
 pub mod primitives;
 
 pub mod render;
 pub use render::*;
+
+pub mod sky;
+pub use sky::SkyRenderer;
+
+pub mod text;
+pub use text::{text_width, PlacedGlyph, TextLayout, TextRenderer};
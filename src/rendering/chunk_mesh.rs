@@ -0,0 +1,185 @@
+use std::rc::Rc;
+
+use nalgebra::{Point3, Vector2, Vector3};
+
+use crate::{asset::Texture, math::Direction, world::{block::BlockRegistry, chunk::{chunk_coordinates, CHUNK_VOLUME}, light::{LightColor, MAX_LIGHT}, to_world_coordinates, voxel::Voxel, World}};
+
+use super::Mesh;
+
+/// Distance the camera has to move before translucent faces are sorted again.
+const SORT_DISTANCE: f32 = 1.0;
+
+pub struct ChunkMesh {
+    pub opaque: Mesh,
+    pub translucent: TranslucentMesh,
+}
+
+impl ChunkMesh {
+    /// Builds the opaque and translucent meshes of the chunk at `position`, skipping faces
+    /// hidden by their neighbours, also in the chunks around it. `None` when the chunk is
+    /// not loaded.
+    pub fn generate(world: &World, position: Vector3<i32>, texture: Rc<Texture>) -> Option<Self> {
+        let chunk = world.chunk(position)?;
+        let registry = world.registry();
+        let cube_vertices: [Vector3<f32>; 8] = [
+            Vector3::new(1.0, 0.0, 1.0), // 0. Left bottom back
+        Vector3::new(0.0, 0.0, 1.0), // 1. Right bottom back
+        Vector3::new(1.0, 1.0, 1.0), // 2. Left top back
+        Vector3::new(0.0, 1.0, 1.0), // 3. Right top back
+        Vector3::new(1.0, 0.0, 0.0), // 4. Left bottom front
+        Vector3::new(0.0, 0.0, 0.0), // 5. Right bottom front
+        Vector3::new(1.0, 1.0, 0.0), // 6. Left top front
+        Vector3::new(0.0, 1.0, 0.0), // 7. Right top front
+        ];
+        let cube_indices: [(Direction, [usize; 4]); 6] = [
+            (Direction::Left, [0, 4, 2, 6]),
+            (Direction::Right, [5, 1, 7, 3]),
+            (Direction::Up, [6, 7, 2, 3]),
+            (Direction::Down, [0, 1, 4, 5]),
+            (Direction::Back, [1, 0, 3, 2]),
+            (Direction::Front, [4, 5, 6, 7]),
+        ];
+        let mut opaque = MeshBuilder::new();
+        let mut translucent = MeshBuilder::new();
+
+        for i in 0..CHUNK_VOLUME {
+            let Some(voxel) = chunk.chunk_data[i] else {
+                continue;
+            };
+            let block = registry.get(voxel.block);
+            let builder = if block.translucent { &mut translucent } else { &mut opaque };
+            let coordinates = chunk_coordinates(i);
+            let world_position = to_world_coordinates(position, coordinates);
+            let displacement = coordinates.cast::<f32>();
+            let top_heights = if block.fluid.is_some() {
+                world.fluid_corner_heights(world_position, &voxel)
+            } else {
+                [1.0; 4]
+            };
+
+            for (direction, vertex_pattern) in &cube_indices {
+                let neighbour = world.get_neighbour(world_position, direction);
+                if neighbour.is_some_and(|neighbour| hides_face(&voxel, neighbour, registry)) {
+                    continue;
+                }
+                // A face is lit by the light in the voxel it looks into.
+                let light = chunk.get_light(coordinates + direction.facing()).unwrap_or([0; 3]);
+                builder.add_face(vertex_pattern.map(|vertex_i| {
+                    let mut vertex = cube_vertices[vertex_i];
+                    if vertex.y > 0.0 {
+                        vertex.y = top_heights[vertex.x as usize + 2 * vertex.z as usize];
+                    }
+                    vertex + displacement
+                }), light);
+            }
+        }
+
+        Some(Self {
+            opaque: opaque.build(Some(texture.clone())),
+            translucent: translucent.build_translucent(Some(texture)),
+        })
+    }
+}
+
+/// Mesh of translucent faces that keeps the face centres around, so the faces can be
+/// drawn back to front.
+pub struct TranslucentMesh {
+    pub mesh: Mesh,
+    face_centres: Vec<Vector3<f32>>,
+    index_pattern: [u32; 6],
+    sorted_from: Option<Point3<f32>>,
+}
+
+impl TranslucentMesh {
+    /// `face_centres` holds a centre for every quad in `mesh`, where quad `i` uses
+    /// vertices `4*i..4*i+4` connected by `index_pattern`.
+    pub fn new(mesh: Mesh, face_centres: Vec<Vector3<f32>>, index_pattern: [u32; 6]) -> Self {
+        Self { mesh, face_centres, index_pattern, sorted_from: None }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.face_centres.is_empty()
+    }
+
+    /// Orders the faces back to front as seen from `camera_position`, given in the local
+    /// space of the chunk. Skipped while the camera stays close to the last sorted position.
+    pub fn sort(&mut self, camera_position: &Point3<f32>) {
+        if self.sorted_from.is_some_and(|sorted_from| nalgebra::distance(&sorted_from, camera_position) < SORT_DISTANCE) {
+            return;
+        }
+        self.sorted_from = Some(*camera_position);
+
+        let distances: Vec<f32> = self.face_centres
+            .iter()
+            .map(|centre| (centre - camera_position.coords).norm_squared())
+            .collect();
+        let mut order: Vec<usize> = (0..self.face_centres.len()).collect();
+        order.sort_by(|&a, &b| distances[b].total_cmp(&distances[a]));
+
+        let indices: Vec<u32> = order
+            .iter()
+            .flat_map(|&face_i| self.index_pattern.map(|index_i| index_i + 4 * face_i as u32))
+            .collect();
+        self.mesh.update_indices(&indices);
+    }
+}
+
+/// Whether `neighbour` covers the face of `voxel` it touches. Opaque blocks hide every
+/// face, translucent blocks and fluids only hide faces of the same block type.
+fn hides_face(voxel: &Voxel, neighbour: &Option<Voxel>, registry: &BlockRegistry) -> bool {
+    let Some(neighbour) = neighbour else {
+        return false;
+    };
+    let neighbour_type = registry.get(neighbour.block);
+    neighbour_type.opaque ||
+        ((neighbour_type.translucent || neighbour_type.fluid.is_some()) && neighbour.block == voxel.block)
+}
+
+const INDEX_PATTERN: [u32; 6] = [0, 1, 2, 2, 1, 3];
+const BASE_UVS: [Vector2<f32>; 4] = [
+    Vector2::new(0.0, 1.0), // Bottom left
+    Vector2::new(1.0, 1.0), // Bottom right
+    Vector2::new(0.0, 0.0), // Top left
+    Vector2::new(1.0, 0.0), // Top right
+];
+
+struct MeshBuilder {
+    indices: Vec<u32>,
+    vertices: Vec<Vector3<f32>>,
+    uvs: Vec<Vector2<f32>>,
+    lights: Vec<f32>,
+    face_centres: Vec<Vector3<f32>>,
+}
+
+impl MeshBuilder {
+    fn new() -> Self {
+        Self { indices: Vec::new(), vertices: Vec::new(), uvs: Vec::new(), lights: Vec::new(), face_centres: Vec::new() }
+    }
+
+    fn add_face(&mut self, corners: [Vector3<f32>; 4], light: LightColor) {
+        let face_i = self.face_centres.len() as u32;
+        self.indices.extend(INDEX_PATTERN.map(|index_i| index_i + 4*face_i));
+        self.vertices.extend(corners);
+        self.uvs.extend(BASE_UVS);
+        for _ in 0..4 {
+            self.lights.extend(light.map(|channel| channel as f32 / MAX_LIGHT as f32));
+        }
+        self.face_centres.push(corners.iter().sum::<Vector3<f32>>() / 4.0);
+    }
+
+    fn build(&self, texture: Option<Rc<Texture>>) -> Mesh {
+        Mesh::new(
+            self.indices.len() as i32,
+            &self.indices,
+            &self.vertices,
+            Some(&self.lights), 
+            Some(&self.uvs), 
+            texture,
+        )
+    }
+
+    fn build_translucent(self, texture: Option<Rc<Texture>>) -> TranslucentMesh {
+        let mesh = self.build(texture);
+        TranslucentMesh::new(mesh, self.face_centres, INDEX_PATTERN)
+    }
+}
@@ -0,0 +1,97 @@
+use nalgebra::{Vector3, Vector4};
+
+use crate::ui::Console;
+
+use super::{text_width, OverlayRenderer, TextLayout, TextRenderer};
+
+/// Part of the screen height the open console covers.
+const HEIGHT_FRACTION: f32 = 0.5;
+/// Space between the edges of the console and its text in pixels.
+const MARGIN: f32 = 6.0;
+const SCROLLBAR_WIDTH: f32 = 4.0;
+const CARET_WIDTH: f32 = 2.0;
+/// Space between two suggestions in pixels.
+const SUGGESTION_SPACING: f32 = 12.0;
+const PROMPT: &str = "> ";
+const BACKGROUND_COLOR: Vector4<f32> = Vector4::new(0.05, 0.05, 0.08, 0.75);
+const INPUT_COLOR: Vector4<f32> = Vector4::new(0.0, 0.0, 0.0, 0.6);
+const SCROLLBAR_COLOR: Vector4<f32> = Vector4::new(1.0, 1.0, 1.0, 0.4);
+const CARET_COLOR: Vector4<f32> = Vector4::new(1.0, 1.0, 1.0, 0.8);
+const LOG_TEXT_COLOR: Vector3<f32> = Vector3::new(0.85, 0.85, 0.85);
+const INPUT_TEXT_COLOR: Vector3<f32> = Vector3::new(1.0, 1.0, 1.0);
+const SUGGESTION_TEXT_COLOR: Vector3<f32> = Vector3::new(0.6, 0.6, 0.6);
+const SELECTED_TEXT_COLOR: Vector3<f32> = Vector3::new(1.0, 0.85, 0.3);
+
+/// Draws the open console as a panel over the top of the screen, with the input line at
+/// its bottom and the suggestions for it just below.
+pub struct ConsoleRenderer {
+    overlay: OverlayRenderer,
+    line_height: f32,
+}
+
+impl ConsoleRenderer {
+    /// `line_height` is the line height of the font the console is drawn with.
+    pub fn new(overlay: OverlayRenderer, line_height: f32) -> Self {
+        Self { overlay, line_height }
+    }
+
+    /// Number of log lines that fit above the input line, when none of them wrap.
+    pub fn visible_line_count(&self) -> usize {
+        let height = self.overlay.screen_size().y * HEIGHT_FRACTION;
+        ((height - self.line_height) / self.line_height).max(0.0) as usize
+    }
+
+    /// Draws the panels and queues the text on `text`, which draws it with its next
+    /// `render`.
+    pub fn render(&self, console: &Console, text: &mut TextRenderer) {
+        if !console.is_open() {
+            return;
+        }
+        let width = self.overlay.screen_size().x;
+        let height = self.overlay.screen_size().y * HEIGHT_FRACTION;
+        let input_top = height - self.line_height;
+        self.overlay.fill(0.0, 0.0, width, height, BACKGROUND_COLOR);
+        self.overlay.fill(0.0, input_top, width, self.line_height, INPUT_COLOR);
+
+        // The newest lines go at the bottom, and long lines wrap upwards.
+        let mut bottom = input_top;
+        for line in console.visible_lines(self.visible_line_count()).rev() {
+            let layout = TextLayout::new(text.font(), line, Some(width - 2.0 * MARGIN - SCROLLBAR_WIDTH));
+            bottom -= layout.size.y;
+            if bottom < 0.0 {
+                break;
+            }
+            text.queue_layout(&layout, MARGIN, bottom, LOG_TEXT_COLOR);
+        }
+
+        // The scrollbar shows which part of the scrollback is in view.
+        let total = console.lines().count();
+        let visible = self.visible_line_count();
+        if total > visible {
+            let thumb = input_top * visible as f32 / total as f32;
+            let end = input_top * (1.0 - console.scroll() as f32 / total as f32);
+            self.overlay.fill(width - SCROLLBAR_WIDTH, end - thumb, SCROLLBAR_WIDTH, thumb, SCROLLBAR_COLOR);
+        }
+
+        text.queue(&format!("{}{}", PROMPT, console.input()), MARGIN, input_top, INPUT_TEXT_COLOR, None);
+        let before_cursor = format!("{}{}", PROMPT, &console.input()[..console.cursor()]);
+        let caret = MARGIN + text_width(text.font(), &before_cursor) as f32;
+        self.overlay.fill(caret, input_top + 2.0, CARET_WIDTH, self.line_height - 4.0, CARET_COLOR);
+
+        if console.suggestions().is_empty() {
+            return;
+        }
+        self.overlay.fill(0.0, height, width, self.line_height, INPUT_COLOR);
+        let mut left = MARGIN;
+        for (i, suggestion) in console.suggestions().iter().enumerate() {
+            let suggestion_width = text_width(text.font(), suggestion) as f32;
+            if left + suggestion_width > width - MARGIN {
+                text.queue("...", left, height, SUGGESTION_TEXT_COLOR, None);
+                break;
+            }
+            let color = if console.selected_suggestion() == Some(i) { SELECTED_TEXT_COLOR } else { SUGGESTION_TEXT_COLOR };
+            text.queue(suggestion, left, height, color, None);
+            left += suggestion_width + SUGGESTION_SPACING;
+        }
+    }
+}
@@ -0,0 +1,62 @@
+use std::rc::Rc;
+
+use nalgebra::{Isometry3, Vector3};
+
+use crate::{asset::{Shader, Texture}, camera::Camera, math::Direction};
+
+use super::{primitives, Mesh};
+
+/// Frames in the crack texture, laid out left to right from barely cracked to broken.
+pub const CRACK_STAGES: i32 = 8;
+/// Distance the decal is drawn in front of the block face to avoid z-fighting.
+const DECAL_OFFSET: f32 = 0.001;
+
+/// Draws cracks over the face of a block that is being broken, as a decal pass after
+/// the chunk geometry.
+pub struct CrackRenderer {
+    shader: Shader,
+    faces: Vec<(Direction, Mesh)>,
+}
+
+impl CrackRenderer {
+    /// `shader` takes a position and a uv attribute and the `mvp`, `stage` and
+    /// `stage_count` uniforms. `texture` holds `CRACK_STAGES` square frames side by side.
+    pub fn new(shader: Shader, texture: Rc<Texture>) -> Self {
+        let faces = Direction::ALL.into_iter().map(|direction| {
+            let data = primitives::face(direction, DECAL_OFFSET);
+            let mesh = Mesh::new(data.indices.len() as i32, &data.indices, &data.vertices, None, Some(&data.uvs), Some(texture.clone()));
+            (direction, mesh)
+        }).collect();
+        Self { shader, faces }
+    }
+
+    /// Draws the crack stage for `fraction` of breaking progress on the face of the block
+    /// at `position` pointing along `normal`.
+    pub fn render(&self, position: Vector3<i32>, normal: Vector3<i32>, fraction: f32, camera: &Camera) {
+        if fraction <= 0.0 {
+            return;
+        }
+        let Some((_, mesh)) = self.faces.iter().find(|(direction, _)| direction.facing() == normal) else {
+            return;
+        };
+        let stage = ((fraction * CRACK_STAGES as f32) as i32).min(CRACK_STAGES - 1);
+        let corner = position.cast::<f32>();
+        let transform = Isometry3::translation(corner.x, corner.y, corner.z);
+
+        self.shader.bind();
+        self.shader.uniform_mat4("mvp", camera.mvp(&transform));
+        self.shader.uniform_int("stage", stage);
+        self.shader.uniform_int("stage_count", CRACK_STAGES);
+        unsafe {
+            gl::Enable(gl::BLEND);
+            gl::BlendFunc(gl::SRC_ALPHA, gl::ONE_MINUS_SRC_ALPHA);
+            gl::DepthMask(gl::FALSE);
+        }
+        mesh.draw();
+        unsafe {
+            gl::DepthMask(gl::TRUE);
+            gl::Disable(gl::BLEND);
+        }
+        self.shader.unbind();
+    }
+}
@@ -1,4 +1,6 @@
-use gl::types::GLuint;
+use std::rc::Rc;
+
+use gl::types::{GLenum, GLuint};
 use nalgebra::{Vector3, Vector2};
 
 use crate::asset::Texture;
@@ -8,11 +10,12 @@ pub struct Mesh {
     vao_id: GLuint,
     ebo_id: GLuint,
     buffers: Vec<GLuint>,
-    texture: Option<Texture>
+    texture: Option<Rc<Texture>>,
+    draw_mode: GLenum,
 }
 
 impl Mesh {
-    pub fn new(element_count: i32, indices: &Vec<u32>, vertices: &Vec<Vector3<f32>>, colors: Option<&[f32]>, uvs: Option<&Vec<Vector2<f32>>>, texture: Option<Texture>) -> Self {
+    pub fn new(element_count: i32, indices: &Vec<u32>, vertices: &Vec<Vector3<f32>>, colors: Option<&[f32]>, uvs: Option<&Vec<Vector2<f32>>>, texture: Option<Rc<Texture>>) -> Self {
         let mut vao_id = 0;
         unsafe { 
             gl::GenVertexArrays(1, &mut vao_id); 
@@ -70,7 +73,7 @@ impl Mesh {
 
                 gl::EnableVertexAttribArray(buffers.len() as u32);
                 gl::VertexAttribPointer(
-                    1,
+                    buffers.len() as u32,
                     3,
                     gl::FLOAT,
                     gl::FALSE,
@@ -95,7 +98,7 @@ impl Mesh {
 
                 gl::EnableVertexAttribArray(buffers.len() as u32);
                 gl::VertexAttribPointer(
-                    1,
+                    buffers.len() as u32,
                     2,
                     gl::FLOAT,
                     gl::FALSE,
@@ -116,7 +119,52 @@ impl Mesh {
             vao_id,
             ebo_id,
             buffers,
-            texture
+            texture,
+            draw_mode: gl::TRIANGLES,
+        }
+    }
+
+    /// Sets the primitive the indices describe, such as `gl::LINES`. Triangles by default.
+    pub fn with_draw_mode(mut self, draw_mode: GLenum) -> Self {
+        self.draw_mode = draw_mode;
+        self
+    }
+
+    /// Replaces the index buffer, for example to change the order faces are drawn in.
+    pub fn update_indices(&mut self, indices: &[u32]) {
+        self.element_count = indices.len() as i32;
+        unsafe {
+            gl::BindBuffer(gl::ELEMENT_ARRAY_BUFFER, self.ebo_id);
+            gl::BufferData(
+                gl::ELEMENT_ARRAY_BUFFER,
+                std::mem::size_of_val(indices) as isize,
+                indices.as_ptr().cast(),
+                gl::STATIC_DRAW,
+            );
+            gl::BindBuffer(gl::ELEMENT_ARRAY_BUFFER, 0);
+        }
+    }
+
+    /// Replaces all of the geometry, for meshes rebuilt every frame. The mesh must have
+    /// been created with the same attributes.
+    pub fn update(&mut self, indices: &[u32], vertices: &[Vector3<f32>], colors: Option<&[f32]>, uvs: Option<&[Vector2<f32>]>) {
+        self.element_count = indices.len() as i32;
+        let mut attributes: Vec<(*const std::ffi::c_void, isize)> = vec![(vertices.as_ptr().cast(), std::mem::size_of_val(vertices) as isize)];
+        if let Some(colors) = colors {
+            attributes.push((colors.as_ptr().cast(), std::mem::size_of_val(colors) as isize));
+        }
+        if let Some(uvs) = uvs {
+            attributes.push((uvs.as_ptr().cast(), std::mem::size_of_val(uvs) as isize));
+        }
+        unsafe {
+            gl::BindBuffer(gl::ELEMENT_ARRAY_BUFFER, self.ebo_id);
+            gl::BufferData(gl::ELEMENT_ARRAY_BUFFER, std::mem::size_of_val(indices) as isize, indices.as_ptr().cast(), gl::DYNAMIC_DRAW);
+            gl::BindBuffer(gl::ELEMENT_ARRAY_BUFFER, 0);
+            for (buffer, (data, size)) in self.buffers.iter().zip(attributes) {
+                gl::BindBuffer(gl::ARRAY_BUFFER, *buffer);
+                gl::BufferData(gl::ARRAY_BUFFER, size, data, gl::DYNAMIC_DRAW);
+            }
+            gl::BindBuffer(gl::ARRAY_BUFFER, 0);
         }
     }
 
@@ -131,7 +179,7 @@ impl Mesh {
                 gl::EnableVertexAttribArray(i as u32);
             }
             gl::BindBuffer(gl::ELEMENT_ARRAY_BUFFER, self.ebo_id);
-            gl::DrawElements(gl::TRIANGLES, self.element_count, gl::UNSIGNED_INT, std::ptr::null());
+            gl::DrawElements(self.draw_mode, self.element_count, gl::UNSIGNED_INT, std::ptr::null());
             for i in 0..self.buffers.len() {
                 gl::DisableVertexAttribArray(i as u32);
             }
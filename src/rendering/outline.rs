@@ -0,0 +1,27 @@
+use nalgebra::{Isometry3, Vector3};
+
+use crate::{asset::Shader, camera::Camera};
+
+use super::{primitives, Mesh};
+
+/// Draws lines around the block the player is looking at.
+pub struct OutlineRenderer {
+    shader: Shader,
+    mesh: Mesh,
+}
+
+impl OutlineRenderer {
+    /// `shader` takes a position and a colour attribute and an `mvp` uniform.
+    pub fn new(shader: Shader) -> Self {
+        Self { shader, mesh: primitives::outline_mesh() }
+    }
+
+    pub fn render(&self, position: Vector3<i32>, camera: &Camera) {
+        let corner = position.cast::<f32>();
+        let transform = Isometry3::translation(corner.x, corner.y, corner.z);
+        self.shader.bind();
+        self.shader.uniform_mat4("mvp", camera.mvp(&transform));
+        self.mesh.draw();
+        self.shader.unbind();
+    }
+}
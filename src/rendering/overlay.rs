@@ -0,0 +1,48 @@
+use nalgebra::{Vector2, Vector4};
+
+use crate::asset::Shader;
+
+use super::{primitives, Mesh};
+
+/// Draws flat, blended rectangles over the scene, positioned in pixels from the top left
+/// corner of the screen.
+pub struct OverlayRenderer {
+    shader: Shader,
+    mesh: Mesh,
+    screen_size: Vector2<f32>,
+}
+
+impl OverlayRenderer {
+    /// `shader` takes a position attribute and the `rect`, `screen_size` and
+    /// `overlay_color` uniforms.
+    pub fn new(shader: Shader, screen_width: u32, screen_height: u32) -> Self {
+        Self { shader, mesh: primitives::quad_mesh(), screen_size: Vector2::new(screen_width as f32, screen_height as f32) }
+    }
+
+    pub fn screen_size(&self) -> Vector2<f32> {
+        self.screen_size
+    }
+
+    pub fn set_screen_size(&mut self, screen_width: u32, screen_height: u32) {
+        self.screen_size = Vector2::new(screen_width as f32, screen_height as f32);
+    }
+
+    /// Fills a rectangle with `color`, whose alpha blends it with what is behind.
+    pub fn fill(&self, left: f32, top: f32, width: f32, height: f32, color: Vector4<f32>) {
+        self.shader.bind();
+        self.shader.uniform_vec4("rect", Vector4::new(left, top, width, height));
+        self.shader.uniform_vec2("screen_size", self.screen_size);
+        self.shader.uniform_vec4("overlay_color", color);
+        unsafe {
+            gl::Enable(gl::BLEND);
+            gl::BlendFunc(gl::SRC_ALPHA, gl::ONE_MINUS_SRC_ALPHA);
+            gl::Disable(gl::DEPTH_TEST);
+        }
+        self.mesh.draw();
+        unsafe {
+            gl::Enable(gl::DEPTH_TEST);
+            gl::Disable(gl::BLEND);
+        }
+        self.shader.unbind();
+    }
+}
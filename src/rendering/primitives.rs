@@ -1,6 +1,8 @@
+use std::rc::Rc;
+
 use nalgebra::{Vector3, Vector2};
 
-use crate::{rendering::Mesh, asset::Texture};
+use crate::{rendering::Mesh, asset::Texture, math::Direction};
 
 const VERTEX_COUNT: usize = 4 * 6;
 const INDEX_COUNT: usize = 6 * 6;
@@ -61,7 +63,55 @@ pub fn cube() -> MeshData {
     }
 }
 
-pub fn cube_mesh(texture: Texture) -> Mesh {
+/// Fully lit textured cube, with the same vertex layout as chunk meshes.
+pub fn cube_mesh(texture: Rc<Texture>) -> Mesh {
     let mesh_data = cube();
-    Mesh::new(INDEX_COUNT as i32, &mesh_data.indices, &mesh_data.vertices, None, Some(&mesh_data.uvs), Some(texture))
+    let lights = vec![1.0; 3 * VERTEX_COUNT];
+    Mesh::new(INDEX_COUNT as i32, &mesh_data.indices, &mesh_data.vertices, Some(&lights), Some(&mesh_data.uvs), Some(texture))
+}
+
+/// A unit square covering the face of the unit cube on the `direction` side, pushed
+/// `offset` outwards along the face normal.
+pub fn face(direction: Direction, offset: f32) -> MeshData {
+    let face = Direction::ALL.iter().position(|d| *d == direction).unwrap();
+    let cube = cube();
+    let normal = direction.facing().cast::<f32>() * offset;
+    MeshData {
+        indices: vec![0, 1, 2, 2, 1, 3],
+        vertices: cube.vertices[4 * face..4 * face + 4].iter().map(|vertex| vertex + normal).collect(),
+        uvs: cube.uvs[4 * face..4 * face + 4].to_vec(),
+        vertex_amount: 4,
+    }
+}
+
+/// Gap between a block outline and the faces of its block, so the lines are not hidden
+/// behind the faces.
+const OUTLINE_MARGIN: f32 = 0.002;
+
+/// The twelve edges of a unit cube as black lines.
+pub fn outline_mesh() -> Mesh {
+    let vertices: Vec<Vector3<f32>> = (0..8)
+        .map(|i| Vector3::new((i & 1) as f32, ((i >> 1) & 1) as f32, ((i >> 2) & 1) as f32))
+        .map(|corner| corner * (1.0 + 2.0 * OUTLINE_MARGIN) - Vector3::repeat(OUTLINE_MARGIN))
+        .collect();
+    let indices = vec![
+        0, 1, 2, 3, 4, 5, 6, 7, // Along X
+        0, 2, 1, 3, 4, 6, 5, 7, // Along Y
+        0, 4, 1, 5, 2, 6, 3, 7, // Along Z
+    ];
+    let colors = vec![0.0; 3 * vertices.len()];
+    Mesh::new(indices.len() as i32, &indices, &vertices, Some(&colors), None, None).with_draw_mode(gl::LINES)
+}
+
+/// A unit square in the XY plane from the origin to (1, 1), for drawing rectangles on
+/// the screen.
+pub fn quad_mesh() -> Mesh {
+    let vertices = vec![
+        Vector3::new(0.0, 0.0, 0.0),
+        Vector3::new(1.0, 0.0, 0.0),
+        Vector3::new(0.0, 1.0, 0.0),
+        Vector3::new(1.0, 1.0, 0.0),
+    ];
+    let indices = vec![0, 2, 1, 1, 2, 3];
+    Mesh::new(indices.len() as i32, &indices, &vertices, None, None, None)
 }
@@ -1,15 +1,55 @@
-use nalgebra::Isometry3;
+use nalgebra::{Isometry3, Vector3};
 
-use crate::{camera::Camera, asset::Shader};
+use crate::{camera::Camera, asset::Shader, world::metadata::FogMode};
 
 use super::Mesh;
 
+const FOG_DISABLED: i32 = 0;
+const FOG_LINEAR: i32 = 1;
+const FOG_EXPONENTIAL: i32 = 2;
+
+const TRANSLUCENT_OPACITY: f32 = 0.6;
+
 pub struct MeshRenderer {
     shader: Shader,
+    skylight: f32,
+    fog: Option<FogMode>,
+    fog_color: Vector3<f32>,
+    render_distance: f32,
+    fade_distance: f32,
 }
 
 impl MeshRenderer {
-    pub fn new(shader: Shader) -> Self { Self { shader } }
+    pub fn new(shader: Shader) -> Self {
+        Self {
+            shader,
+            skylight: 1.0,
+            fog: None,
+            fog_color: Vector3::zeros(),
+            render_distance: f32::INFINITY,
+            fade_distance: 0.0,
+        }
+    }
+
+    pub fn set_skylight(&mut self, skylight: f32) {
+        self.skylight = skylight;
+    }
+
+    pub fn set_fog(&mut self, fog: Option<FogMode>) {
+        self.fog = fog;
+    }
+
+    /// Colour that fog and faded chunks blend into, normally the sky colour.
+    pub fn set_fog_color(&mut self, fog_color: Vector3<f32>) {
+        self.fog_color = fog_color;
+    }
+
+    /// Geometry further than `render_distance` is fully blended into the fog colour,
+    /// starting `fade_distance` before that.
+    pub fn set_render_distance(&mut self, render_distance: f32, fade_distance: f32) {
+        self.render_distance = render_distance;
+        self.fade_distance = fade_distance;
+    }
 
     pub fn render(
         &self, 
@@ -17,12 +57,58 @@ impl MeshRenderer {
         mesh: &Mesh, 
         camera: &Camera
     ) {
+        self.draw(transform, mesh, camera, 1.0);
+    }
+
+    /// Draws blended geometry without writing depth. Must be called after all opaque
+    /// geometry has been rendered, in back to front order.
+    pub fn render_translucent(
+        &self,
+        transform: &Isometry3<f32>,
+        mesh: &Mesh,
+        camera: &Camera
+    ) {
+        unsafe {
+            gl::Enable(gl::BLEND);
+            gl::BlendFunc(gl::SRC_ALPHA, gl::ONE_MINUS_SRC_ALPHA);
+            gl::DepthMask(gl::FALSE);
+        }
+        self.draw(transform, mesh, camera, TRANSLUCENT_OPACITY);
+        unsafe {
+            gl::DepthMask(gl::TRUE);
+            gl::Disable(gl::BLEND);
+        }
+    }
+
+    fn draw(&self, transform: &Isometry3<f32>, mesh: &Mesh, camera: &Camera, opacity: f32) {
         let mvp = camera.mvp(transform);
 
         self.shader.bind();
         self.shader.uniform_mat4("mvp", mvp);
+        self.shader.uniform_mat4("model_view", camera.model_view(transform));
         self.shader.uniform_int("texture0", 0);
+        self.shader.uniform_float("skylight", self.skylight);
+        self.shader.uniform_float("opacity", opacity);
+        self.upload_fog();
         mesh.draw();
         self.shader.unbind();
     }
+
+    fn upload_fog(&self) {
+        match self.fog {
+            None => self.shader.uniform_int("fog_mode", FOG_DISABLED),
+            Some(FogMode::Linear { start, end }) => {
+                self.shader.uniform_int("fog_mode", FOG_LINEAR);
+                self.shader.uniform_float("fog_start", start);
+                self.shader.uniform_float("fog_end", end);
+            },
+            Some(FogMode::Exponential { density }) => {
+                self.shader.uniform_int("fog_mode", FOG_EXPONENTIAL);
+                self.shader.uniform_float("fog_density", density);
+            },
+        }
+        self.shader.uniform_vec3("fog_color", self.fog_color);
+        self.shader.uniform_float("render_distance", self.render_distance);
+        self.shader.uniform_float("fade_distance", self.fade_distance);
+    }
 }
@@ -0,0 +1,62 @@
+use std::rc::Rc;
+
+use crate::{asset::{Shader, Texture}, camera::Camera, world::time::WorldClock};
+
+use super::{primitives, Mesh};
+
+enum Sky {
+    Cubemap,
+    Procedural,
+}
+
+/// Draws the sky around the camera. Must be rendered before any other geometry, as it
+/// does not write to the depth buffer.
+pub struct SkyRenderer {
+    shader: Shader,
+    mesh: Mesh,
+    sky: Sky,
+}
+
+impl SkyRenderer {
+    pub fn cubemap(texture: Rc<Texture>) -> Self {
+        let shader = Shader::from_file("resources/shader/skybox.vert", "resources/shader/skybox.frag");
+        Self { shader, mesh: sky_mesh(Some(texture)), sky: Sky::Cubemap }
+    }
+
+    /// A gradient sky dome with sun and moon discs that follow the world time.
+    pub fn procedural() -> Self {
+        let shader = Shader::from_file("resources/shader/skybox.vert", "resources/shader/sky.frag");
+        Self { shader, mesh: sky_mesh(None), sky: Sky::Procedural }
+    }
+
+    pub fn render(&self, camera: &Camera, clock: &WorldClock) {
+        self.shader.bind();
+        self.shader.uniform_mat4("view_projection", camera.rotation_view_projection());
+        match self.sky {
+            Sky::Cubemap => {
+                self.shader.uniform_int("cubemap", 0);
+            },
+            Sky::Procedural => {
+                self.shader.uniform_vec3("sky_color", clock.sky_color());
+                self.shader.uniform_vec3("sun_direction", clock.sun_direction());
+                self.shader.uniform_float("daylight", clock.daylight());
+            },
+        }
+
+        unsafe {
+            gl::DepthMask(gl::FALSE);
+            gl::Disable(gl::CULL_FACE);
+        }
+        self.mesh.draw();
+        unsafe {
+            gl::Enable(gl::CULL_FACE);
+            gl::DepthMask(gl::TRUE);
+        }
+        self.shader.unbind();
+    }
+}
+
+fn sky_mesh(texture: Option<Rc<Texture>>) -> Mesh {
+    let mesh_data = primitives::cube();
+    Mesh::new(mesh_data.indices.len() as i32, &mesh_data.indices, &mesh_data.vertices, None, None, texture)
+}
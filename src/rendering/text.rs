@@ -0,0 +1,280 @@
+use std::rc::Rc;
+
+use nalgebra::{Vector2, Vector3};
+
+use crate::asset::{Font, Shader, Texture};
+
+use super::Mesh;
+
+/// A glyph placed by `TextLayout`, in pixels from the top left corner of the text.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PlacedGlyph {
+    pub character: char,
+    pub position: Vector2<f32>,
+    pub size: Vector2<f32>,
+    /// Corners of the glyph in the atlas, as texture coordinates.
+    pub uv_min: Vector2<f32>,
+    pub uv_max: Vector2<f32>,
+}
+
+/// Where the glyphs of a string go, with kerning and optional wrapping. Needs no GL
+/// context.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct TextLayout {
+    pub glyphs: Vec<PlacedGlyph>,
+    /// Width of the widest line and height of all lines.
+    pub size: Vector2<f32>,
+    pub line_count: usize,
+}
+
+impl TextLayout {
+    /// Lays out `text`, starting a new line at every `\n`. With a `max_width`, lines are
+    /// wrapped at spaces before they get wider, and words wider than a line on their own
+    /// are broken between characters.
+    pub fn new(font: &Font, text: &str, max_width: Option<f32>) -> Self {
+        let mut pen = Pen { font, layout: Self::default(), x: 0, previous: None };
+        let max_width = max_width.map_or(i32::MAX, |width| width.floor() as i32);
+        for (i, paragraph) in text.split('\n').enumerate() {
+            if i > 0 {
+                pen.new_line();
+            }
+            for (j, word) in paragraph.split(' ').enumerate() {
+                if j > 0 {
+                    let kerning = word.chars().next().map_or(0, |first| font.kerning(' ', first));
+                    let with_space = pen.x + pen.advance(' ') + kerning + text_width(font, word);
+                    if with_space > max_width && pen.previous.is_some() {
+                        pen.new_line();
+                    } else {
+                        pen.place(' ');
+                    }
+                }
+                for c in word.chars() {
+                    if pen.x + pen.advance(c) > max_width && pen.previous.is_some() {
+                        pen.new_line();
+                    }
+                    pen.place(c);
+                }
+            }
+        }
+        pen.layout.line_count += 1;
+        pen.layout.size.x = pen.layout.size.x.max(pen.x as f32);
+        pen.layout.size.y = (pen.layout.line_count as i32 * font.line_height) as f32;
+        pen.layout
+    }
+}
+
+/// Width of `text` on a single line, such as the distance from the start of a line to
+/// a cursor.
+pub fn text_width(font: &Font, text: &str) -> i32 {
+    let mut width = 0;
+    let mut previous = None;
+    for c in text.chars() {
+        let kerning = previous.map_or(0, |previous| font.kerning(previous, c));
+        width += kerning + font.glyph(c).map_or(0, |glyph| glyph.x_advance);
+        previous = Some(c);
+    }
+    width
+}
+
+/// Places glyphs one after another, like a pen moving along the lines.
+struct Pen<'a> {
+    font: &'a Font,
+    layout: TextLayout,
+    x: i32,
+    /// The character before the pen on its line, for kerning.
+    previous: Option<char>,
+}
+
+impl Pen<'_> {
+    /// How far placing `c` moves the pen.
+    fn advance(&self, c: char) -> i32 {
+        let kerning = self.previous.map_or(0, |previous| self.font.kerning(previous, c));
+        kerning + self.font.glyph(c).map_or(0, |glyph| glyph.x_advance)
+    }
+
+    fn place(&mut self, c: char) {
+        let Some(glyph) = self.font.glyph(c) else {
+            return;
+        };
+        self.x += self.previous.map_or(0, |previous| self.font.kerning(previous, c));
+        if glyph.width > 0 && glyph.height > 0 {
+            let top = self.layout.line_count as i32 * self.font.line_height;
+            let atlas = Vector2::new(self.font.atlas_width as f32, self.font.atlas_height as f32);
+            let corner = Vector2::new(glyph.x as f32, glyph.y as f32);
+            let size = Vector2::new(glyph.width as f32, glyph.height as f32);
+            self.layout.glyphs.push(PlacedGlyph {
+                character: c,
+                position: Vector2::new((self.x + glyph.x_offset) as f32, (top + glyph.y_offset) as f32),
+                size,
+                uv_min: corner.component_div(&atlas),
+                uv_max: (corner + size).component_div(&atlas),
+            });
+        }
+        self.x += glyph.x_advance;
+        self.previous = Some(c);
+    }
+
+    fn new_line(&mut self) {
+        self.layout.size.x = self.layout.size.x.max(self.x as f32);
+        self.layout.line_count += 1;
+        self.x = 0;
+        self.previous = None;
+    }
+}
+
+/// Draws text over the scene. Text queued during a frame is drawn together by `render`,
+/// as one mesh that is rebuilt every frame.
+pub struct TextRenderer {
+    shader: Shader,
+    font: Rc<Font>,
+    mesh: Mesh,
+    screen_size: Vector2<f32>,
+    indices: Vec<u32>,
+    vertices: Vec<Vector3<f32>>,
+    colors: Vec<f32>,
+    uvs: Vec<Vector2<f32>>,
+}
+
+impl TextRenderer {
+    /// `shader` takes a position, a colour and a uv attribute and a `screen_size`
+    /// uniform. `atlas` is the texture of the font's page.
+    pub fn new(shader: Shader, font: Rc<Font>, atlas: Rc<Texture>, screen_width: u32, screen_height: u32) -> Self {
+        let mesh = Mesh::new(0, &Vec::new(), &Vec::new(), Some(&[]), Some(&Vec::new()), Some(atlas));
+        Self {
+            shader,
+            font,
+            mesh,
+            screen_size: Vector2::new(screen_width as f32, screen_height as f32),
+            indices: Vec::new(),
+            vertices: Vec::new(),
+            colors: Vec::new(),
+            uvs: Vec::new(),
+        }
+    }
+
+    pub fn font(&self) -> &Font {
+        &self.font
+    }
+
+    pub fn set_screen_size(&mut self, screen_width: u32, screen_height: u32) {
+        self.screen_size = Vector2::new(screen_width as f32, screen_height as f32);
+    }
+
+    /// Queues `text` with its top left corner at `left`, `top` in pixels from the top left
+    /// corner of the screen, and returns its size.
+    pub fn queue(&mut self, text: &str, left: f32, top: f32, color: Vector3<f32>, max_width: Option<f32>) -> Vector2<f32> {
+        let layout = TextLayout::new(&self.font, text, max_width);
+        self.queue_layout(&layout, left, top, color);
+        layout.size
+    }
+
+    pub fn queue_layout(&mut self, layout: &TextLayout, left: f32, top: f32, color: Vector3<f32>) {
+        let origin = Vector2::new(left, top);
+        for glyph in &layout.glyphs {
+            let start = self.vertices.len() as u32;
+            let (min, max) = (origin + glyph.position, origin + glyph.position + glyph.size);
+            self.vertices.extend([
+                Vector3::new(min.x, min.y, 0.0),
+                Vector3::new(max.x, min.y, 0.0),
+                Vector3::new(min.x, max.y, 0.0),
+                Vector3::new(max.x, max.y, 0.0),
+            ]);
+            self.uvs.extend([
+                glyph.uv_min,
+                Vector2::new(glyph.uv_max.x, glyph.uv_min.y),
+                Vector2::new(glyph.uv_min.x, glyph.uv_max.y),
+                glyph.uv_max,
+            ]);
+            for _ in 0..4 {
+                self.colors.extend(color.iter());
+            }
+            self.indices.extend([0, 2, 1, 1, 2, 3].map(|i| start + i));
+        }
+    }
+
+    /// Draws everything queued since the last call.
+    pub fn render(&mut self) {
+        if self.indices.is_empty() {
+            return;
+        }
+        self.mesh.update(&self.indices, &self.vertices, Some(&self.colors), Some(&self.uvs));
+        self.shader.bind();
+        self.shader.uniform_vec2("screen_size", self.screen_size);
+        unsafe {
+            gl::Enable(gl::BLEND);
+            gl::BlendFunc(gl::SRC_ALPHA, gl::ONE_MINUS_SRC_ALPHA);
+            gl::Disable(gl::DEPTH_TEST);
+        }
+        self.mesh.draw();
+        unsafe {
+            gl::Enable(gl::DEPTH_TEST);
+            gl::Disable(gl::BLEND);
+        }
+        self.shader.unbind();
+        self.indices.clear();
+        self.vertices.clear();
+        self.colors.clear();
+        self.uvs.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FONT: &str = "\
+common lineHeight=10 base=8 scaleW=64 scaleH=64 pages=1
+page id=0 file=\"test.png\"
+char id=32 x=0 y=0 width=0 height=0 xoffset=0 yoffset=0 xadvance=3
+char id=63 x=20 y=0 width=4 height=6 xoffset=0 yoffset=2 xadvance=4
+char id=97 x=0 y=0 width=4 height=6 xoffset=0 yoffset=2 xadvance=5
+char id=98 x=10 y=0 width=5 height=8 xoffset=1 yoffset=0 xadvance=6
+kerning first=97 second=98 amount=-1
+kerning first=32 second=98 amount=-2
+";
+
+    fn layout(text: &str, max_width: Option<f32>) -> TextLayout {
+        TextLayout::new(&Font::parse(FONT).unwrap(), text, max_width)
+    }
+
+    fn positions(layout: &TextLayout) -> Vec<(f32, f32)> {
+        layout.glyphs.iter().map(|glyph| (glyph.position.x, glyph.position.y)).collect()
+    }
+
+    #[test]
+    fn places_glyphs_with_kerning_and_new_lines() {
+        let kerned = layout("ab", None);
+        assert_eq!(positions(&kerned), vec![(0.0, 2.0), (5.0, 0.0)]);
+        assert_eq!(kerned.size, Vector2::new(10.0, 10.0));
+        assert_eq!(text_width(&Font::parse(FONT).unwrap(), "ab"), 10);
+
+        let lines = layout("a\nb", None);
+        assert_eq!(positions(&lines), vec![(0.0, 2.0), (1.0, 10.0)]);
+        assert_eq!((lines.line_count, lines.size), (2, Vector2::new(6.0, 20.0)));
+    }
+
+    #[test]
+    fn wraps_at_spaces_and_breaks_words_wider_than_a_line() {
+        assert_eq!(layout("aa aa", Some(23.0)).line_count, 1);
+        let wrapped = layout("aa aa", Some(22.0));
+        assert_eq!(positions(&wrapped), vec![(0.0, 2.0), (5.0, 2.0), (0.0, 12.0), (5.0, 12.0)]);
+        assert_eq!(wrapped.size, Vector2::new(10.0, 20.0));
+
+        // The space kerns with the next word, which then still fits.
+        assert_eq!(layout("aa b", Some(17.0)).line_count, 1);
+        assert_eq!(layout("aa b", Some(16.0)).line_count, 2);
+
+        let broken = layout("aaaaa", Some(12.0));
+        assert_eq!(broken.line_count, 3);
+        assert_eq!(positions(&broken)[2], (0.0, 12.0));
+    }
+
+    #[test]
+    fn draws_unknown_characters_as_the_replacement() {
+        let replaced = layout("é", None);
+        assert_eq!(replaced.glyphs.len(), 1);
+        assert_eq!(replaced.glyphs[0].character, 'é');
+        assert_eq!(replaced.glyphs[0].uv_min, Vector2::new(20.0 / 64.0, 0.0));
+        assert_eq!(replaced.size.x, 4.0);
+    }
+}
@@ -0,0 +1,5 @@
+mod host;
+pub use host::Server;
+
+mod properties;
+pub use properties::ServerProperties;
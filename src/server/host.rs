@@ -0,0 +1,496 @@
+use std::{collections::{HashMap, HashSet, VecDeque}, io, net::{SocketAddr, TcpListener}};
+
+use nalgebra::Vector3;
+
+use crate::{command::{CommandContext, CommandRegistry, CommandSender, Permission}, ecs::{components::{self, Model, Name}, player::{GameMode, InputState, PlayerData, PlayerInput}, Entity}, network::{Announcement, Announcer, Connection, Message, Transport, PROTOCOL_VERSION}, physics::PhysicsBody, simulation::Simulation, transform::Transform, world::{storage::encode_chunk_voxels, time::TICKS_PER_SECOND, to_chunk_coordinates}};
+
+use super::ServerProperties;
+
+/// Ticks between keep-alive messages, which also carry the time of day.
+const KEEP_ALIVE_INTERVAL: u64 = 5 * TICKS_PER_SECOND;
+/// Ticks without any message from a client before it is disconnected.
+const TIMEOUT: u64 = 30 * TICKS_PER_SECOND;
+const MAX_NAME_LENGTH: usize = 16;
+/// Most inputs queued for a client. A client further ahead than this loses its oldest
+/// inputs, and corrects its player once it learns where the server moved it.
+const MAX_QUEUED_INPUTS: usize = 20;
+/// Longest chat message or command line accepted from a client.
+const MAX_CHAT_LENGTH: usize = 256;
+
+enum ClientState {
+    Handshaking,
+    LoggingIn,
+    Playing { name: String, player: Entity },
+}
+
+struct RemoteClient {
+    connection: Box<dyn Transport>,
+    state: ClientState,
+    /// Server tick of the last message from the client.
+    last_message: u64,
+    /// Chunks the client was sent and has not been told to unload.
+    chunks: HashSet<Vector3<i32>>,
+    /// Entities the client was told about, with the position it last received.
+    tracked: HashMap<Entity, Vector3<f32>>,
+    /// Inputs received but not applied yet, oldest first. One is applied every tick.
+    inputs: VecDeque<(u32, InputState)>,
+    /// Number of the input the player moved with during the last tick.
+    last_input: u32,
+    /// State of its own player the client last received, with the input it included.
+    player_state: Option<(u32, Vector3<f32>, Vector3<f32>)>,
+    /// Game mode of its player the client last received.
+    game_mode: Option<GameMode>,
+    /// Set once the client was told to disconnect, it is dropped after the next flush.
+    disconnected: bool,
+}
+
+impl RemoteClient {
+    fn new(connection: Box<dyn Transport>, ticks: u64) -> Self {
+        Self {
+            connection,
+            state: ClientState::Handshaking,
+            last_message: ticks,
+            chunks: HashSet::new(),
+            tracked: HashMap::new(),
+            inputs: VecDeque::new(),
+            last_input: 0,
+            player_state: None,
+            game_mode: None,
+            disconnected: false,
+        }
+    }
+
+    fn disconnect(&mut self, reason: &str) {
+        if !self.disconnected {
+            self.connection.send(&Message::Disconnect { reason: reason.to_owned() });
+            self.disconnected = true;
+        }
+    }
+}
+
+/// Accepts clients over TCP and keeps them in sync with a `Simulation`.
+pub struct Server {
+    listener: TcpListener,
+    properties: ServerProperties,
+    clients: Vec<RemoteClient>,
+    /// Announces the server on the local network, if enabled.
+    announcer: Option<Announcer>,
+    /// Whether the last announcement failed, so a lasting failure is reported once.
+    announce_failed: bool,
+    commands: CommandRegistry,
+    ticks: u64,
+}
+
+impl Server {
+    /// Listens on the port from `properties` on every interface, and broadcasts
+    /// announcements when LAN discovery is enabled.
+    pub fn bind(properties: ServerProperties) -> io::Result<Self> {
+        let listener = TcpListener::bind(("0.0.0.0", properties.port))?;
+        listener.set_nonblocking(true)?;
+        let announcer = if properties.lan_discovery {
+            Announcer::broadcast().map_err(|error| eprintln!("LAN discovery is disabled: {}", error)).ok()
+        } else {
+            None
+        };
+        Ok(Self { listener, properties, clients: Vec::new(), announcer, announce_failed: false, commands: CommandRegistry::with_defaults(), ticks: 0 })
+    }
+
+    /// Replaces the announcer, for example to announce to a single address, or stops
+    /// announcing with `None`.
+    pub fn set_announcer(&mut self, announcer: Option<Announcer>) {
+        self.announcer = announcer;
+    }
+
+    /// What the server announces on the local network.
+    pub fn announcement(&self) -> Announcement {
+        Announcement {
+            motd: self.properties.motd.clone(),
+            port: self.local_address().map_or(self.properties.port, |address| address.port()),
+            players: self.players().len() as u16,
+            max_players: self.properties.max_players as u16,
+            version: PROTOCOL_VERSION,
+        }
+    }
+
+    pub fn local_address(&self) -> io::Result<SocketAddr> {
+        self.listener.local_addr()
+    }
+
+    pub fn properties(&self) -> &ServerProperties {
+        &self.properties
+    }
+
+    /// The commands players and the console can run.
+    pub fn commands(&self) -> &CommandRegistry {
+        &self.commands
+    }
+
+    /// The commands, for registering more.
+    pub fn commands_mut(&mut self) -> &mut CommandRegistry {
+        &mut self.commands
+    }
+
+    /// Runs a command line from the server console and returns its feedback.
+    pub fn run_command(&mut self, line: &str, simulation: &mut Simulation) -> String {
+        self.execute(line, &CommandSender::console(), simulation)
+    }
+
+    /// Sends a line to the chat of every player and prints it to the console.
+    pub fn broadcast_chat(&mut self, text: &str) {
+        println!("{}", text);
+        for client in &mut self.clients {
+            if let ClientState::Playing { .. } = client.state {
+                client.connection.send(&Message::Chat { text: text.to_owned() });
+            }
+        }
+    }
+
+    /// Names of the players that are logged in.
+    pub fn players(&self) -> Vec<&str> {
+        self.clients.iter().filter_map(|client| match &client.state {
+            ClientState::Playing { name, .. } => Some(name.as_str()),
+            _ => None,
+        }).collect()
+    }
+
+    /// Accepts new clients, applies what clients sent and sends them what changed during
+    /// the last simulation tick. Call once after every tick.
+    pub fn tick(&mut self, simulation: &mut Simulation) {
+        self.ticks += 1;
+        self.accept();
+        for i in 0..self.clients.len() {
+            self.receive(i, simulation);
+        }
+
+        let changes = changes_by_chunk(simulation);
+        // Clients unload chunks beyond the view distance plus one, so the server keeps
+        // every chunk a client still has.
+        let centres: Vec<Vector3<i32>> = self.clients.iter().filter_map(|client| match client.state {
+            ClientState::Playing { player, .. } => simulation.entities.get::<PhysicsBody>(player).map(player_chunk),
+            _ => None,
+        }).collect();
+        simulation.load_chunks_around(&centres, self.properties.view_distance);
+        let keep_alive = self.ticks.is_multiple_of(KEEP_ALIVE_INTERVAL);
+        for client in &mut self.clients {
+            let ClientState::Playing { player, .. } = client.state else {
+                continue;
+            };
+            for (chunk, message) in &changes {
+                if client.chunks.contains(chunk) {
+                    client.connection.send(message);
+                }
+            }
+            stream_chunks(client, player, simulation, &self.properties);
+            replicate_entities(client, player, simulation, self.properties.entity_range);
+            if let Some(&game_mode) = simulation.entities.get::<GameMode>(player) {
+                if client.game_mode != Some(game_mode) {
+                    client.game_mode = Some(game_mode);
+                    client.connection.send(&Message::GameModeChange { game_mode });
+                }
+            }
+            // Held actions carry on while no input arrives.
+            if let Some((sequence, state)) = client.inputs.pop_front() {
+                if let Some(input) = simulation.entities.get_mut::<PlayerInput>(player) {
+                    input.apply(&state);
+                }
+                client.last_input = sequence;
+            }
+            if keep_alive {
+                client.connection.send(&Message::KeepAlive { id: self.ticks });
+                client.connection.send(&Message::TimeUpdate { time: simulation.clock.ticks(), paused: simulation.clock.is_paused() });
+            }
+        }
+
+        for client in &mut self.clients {
+            if self.ticks - client.last_message > TIMEOUT {
+                client.disconnect("Timed out");
+            }
+            if client.connection.flush().is_err() || client.connection.is_closed() {
+                client.disconnected = true;
+            }
+        }
+        self.remove_disconnected(simulation);
+
+        let announcement = self.announcement();
+        if let Some(announcer) = &mut self.announcer {
+            // Discovery is a convenience, a network without broadcasts should not stop the
+            // server. Failed announcements are retried at the next interval.
+            match announcer.announce(&announcement) {
+                Ok(_) => self.announce_failed = false,
+                Err(error) => {
+                    if !self.announce_failed {
+                        eprintln!("Failed to announce the server, retrying: {}", error);
+                    }
+                    self.announce_failed = true;
+                },
+            }
+        }
+    }
+
+    /// Disconnects every client, saving their players.
+    pub fn shutdown(&mut self, simulation: &mut Simulation) {
+        for client in &mut self.clients {
+            client.disconnect("Server stopped");
+            let _ = client.connection.flush();
+        }
+        self.remove_disconnected(simulation);
+    }
+
+    /// Adds a client connected over any transport, such as a `LocalTransport`.
+    pub fn add_client(&mut self, transport: impl Transport + 'static) {
+        self.clients.push(RemoteClient::new(Box::new(transport), self.ticks));
+    }
+
+    fn accept(&mut self) {
+        loop {
+            match self.listener.accept() {
+                Ok((stream, _)) => match Connection::new(stream) {
+                    Ok(connection) => self.add_client(connection),
+                    Err(error) => eprintln!("Failed to set up connection: {}", error),
+                },
+                Err(error) if error.kind() == io::ErrorKind::WouldBlock => break,
+                Err(error) => {
+                    eprintln!("Failed to accept connection: {}", error);
+                    break;
+                },
+            }
+        }
+    }
+
+    fn receive(&mut self, i: usize, simulation: &mut Simulation) {
+        loop {
+            let message = match self.clients[i].connection.receive() {
+                Ok(Some(message)) => message,
+                Ok(None) => break,
+                Err(error) => {
+                    self.clients[i].disconnect(&format!("Invalid data: {}", error));
+                    break;
+                },
+            };
+            self.clients[i].last_message = self.ticks;
+            self.handle(i, message, simulation);
+            if self.clients[i].disconnected {
+                break;
+            }
+        }
+        if self.clients[i].connection.is_closed() {
+            self.clients[i].disconnected = true;
+        }
+    }
+
+    fn handle(&mut self, i: usize, message: Message, simulation: &mut Simulation) {
+        match (&self.clients[i].state, message) {
+            (ClientState::Handshaking, Message::Handshake { version }) => {
+                if version == PROTOCOL_VERSION {
+                    self.clients[i].state = ClientState::LoggingIn;
+                } else {
+                    self.clients[i].disconnect(&format!("Server uses protocol version {}, client uses {}", PROTOCOL_VERSION, version));
+                }
+            },
+            (ClientState::LoggingIn, Message::Login { name }) => self.login(i, name, simulation),
+            (ClientState::Playing { .. }, Message::Input { sequence, state }) => {
+                let client = &mut self.clients[i];
+                let newest = client.inputs.back().map_or(client.last_input, |(newest, _)| *newest);
+                if sequence > newest {
+                    client.inputs.push_back((sequence, state));
+                    if client.inputs.len() > MAX_QUEUED_INPUTS {
+                        client.inputs.pop_front();
+                    }
+                }
+            },
+            (ClientState::Playing { .. }, Message::Chat { text }) => self.chat(i, text, simulation),
+            (ClientState::Playing { name, player }, Message::CompletionRequest { line }) => {
+                let sender = CommandSender::player(name, *player, self.permission(name));
+                let candidates = self.commands.complete(&line, &CommandContext { simulation, sender: &sender });
+                self.clients[i].connection.send(&Message::Completions { line, candidates });
+            },
+            (_, Message::KeepAlive { .. }) => {},
+            (_, message) => self.clients[i].disconnect(&format!("Unexpected message {:?}", message)),
+        }
+    }
+
+    /// Runs a command sent by a player, or passes a chat message on to everyone.
+    fn chat(&mut self, i: usize, text: String, simulation: &mut Simulation) {
+        let ClientState::Playing { name, player } = &self.clients[i].state else {
+            return;
+        };
+        let text = text.trim();
+        if text.is_empty() || text.len() > MAX_CHAT_LENGTH || text.chars().any(char::is_control) {
+            return;
+        }
+        if text.starts_with('/') {
+            let sender = CommandSender::player(name, *player, self.permission(name));
+            println!("{} ran {}", name, text);
+            let feedback = self.execute(text, &sender, simulation);
+            self.clients[i].connection.send(&Message::Chat { text: feedback });
+        } else {
+            let line = format!("<{}> {}", name, text);
+            self.broadcast_chat(&line);
+        }
+    }
+
+    fn permission(&self, name: &str) -> Permission {
+        if self.properties.operators.iter().any(|operator| operator.eq_ignore_ascii_case(name)) {
+            Permission::Operator
+        } else {
+            Permission::Player
+        }
+    }
+
+    /// Runs a command line for `sender`, then sends every client the time of day in case
+    /// the command changed it.
+    fn execute(&mut self, line: &str, sender: &CommandSender, simulation: &mut Simulation) -> String {
+        let feedback = match self.commands.execute(line, &mut CommandContext { simulation, sender }) {
+            Ok(feedback) => feedback,
+            Err(error) => error.to_string(),
+        };
+        for client in &mut self.clients {
+            if let ClientState::Playing { .. } = client.state {
+                client.connection.send(&Message::TimeUpdate { time: simulation.clock.ticks(), paused: simulation.clock.is_paused() });
+            }
+        }
+        feedback
+    }
+
+    fn login(&mut self, i: usize, name: String, simulation: &mut Simulation) {
+        let valid_name = !name.is_empty() && name.len() <= MAX_NAME_LENGTH
+            && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+        let players = self.players();
+        let refusal = if !valid_name {
+            Some("Names are 1 to 16 letters, digits or underscores".to_owned())
+        } else if players.contains(&name.as_str()) {
+            Some(format!("{} is already playing", name))
+        } else if players.len() >= self.properties.max_players {
+            Some("Server is full".to_owned())
+        } else {
+            None
+        };
+        if let Some(reason) = refusal {
+            self.clients[i].disconnect(&reason);
+            return;
+        }
+
+        let data = simulation.load_player(&name).unwrap_or_else(|error| {
+            eprintln!("Failed to load player {}: {}", name, error);
+            PlayerData::new()
+        });
+        let game_mode = data.game_mode;
+        let position = simulation.world.spawn_point();
+        let player = components::spawn_player(&mut simulation.entities, position, data);
+        simulation.entities.insert(player, Name(name.clone()));
+
+        let client = &mut self.clients[i];
+        client.game_mode = Some(game_mode);
+        client.connection.send(&Message::LoginAccepted { player: player.to_bits(), position, game_mode, time: simulation.clock.ticks() });
+        client.connection.send(&Message::TimeUpdate { time: simulation.clock.ticks(), paused: simulation.clock.is_paused() });
+        println!("{} joined the game in {} mode", name, game_mode);
+        client.state = ClientState::Playing { name, player };
+    }
+
+    fn remove_disconnected(&mut self, simulation: &mut Simulation) {
+        for client in self.clients.iter().filter(|client| client.disconnected) {
+            let ClientState::Playing { name, player } = &client.state else {
+                continue;
+            };
+            if let Some(data) = PlayerData::from_entity(&simulation.entities, *player) {
+                if let Err(error) = simulation.save_player(name, &data) {
+                    eprintln!("Failed to save player {}: {}", name, error);
+                }
+            }
+            simulation.entities.despawn(*player);
+            println!("{} left the game", name);
+        }
+        self.clients.retain(|client| !client.disconnected);
+    }
+}
+
+/// The voxels that changed during the last tick as one message per chunk.
+fn changes_by_chunk(simulation: &mut Simulation) -> Vec<(Vector3<i32>, Message)> {
+    let mut changes = HashMap::new();
+    for position in simulation.world.take_changed_voxels() {
+        let (chunk, local) = to_chunk_coordinates(position);
+        changes.entry(chunk).or_insert_with(Vec::new).push((local, simulation.world.get_voxel(position).copied().flatten()));
+    }
+    changes.into_iter().map(|(chunk, changes)| (chunk, Message::BlockChanges { chunk, changes })).collect()
+}
+
+/// Sends the client the nearest chunks within the view distance it does not have yet,
+/// and unloads the ones it left behind. Chunks are unloaded one chunk further out than
+/// they are loaded, so walking along a chunk border does not send them over and over.
+fn stream_chunks(client: &mut RemoteClient, player: Entity, simulation: &Simulation, properties: &ServerProperties) {
+    let Some(body) = simulation.entities.get::<PhysicsBody>(player) else {
+        return;
+    };
+    let centre = player_chunk(body);
+    let distance = |position: &Vector3<i32>| (position - centre).map(|c| c * c).sum();
+
+    let unload_distance = (properties.view_distance + 1).pow(2);
+    let far: Vec<Vector3<i32>> = client.chunks.iter().copied().filter(|position| distance(position) > unload_distance).collect();
+    for position in far {
+        client.chunks.remove(&position);
+        client.connection.send(&Message::ChunkUnload { position });
+    }
+
+    let view_distance = properties.view_distance.pow(2);
+    let mut missing: Vec<Vector3<i32>> = simulation.world.chunks()
+        .map(|(position, _)| *position)
+        .filter(|position| distance(position) <= view_distance && !client.chunks.contains(position))
+        .collect();
+    missing.sort_by_key(|position| (distance(position), position.x, position.y, position.z));
+    for position in missing.into_iter().take(properties.chunks_per_tick) {
+        let chunk = simulation.world.chunk(position).unwrap();
+        client.connection.send(&Message::ChunkData { position, data: encode_chunk_voxels(chunk, simulation.world.registry()) });
+        client.chunks.insert(position);
+    }
+}
+
+/// The chunk a player is in.
+fn player_chunk(body: &PhysicsBody) -> Vector3<i32> {
+    to_chunk_coordinates(body.position.map(|c| c.floor() as i32)).0
+}
+
+/// Tells the client about entities within `range` of its player that appeared, moved or
+/// disappeared, and where its own player is after its last applied input.
+fn replicate_entities(client: &mut RemoteClient, player: Entity, simulation: &Simulation, range: f32) {
+    let entities = &simulation.entities;
+    let Some(centre) = entities.get::<PhysicsBody>(player).map(|body| body.position) else {
+        return;
+    };
+    for (entity, transform) in entities.iter::<Transform>() {
+        let position = transform.position();
+        if entity == player || (position - centre).norm() > range {
+            continue;
+        }
+        match client.tracked.insert(entity, position) {
+            None => client.connection.send(&Message::EntitySpawn {
+                entity: entity.to_bits(),
+                model: entities.get::<Model>(entity).copied(),
+                position,
+            }),
+            Some(previous) if previous != position => client.connection.send(&Message::EntityMove { entity: entity.to_bits(), position }),
+            Some(_) => {},
+        }
+    }
+    let gone: Vec<Entity> = client.tracked.keys().copied().filter(|entity| {
+        let position = entities.get::<Transform>(*entity).map(|transform| transform.position());
+        position.is_none_or(|position| (position - centre).norm() > range)
+    }).collect();
+    for entity in gone {
+        client.tracked.remove(&entity);
+        client.connection.send(&Message::EntityDespawn { entity: entity.to_bits() });
+    }
+
+    if let Some(body) = entities.get::<PhysicsBody>(player) {
+        let state = (client.last_input, body.position, body.velocity);
+        if client.player_state != Some(state) {
+            client.player_state = Some(state);
+            client.connection.send(&Message::PlayerState {
+                input: client.last_input,
+                position: body.position,
+                velocity: body.velocity,
+                on_ground: body.on_ground,
+            });
+        }
+    }
+}
+
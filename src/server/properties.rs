@@ -0,0 +1,95 @@
+use std::{fs, io, path::{Path, PathBuf}};
+
+use crate::encoding::invalid_data;
+
+/// Settings of a dedicated server. Saved as a plain `key=value` file, like the world
+/// metadata.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ServerProperties {
+    /// Directory the world is loaded from and saved to.
+    pub world: PathBuf,
+    pub port: u16,
+    pub max_players: usize,
+    /// Message of the day, shown to players looking for servers.
+    pub motd: String,
+    /// Chunks around a player that are sent to its client.
+    pub view_distance: i32,
+    /// Blocks around a player within which its client is told about entities.
+    pub entity_range: f32,
+    /// Most chunks sent to a client per tick, so joining or moving fast does not flood
+    /// the connection.
+    pub chunks_per_tick: usize,
+    /// Whether the server announces itself to clients on the local network.
+    pub lan_discovery: bool,
+    /// Names of the players allowed to run operator commands, such as `/tp`.
+    pub operators: Vec<String>,
+}
+
+impl Default for ServerProperties {
+    fn default() -> Self {
+        Self {
+            world: PathBuf::from("saves/world"),
+            port: 24680,
+            max_players: 8,
+            motd: "A voxel server".to_owned(),
+            view_distance: 6,
+            entity_range: 48.0,
+            chunks_per_tick: 4,
+            lan_discovery: true,
+            operators: Vec::new(),
+        }
+    }
+}
+
+impl ServerProperties {
+    /// Reads the properties at `path`, writing the defaults there first when the file
+    /// does not exist yet.
+    pub fn load_or_create(path: &Path) -> io::Result<Self> {
+        if !path.exists() {
+            let properties = Self::default();
+            properties.save(path)?;
+            return Ok(properties);
+        }
+        Self::parse(&fs::read_to_string(path)?)
+    }
+
+    /// Parses `key=value` lines. Missing keys keep their defaults, unknown keys are ignored.
+    pub fn parse(text: &str) -> io::Result<Self> {
+        let mut properties = Self::default();
+        for line in text.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let Some((key, value)) = line.split_once('=') else {
+                return Err(invalid_data(format!("Malformed line '{}'", line)));
+            };
+            let value = value.trim();
+            match key.trim() {
+                "world" => properties.world = PathBuf::from(value),
+                "port" => properties.port = value.parse().map_err(|_| invalid_data(format!("Invalid port '{}'", value)))?,
+                "max_players" => properties.max_players = value.parse().map_err(|_| invalid_data(format!("Invalid max_players '{}'", value)))?,
+                "motd" => properties.motd = value.to_owned(),
+                "view_distance" => properties.view_distance = value.parse().map_err(|_| invalid_data(format!("Invalid view_distance '{}'", value)))?,
+                "entity_range" => properties.entity_range = value.parse().map_err(|_| invalid_data(format!("Invalid entity_range '{}'", value)))?,
+                "chunks_per_tick" => properties.chunks_per_tick = value.parse().map_err(|_| invalid_data(format!("Invalid chunks_per_tick '{}'", value)))?,
+                "lan_discovery" => properties.lan_discovery = value.parse().map_err(|_| invalid_data(format!("Invalid lan_discovery '{}'", value)))?,
+                "operators" => properties.operators = value.split(',').map(str::trim).filter(|name| !name.is_empty()).map(str::to_owned).collect(),
+                _ => {},
+            }
+        }
+        Ok(properties)
+    }
+
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, format!(
+            "world={}\nport={}\nmax_players={}\nmotd={}\nview_distance={}\nentity_range={}\nchunks_per_tick={}\nlan_discovery={}\noperators={}\n",
+            self.world.display(), self.port, self.max_players, self.motd,
+            self.view_distance, self.entity_range, self.chunks_per_tick, self.lan_discovery,
+            self.operators.join(","),
+        ))
+    }
+}
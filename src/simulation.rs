@@ -0,0 +1,236 @@
+use std::{fs, io, path::{Path, PathBuf}};
+
+use nalgebra::Vector3;
+
+use crate::{ecs::{components::{self, Model}, player::PlayerData, systems, Entities, Schedule, SystemContext}, item::{ItemRegistry, RecipeBook}, world::{block::BlockRegistry, fluid, generation, metadata::WorldMetadata, storage::ChunkStorage, time::{WorldClock, TICKS_PER_SECOND}, World}};
+
+/// Chunks along X and Z that are loaded when a world is opened, starting at the origin.
+/// Further chunks are loaded around players with `load_chunks_around`.
+pub const WORLD_SIZE: i32 = 4;
+/// Seconds simulated by one tick.
+pub const TICK_DELTA: f32 = 1.0 / TICKS_PER_SECOND as f32;
+
+/// Crafting recipes, see `RecipeBook::parse`.
+const RECIPES_PATH: &str = "resources/recipes.txt";
+/// Water source placed above the terrain when a world is first generated.
+const SPRING: Vector3<i32> = Vector3::new(10, 7, 10);
+
+/// The world, its entities and its clock, advanced together one tick at a time. Has no
+/// window or GL state, so it runs the same in the client and the dedicated server.
+pub struct Simulation {
+    pub world: World,
+    pub entities: Entities,
+    pub clock: WorldClock,
+    pub metadata: WorldMetadata,
+    pub recipes: RecipeBook,
+    schedule: Schedule,
+    /// Where the world is saved. `None` for replicas, whose state belongs to a server.
+    storage: Option<(ChunkStorage, PathBuf)>,
+}
+
+impl Simulation {
+    /// Opens the world saved in `directory`, generating the chunks and metadata that were
+    /// never saved.
+    pub fn open(directory: &Path) -> Self {
+        let metadata = WorldMetadata::load(&directory.join("metadata.txt")).unwrap_or_else(|_| {
+            let name = directory.file_name().map_or("world".into(), |name| name.to_string_lossy());
+            WorldMetadata::new(&name)
+        });
+        let clock = metadata.clock();
+
+        let registry = BlockRegistry::with_defaults();
+        let items = ItemRegistry::with_defaults(&registry);
+        let recipes = load_recipes(&items);
+        let mut world = World::new(registry, items, metadata.seed);
+        world.set_spawn_point(metadata.spawn_point);
+
+        let mut simulation = Self {
+            world,
+            entities: Entities::new(),
+            clock,
+            metadata,
+            recipes,
+            schedule: systems::default_schedule(),
+            storage: Some((ChunkStorage::new(&directory.join("chunks")), directory.to_owned())),
+        };
+        let mut generated = false;
+        for z in 0..WORLD_SIZE {
+            for x in 0..WORLD_SIZE {
+                generated |= simulation.load_chunk(Vector3::new(x, 0, z));
+            }
+        }
+        if generated {
+            let water = simulation.world.registry().id("water").unwrap();
+            simulation.world.set_voxel(SPRING, Some(fluid::source(water)));
+        }
+
+        let cobblestone = simulation.world.registry().id("cobblestone").unwrap();
+        components::spawn_mob(&mut simulation.entities, Vector3::new(20.5, 5.0, 12.5), Model::Block(cobblestone));
+        simulation
+    }
+
+    /// An empty world that is filled by a server. Blocks are not ticked and only the
+    /// systems in `systems::replica_schedule` run, the server does the rest.
+    pub fn replica() -> Self {
+        let registry = BlockRegistry::with_defaults();
+        let items = ItemRegistry::with_defaults(&registry);
+        Self {
+            recipes: load_recipes(&items),
+            world: World::new(registry, items, 0),
+            entities: Entities::new(),
+            clock: WorldClock::default(),
+            metadata: WorldMetadata::new("remote"),
+            schedule: systems::replica_schedule(),
+            storage: None,
+        }
+    }
+
+    pub fn is_replica(&self) -> bool {
+        self.storage.is_none()
+    }
+
+    pub fn tick(&mut self) {
+        self.clock.tick();
+        if !self.is_replica() {
+            self.world.tick();
+        }
+        self.schedule.run(&mut SystemContext { entities: &mut self.entities, world: &mut self.world, delta: TICK_DELTA });
+    }
+
+    /// Writes every loaded chunk and the metadata, including the clock, to the world
+    /// directory. Does nothing for replicas.
+    pub fn save(&mut self) -> io::Result<()> {
+        let Some((storage, directory)) = &self.storage else {
+            return Ok(());
+        };
+        for (position, chunk) in self.world.chunks() {
+            storage.save(*position, chunk, self.world.ticks(), self.world.registry(), self.world.items())?;
+        }
+        self.metadata.store_clock(&self.clock);
+        self.metadata.save(&directory.join("metadata.txt"))
+    }
+
+    /// Loads the chunk at `position` from the world directory, or generates it when it was
+    /// never saved. Returns whether it was generated. Does nothing for replicas, which get
+    /// their chunks from a server.
+    pub fn load_chunk(&mut self, position: Vector3<i32>) -> bool {
+        let Some((storage, _)) = &self.storage else {
+            return false;
+        };
+        match storage.load(position, self.world.ticks(), self.world.registry(), self.world.items()) {
+            Ok(Some(chunk)) => {
+                self.world.insert_chunk(position, chunk);
+                return false;
+            },
+            Ok(None) => {},
+            Err(error) => eprintln!("Failed to load chunk ({}, {}, {}): {}", position.x, position.y, position.z, error),
+        }
+        generation::generate_chunk(&mut self.world, position);
+        true
+    }
+
+    /// Saves the chunk at `position` and removes it from the world. The chunk stays loaded
+    /// when saving fails.
+    pub fn unload_chunk(&mut self, position: Vector3<i32>) -> io::Result<()> {
+        if let (Some((storage, _)), Some(chunk)) = (&self.storage, self.world.chunk(position)) {
+            storage.save(position, chunk, self.world.ticks(), self.world.registry(), self.world.items())?;
+        }
+        self.world.remove_chunk(position);
+        Ok(())
+    }
+
+    /// Loads the chunks within `distance` chunks of any of `centres`, given in chunk
+    /// coordinates, and unloads the chunks further than `distance + 1` from all of them,
+    /// so moving along a chunk border does not load the same chunks over and over. The
+    /// world is one chunk high. Does nothing without centres, and for replicas.
+    pub fn load_chunks_around(&mut self, centres: &[Vector3<i32>], distance: i32) {
+        if self.is_replica() || centres.is_empty() {
+            return;
+        }
+        let within = |position: Vector3<i32>, distance: i32| {
+            centres.iter().any(|centre| (position - centre).map(|c| c * c).sum() <= distance * distance)
+        };
+
+        for position in self.world.chunk_positions() {
+            if within(position, distance + 1) {
+                continue;
+            }
+            if let Err(error) = self.unload_chunk(position) {
+                eprintln!("Failed to save chunk ({}, {}, {}): {}", position.x, position.y, position.z, error);
+            }
+        }
+        for centre in centres {
+            for z in -distance..=distance {
+                for x in -distance..=distance {
+                    let position = Vector3::new(centre.x + x, 0, centre.z + z);
+                    if within(position, distance) && self.world.chunk(position).is_none() {
+                        self.load_chunk(position);
+                    }
+                }
+            }
+        }
+    }
+
+    /// The saved data of the player called `name`, or a new player when there is none.
+    pub fn load_player(&self, name: &str) -> io::Result<PlayerData> {
+        let Some(path) = self.player_path(name) else {
+            return Ok(PlayerData::new());
+        };
+        match fs::read(path) {
+            Ok(bytes) => PlayerData::decode(&bytes, self.world.items()),
+            Err(error) if error.kind() == io::ErrorKind::NotFound => Ok(PlayerData::new()),
+            Err(error) => Err(error),
+        }
+    }
+
+    pub fn save_player(&self, name: &str, data: &PlayerData) -> io::Result<()> {
+        let Some(path) = self.player_path(name) else {
+            return Ok(());
+        };
+        fs::create_dir_all(path.parent().unwrap())?;
+        fs::write(path, data.encode(self.world.items()))
+    }
+
+    fn player_path(&self, name: &str) -> Option<PathBuf> {
+        let (_, directory) = self.storage.as_ref()?;
+        Some(directory.join("players").join(format!("{}.bin", name)))
+    }
+}
+
+/// The recipes in `RECIPES_PATH`. Without them nothing can be crafted, but the game still runs.
+fn load_recipes(items: &ItemRegistry) -> RecipeBook {
+    RecipeBook::load(Path::new(RECIPES_PATH), items).unwrap_or_else(|error| {
+        eprintln!("Failed to load recipes from {}: {}", RECIPES_PATH, error);
+        RecipeBook::new()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::world::{to_world_coordinates, voxel::Voxel};
+
+    use super::*;
+
+    #[test]
+    fn chunks_follow_the_player_and_keep_their_changes() {
+        let directory = std::env::temp_dir().join(format!("voxel_game_chunks_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&directory);
+        let mut simulation = Simulation::open(&directory);
+        let glass = Some(Voxel::new(simulation.world.registry().id("glass").unwrap()));
+        let changed = to_world_coordinates(Vector3::zeros(), Vector3::new(1, 6, 1));
+        simulation.world.set_voxel(changed, glass);
+
+        let far = Vector3::new(20, 0, 0);
+        simulation.load_chunks_around(&[far], 2);
+        assert!(simulation.world.chunk(far).is_some());
+        assert!(simulation.world.chunk(far + Vector3::new(0, 0, 2)).is_some());
+        assert!(simulation.world.chunk(far + Vector3::new(2, 0, 2)).is_none());
+        assert!(simulation.world.chunk(Vector3::zeros()).is_none());
+        assert!(simulation.world.chunk_positions().iter().all(|position| (position - far).map(|c| c * c).sum() <= 9));
+
+        simulation.load_chunks_around(&[Vector3::zeros()], 2);
+        assert_eq!(simulation.world.get_voxel(changed), Some(&glass));
+        assert!(simulation.world.chunk(far).is_none());
+        fs::remove_dir_all(&directory).unwrap();
+    }
+}
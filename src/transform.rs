@@ -1,5 +1,6 @@
 use nalgebra::{Isometry3, Translation3, UnitQuaternion, Vector3};
 
+#[derive(Clone, Debug)]
 pub struct Transform {
     position: Vector3<f32>,
     rotation: UnitQuaternion<f32>,
@@ -15,6 +16,14 @@ impl Transform {
         }
     }
 
+    pub fn position(&self) -> Vector3<f32> {
+        self.position
+    }
+
+    pub fn set_position(&mut self, position: Vector3<f32>) {
+        self.position = position;
+    }
+
     pub fn translate(&mut self, translation: Vector3<f32>) {
         self.position += translation;
     }
@@ -0,0 +1,2 @@
+mod console;
+pub use console::{Console, MAX_HISTORY, MAX_LINES};
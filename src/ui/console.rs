@@ -0,0 +1,395 @@
+use std::collections::VecDeque;
+
+/// Most lines kept in the scrollback, older lines are forgotten.
+pub const MAX_LINES: usize = 500;
+/// Most lines kept in the history of submitted lines.
+pub const MAX_HISTORY: usize = 100;
+
+/// The developer console: a log of output lines with scrollback, and an input line with
+/// a history of submitted lines and completions. Only keeps state, see `ConsoleRenderer`
+/// for drawing it.
+#[derive(Clone, Debug, Default)]
+pub struct Console {
+    open: bool,
+    /// Whether `toggle` opened the console during the current batch of events, so the
+    /// character typed by the same key press is dropped.
+    skip_char: bool,
+    lines: VecDeque<String>,
+    /// Lines scrolled up from the newest line.
+    scroll: usize,
+    input: String,
+    /// Byte offset of the cursor in `input`, always on a character boundary.
+    cursor: usize,
+    history: VecDeque<String>,
+    /// The history entry shown in the input while browsing, counted from the newest.
+    history_index: Option<usize>,
+    /// What was typed before browsing the history, restored when browsing past the newest.
+    draft: String,
+    /// Candidates for the last word of `completion_base`.
+    suggestions: Vec<String>,
+    /// The suggestion last put into the input by `complete`.
+    selected: Option<usize>,
+    /// The input the suggestions are for.
+    completion_base: String,
+    /// Whether the input changed since the last `take_completion_request`.
+    edited: bool,
+}
+
+impl Console {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.open
+    }
+
+    pub fn set_open(&mut self, open: bool) {
+        self.open = open;
+        self.skip_char = false;
+    }
+
+    /// Opens or closes the console from the key bound to it. The character typed by the
+    /// same key press arrives in the same batch of events and is not put into the input.
+    pub fn toggle(&mut self) {
+        self.open = !self.open;
+        self.skip_char = self.open;
+    }
+
+    /// Ends a batch of window events. A key that types no character, such as a dead key,
+    /// then does not cost the character typed after it.
+    pub fn end_events(&mut self) {
+        self.skip_char = false;
+    }
+
+    /// Adds output to the log, one line per line of `text`. A scrolled view keeps showing
+    /// the same lines.
+    pub fn log(&mut self, text: &str) {
+        for line in text.lines() {
+            self.lines.push_back(line.to_owned());
+            if self.scroll > 0 {
+                self.scroll += 1;
+            }
+        }
+        while self.lines.len() > MAX_LINES {
+            self.lines.pop_front();
+        }
+        self.scroll = self.scroll.min(self.lines.len().saturating_sub(1));
+    }
+
+    /// Every line in the scrollback, oldest first.
+    pub fn lines(&self) -> impl Iterator<Item = &str> {
+        self.lines.iter().map(String::as_str)
+    }
+
+    /// The `count` lines that fit the view at the current scroll position, oldest first.
+    pub fn visible_lines(&self, count: usize) -> impl DoubleEndedIterator<Item = &str> {
+        let end = self.lines.len() - self.scroll;
+        self.lines.range(end.saturating_sub(count)..end).map(String::as_str)
+    }
+
+    pub fn scroll(&self) -> usize {
+        self.scroll
+    }
+
+    /// Scrolls towards older lines, stopping at the oldest.
+    pub fn scroll_up(&mut self, lines: usize) {
+        self.scroll = (self.scroll + lines).min(self.lines.len().saturating_sub(1));
+    }
+
+    /// Scrolls towards newer lines, stopping at the newest.
+    pub fn scroll_down(&mut self, lines: usize) {
+        self.scroll = self.scroll.saturating_sub(lines);
+    }
+
+    pub fn input(&self) -> &str {
+        &self.input
+    }
+
+    /// Byte offset of the cursor in the input.
+    pub fn cursor(&self) -> usize {
+        self.cursor
+    }
+
+    /// Inserts a typed character at the cursor. Control characters, and the first
+    /// character in the batch of events in which `toggle` opened the console, are ignored.
+    pub fn type_char(&mut self, c: char) {
+        if std::mem::take(&mut self.skip_char) || c.is_control() {
+            return;
+        }
+        self.input.insert(self.cursor, c);
+        self.cursor += c.len_utf8();
+        self.edit();
+    }
+
+    /// Removes the character before the cursor.
+    pub fn backspace(&mut self) {
+        if let Some(previous) = self.previous_boundary() {
+            self.input.replace_range(previous..self.cursor, "");
+            self.cursor = previous;
+            self.edit();
+        }
+    }
+
+    /// Removes the character after the cursor.
+    pub fn delete(&mut self) {
+        if let Some(next) = self.next_boundary() {
+            self.input.replace_range(self.cursor..next, "");
+            self.edit();
+        }
+    }
+
+    pub fn cursor_left(&mut self) {
+        if let Some(previous) = self.previous_boundary() {
+            self.cursor = previous;
+        }
+    }
+
+    pub fn cursor_right(&mut self) {
+        if let Some(next) = self.next_boundary() {
+            self.cursor = next;
+        }
+    }
+
+    pub fn cursor_home(&mut self) {
+        self.cursor = 0;
+    }
+
+    pub fn cursor_end(&mut self) {
+        self.cursor = self.input.len();
+    }
+
+    /// Shows the previous submitted line in the input.
+    pub fn history_previous(&mut self) {
+        let index = self.history_index.map_or(0, |index| index + 1);
+        if index >= self.history.len() {
+            return;
+        }
+        if self.history_index.is_none() {
+            self.draft = self.input.clone();
+        }
+        self.history_index = Some(index);
+        let line = self.history[self.history.len() - 1 - index].clone();
+        self.replace_input(line);
+    }
+
+    /// Shows the next submitted line in the input, or what was typed before browsing.
+    pub fn history_next(&mut self) {
+        let Some(index) = self.history_index else {
+            return;
+        };
+        let line = match index.checked_sub(1) {
+            Some(index) => {
+                self.history_index = Some(index);
+                self.history[self.history.len() - 1 - index].clone()
+            },
+            None => {
+                self.history_index = None;
+                std::mem::take(&mut self.draft)
+            },
+        };
+        self.replace_input(line);
+    }
+
+    /// The submitted lines, oldest first.
+    pub fn history(&self) -> impl Iterator<Item = &str> {
+        self.history.iter().map(String::as_str)
+    }
+
+    /// Clears the input and returns it, adding it to the history and echoing it to the
+    /// log. Returns `None` for an empty input.
+    pub fn submit(&mut self) -> Option<String> {
+        let line = self.input.trim().to_owned();
+        self.replace_input(String::new());
+        self.history_index = None;
+        self.draft.clear();
+        self.scroll = 0;
+        if line.is_empty() {
+            return None;
+        }
+        if self.history.back() != Some(&line) {
+            self.history.push_back(line.clone());
+            if self.history.len() > MAX_HISTORY {
+                self.history.pop_front();
+            }
+        }
+        self.log(&format!("> {}", line));
+        Some(line)
+    }
+
+    /// The input to look up completions for, when it changed since the last call and
+    /// holds a command. Pass the completions to `set_suggestions`.
+    pub fn take_completion_request(&mut self) -> Option<String> {
+        if !std::mem::take(&mut self.edited) || !self.input.starts_with('/') {
+            return None;
+        }
+        Some(self.input.clone())
+    }
+
+    /// Offers `candidates` for the last word of `line`. Ignored when the input changed
+    /// since `line` was requested.
+    pub fn set_suggestions(&mut self, line: &str, candidates: Vec<String>) {
+        if line != self.input {
+            return;
+        }
+        self.completion_base = line.to_owned();
+        self.suggestions = candidates;
+        self.selected = None;
+    }
+
+    pub fn suggestions(&self) -> &[String] {
+        &self.suggestions
+    }
+
+    /// The suggestion currently in the input, if `complete` put one there.
+    pub fn selected_suggestion(&self) -> Option<usize> {
+        self.selected
+    }
+
+    /// Replaces the last word of the input with the next suggestion, cycling through them
+    /// when called again.
+    pub fn complete(&mut self) {
+        if self.suggestions.is_empty() {
+            return;
+        }
+        let index = self.selected.map_or(0, |index| (index + 1) % self.suggestions.len());
+        let start = self.completion_base.rfind(char::is_whitespace).map_or(0, |space| space + 1);
+        // A completed command name keeps its slash.
+        let start = if start == 0 && self.completion_base.starts_with('/') { 1 } else { start };
+        self.input = format!("{}{}", &self.completion_base[..start], self.suggestions[index]);
+        self.cursor = self.input.len();
+        self.selected = Some(index);
+    }
+
+    fn replace_input(&mut self, input: String) {
+        self.input = input;
+        self.cursor = self.input.len();
+        self.edit();
+    }
+
+    /// Forgets the suggestions, which no longer fit the input.
+    fn edit(&mut self) {
+        self.suggestions.clear();
+        self.selected = None;
+        self.edited = true;
+    }
+
+    fn previous_boundary(&self) -> Option<usize> {
+        self.input[..self.cursor].char_indices().next_back().map(|(index, _)| index)
+    }
+
+    fn next_boundary(&self) -> Option<usize> {
+        self.input[self.cursor..].chars().next().map(|c| self.cursor + c.len_utf8())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn typed(text: &str) -> Console {
+        let mut console = Console::new();
+        console.set_open(true);
+        for c in text.chars() {
+            console.type_char(c);
+        }
+        console
+    }
+
+    #[test]
+    fn drops_only_the_character_of_the_key_that_opened_it() {
+        let mut console = Console::new();
+        console.toggle();
+        for c in "``a`\u{8}".chars() {
+            console.type_char(c);
+        }
+        assert_eq!(console.input(), "`a`");
+
+        // A key that typed nothing does not cost the next character.
+        console.toggle();
+        console.toggle();
+        console.end_events();
+        console.type_char('x');
+        assert_eq!(console.input(), "`a`x");
+
+        console.toggle();
+        console.set_open(true);
+        console.type_char('`');
+        assert_eq!(console.input(), "`a`x`");
+    }
+
+    #[test]
+    fn browses_the_history_and_restores_the_draft() {
+        let mut console = typed("first");
+        console.submit();
+        for c in "second".chars() {
+            console.type_char(c);
+        }
+        console.submit();
+        console.type_char('d');
+
+        console.history_previous();
+        assert_eq!(console.input(), "second");
+        console.history_previous();
+        assert_eq!(console.input(), "first");
+        console.history_previous();
+        assert_eq!(console.input(), "first");
+        console.history_next();
+        assert_eq!(console.input(), "second");
+        console.history_next();
+        assert_eq!(console.input(), "d");
+        assert_eq!(console.cursor(), 1);
+        assert_eq!(console.history().collect::<Vec<_>>(), ["first", "second"]);
+    }
+
+    #[test]
+    fn keeps_the_scrolled_view_while_lines_are_logged() {
+        let mut console = Console::new();
+        for i in 0..10 {
+            console.log(&i.to_string());
+        }
+        console.scroll_up(3);
+        assert_eq!(console.visible_lines(2).collect::<Vec<_>>(), ["5", "6"]);
+        console.log("10\n11");
+        assert_eq!(console.scroll(), 5);
+        assert_eq!(console.visible_lines(2).collect::<Vec<_>>(), ["5", "6"]);
+
+        console.scroll_up(100);
+        assert_eq!(console.scroll(), 11);
+        assert_eq!(console.visible_lines(2).collect::<Vec<_>>(), ["0"]);
+        for i in 0..MAX_LINES {
+            console.log(&i.to_string());
+        }
+        assert_eq!(console.lines().count(), MAX_LINES);
+        assert_eq!(console.scroll(), MAX_LINES - 1);
+        console.scroll_down(MAX_LINES);
+        assert_eq!(console.visible_lines(1).collect::<Vec<_>>(), [(MAX_LINES - 1).to_string()]);
+    }
+
+    #[test]
+    fn cycles_through_completions_of_the_last_word() {
+        let mut console = typed("/tp Al");
+        assert_eq!(console.take_completion_request(), Some("/tp Al".to_owned()));
+        assert_eq!(console.take_completion_request(), None);
+        console.set_suggestions("/tp Al", vec!["Alice".to_owned(), "Alan".to_owned()]);
+
+        console.complete();
+        assert_eq!(console.input(), "/tp Alice");
+        console.complete();
+        assert_eq!(console.input(), "/tp Alan");
+        assert_eq!(console.selected_suggestion(), Some(1));
+        console.complete();
+        assert_eq!(console.input(), "/tp Alice");
+
+        // Typing forgets the suggestions, and stale ones are ignored.
+        console.type_char(' ');
+        assert!(console.suggestions().is_empty());
+        console.set_suggestions("/tp Al", vec!["Alice".to_owned()]);
+        assert!(console.suggestions().is_empty());
+
+        let mut console = typed("/ti");
+        console.set_suggestions("/ti", vec!["time".to_owned()]);
+        console.complete();
+        assert_eq!(console.input(), "/time");
+    }
+}
@@ -1,3 +1,32 @@
+pub mod block;
+
+pub mod block_entity;
+
 pub mod chunk;
 
+pub mod falling;
+
+pub mod fluid;
+
+pub mod generation;
+
+pub mod growth;
+
+pub mod light;
+
+mod map;
+pub use map::*;
+
+pub mod metadata;
+
+pub mod pathfinding;
+
+pub mod storage;
+
+pub mod support;
+
+pub mod tick;
+
+pub mod time;
+
 pub mod voxel;
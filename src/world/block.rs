@@ -0,0 +1,217 @@
+use std::collections::HashMap;
+
+use nalgebra::Vector3;
+
+use crate::item::ToolKind;
+
+use super::{block_entity::{self, BlockEntity}, falling, fluid::{self, FluidProperties}, growth, light::LightColor, map::World, support};
+
+/// Called with the world position of the voxel being ticked.
+pub type TickHandler = fn(&mut World, Vector3<i32>);
+/// Called with the position of the notified voxel and the position of the voxel that changed.
+pub type NeighbourHandler = fn(&mut World, Vector3<i32>, Vector3<i32>);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct BlockId(pub u16);
+
+pub struct BlockType {
+    pub name: String,
+    pub opaque: bool,
+    /// Blocks the movement of physics bodies.
+    pub solid: bool,
+    /// Drawn in the blended pass after all opaque geometry.
+    pub translucent: bool,
+    pub emission: LightColor,
+    pub fluid: Option<FluidProperties>,
+    /// Damages entities touching the block.
+    pub burns: bool,
+    /// Seconds it takes to break the block by hand.
+    pub hardness: f32,
+    /// Tools of this kind break the block faster.
+    pub tool: Option<ToolKind>,
+    /// Runs when a tick scheduled for this block is due.
+    pub on_scheduled_tick: Option<TickHandler>,
+    /// Runs when this block is picked for a random tick.
+    pub on_random_tick: Option<TickHandler>,
+    /// Runs when this voxel or one of its neighbours changed.
+    pub on_neighbour_changed: Option<NeighbourHandler>,
+    /// Creates the block entity placed together with this block.
+    pub block_entity: Option<fn() -> BlockEntity>,
+}
+
+impl BlockType {
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_owned(),
+            opaque: true,
+            solid: true,
+            translucent: false,
+            emission: [0, 0, 0],
+            fluid: None,
+            burns: false,
+            hardness: 1.0,
+            tool: None,
+            on_scheduled_tick: None,
+            on_random_tick: None,
+            on_neighbour_changed: None,
+            block_entity: None,
+        }
+    }
+
+    pub fn with_emission(mut self, emission: LightColor) -> Self {
+        self.emission = emission;
+        self
+    }
+
+    pub fn transparent(mut self) -> Self {
+        self.opaque = false;
+        self
+    }
+
+    pub fn translucent(mut self) -> Self {
+        self.opaque = false;
+        self.translucent = true;
+        self
+    }
+
+    pub fn non_solid(mut self) -> Self {
+        self.solid = false;
+        self
+    }
+
+    /// Makes the block a fluid, which flows on scheduled ticks after its surroundings change.
+    pub fn with_fluid(mut self, fluid: FluidProperties) -> Self {
+        self.solid = false;
+        self.fluid = Some(fluid);
+        self.with_neighbour_changed(fluid::neighbour_changed)
+            .with_scheduled_tick(fluid::update)
+    }
+
+    pub fn with_hardness(mut self, hardness: f32) -> Self {
+        self.hardness = hardness;
+        self
+    }
+
+    pub fn with_tool(mut self, tool: ToolKind) -> Self {
+        self.tool = Some(tool);
+        self
+    }
+
+    pub fn burning(mut self) -> Self {
+        self.burns = true;
+        self
+    }
+
+    pub fn with_scheduled_tick(mut self, handler: TickHandler) -> Self {
+        self.on_scheduled_tick = Some(handler);
+        self
+    }
+
+    pub fn with_random_tick(mut self, handler: TickHandler) -> Self {
+        self.on_random_tick = Some(handler);
+        self
+    }
+
+    pub fn with_neighbour_changed(mut self, handler: NeighbourHandler) -> Self {
+        self.on_neighbour_changed = Some(handler);
+        self
+    }
+
+    pub fn with_block_entity(mut self, create: fn() -> BlockEntity) -> Self {
+        self.block_entity = Some(create);
+        self
+    }
+
+    /// Makes the block break off when the voxel below it is no longer solid.
+    pub fn needs_support(self) -> Self {
+        self.with_neighbour_changed(support::break_if_unsupported)
+    }
+
+    /// Makes the block fall when there is nothing below it to rest on.
+    pub fn with_gravity(self) -> Self {
+        self.with_neighbour_changed(falling::check_support)
+            .with_scheduled_tick(falling::start_falling)
+    }
+
+    pub fn emits_light(&self) -> bool {
+        self.emission.iter().any(|&channel| channel > 0)
+    }
+}
+
+pub struct BlockRegistry {
+    blocks: Vec<BlockType>,
+    names: HashMap<String, BlockId>,
+}
+
+impl BlockRegistry {
+    pub fn new() -> Self {
+        Self { blocks: Vec::new(), names: HashMap::new() }
+    }
+
+    pub fn with_defaults() -> Self {
+        let mut registry = Self::new();
+        registry.register(BlockType::new("cobblestone").with_hardness(2.0).with_tool(ToolKind::Pickaxe));
+        registry.register(BlockType::new("torch")
+            .transparent()
+            .non_solid()
+            .needs_support()
+            .with_emission([14, 11, 7])
+            .with_hardness(0.0));
+        registry.register(BlockType::new("lava")
+            .transparent()
+            .with_emission([15, 6, 0])
+            .burning()
+            .with_fluid(FluidProperties { flow_distance: 3, tick_delay: 30, renews: false }));
+        registry.register(BlockType::new("lamp").with_emission([15, 15, 15]).with_hardness(0.3).with_tool(ToolKind::Pickaxe));
+        registry.register(BlockType::new("water")
+            .translucent()
+            .with_fluid(FluidProperties { flow_distance: 7, tick_delay: 5, renews: true }));
+        registry.register(BlockType::new("glass").translucent().with_hardness(0.3));
+        registry.register(BlockType::new("leaves").translucent().with_random_tick(growth::decay_leaves).with_hardness(0.2));
+        registry.register(BlockType::new("dirt").with_hardness(0.5).with_tool(ToolKind::Shovel));
+        registry.register(BlockType::new("grass").with_random_tick(growth::spread_grass).with_hardness(0.6).with_tool(ToolKind::Shovel));
+        registry.register(BlockType::new("log").with_hardness(2.0).with_tool(ToolKind::Axe));
+        registry.register(BlockType::new("wheat")
+            .transparent()
+            .non_solid()
+            .needs_support()
+            .with_random_tick(growth::grow_crop)
+            .with_hardness(0.0));
+        registry.register(BlockType::new("sand").with_gravity().with_hardness(0.5).with_tool(ToolKind::Shovel));
+        registry.register(BlockType::new("gravel").with_gravity().with_hardness(0.6).with_tool(ToolKind::Shovel));
+        registry.register(BlockType::new("chest").with_block_entity(block_entity::chest).with_hardness(2.5).with_tool(ToolKind::Axe));
+        registry.register(BlockType::new("sign")
+            .transparent()
+            .non_solid()
+            .needs_support()
+            .with_block_entity(block_entity::sign)
+            .with_tool(ToolKind::Axe));
+        registry.register(BlockType::new("furnace").with_block_entity(block_entity::furnace).with_hardness(3.5).with_tool(ToolKind::Pickaxe));
+        registry
+    }
+
+    pub fn register(&mut self, block: BlockType) -> BlockId {
+        let id = BlockId(self.blocks.len() as u16);
+        self.names.insert(block.name.clone(), id);
+        self.blocks.push(block);
+        id
+    }
+
+    pub fn get(&self, id: BlockId) -> &BlockType {
+        &self.blocks[id.0 as usize]
+    }
+
+    pub fn id(&self, name: &str) -> Option<BlockId> {
+        self.names.get(name).copied()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (BlockId, &BlockType)> {
+        self.blocks.iter().enumerate().map(|(index, block)| (BlockId(index as u16), block))
+    }
+}
+
+impl Default for BlockRegistry {
+    fn default() -> Self {
+        Self::with_defaults()
+    }
+}
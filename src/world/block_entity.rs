@@ -0,0 +1,269 @@
+use std::io;
+
+use crate::{encoding::{invalid_data, Reader}, item::{decode_inventory, encode_inventory, Inventory, ItemId, ItemRegistry, ItemStack}};
+
+pub const CHEST_SIZE: usize = 27;
+
+pub const FURNACE_INPUT: usize = 0;
+pub const FURNACE_FUEL: usize = 1;
+pub const FURNACE_OUTPUT: usize = 2;
+/// Ticks it takes to smelt one item.
+pub const SMELT_TIME: u32 = 200;
+
+/// Items a furnace can smelt, by item name.
+const SMELTING: [(&str, &str); 1] = [("sand", "glass")];
+/// Ticks a furnace burns on one item of fuel, by item name.
+const FUEL: [(&str, u32); 3] = [("log", 300), ("stick", 100), ("leaves", 50)];
+
+const CHEST: u8 = 0;
+const SIGN: u8 = 1;
+const FURNACE: u8 = 2;
+
+/// State of a single voxel that does not fit in its block id and block state.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum BlockEntity {
+    Chest(Inventory),
+    Sign(String),
+    Furnace(Furnace),
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Furnace {
+    /// Input, fuel and output slots.
+    pub inventory: Inventory,
+    /// Ticks spent smelting the current input item.
+    pub progress: u32,
+    /// Ticks left on the burning fuel.
+    pub burn_time: u32,
+}
+
+pub fn chest() -> BlockEntity {
+    BlockEntity::Chest(Inventory::new(CHEST_SIZE))
+}
+
+pub fn sign() -> BlockEntity {
+    BlockEntity::Sign(String::new())
+}
+
+pub fn furnace() -> BlockEntity {
+    BlockEntity::Furnace(Furnace { inventory: Inventory::new(3), progress: 0, burn_time: 0 })
+}
+
+impl BlockEntity {
+    pub fn inventory(&self) -> Option<&Inventory> {
+        match self {
+            BlockEntity::Chest(inventory) => Some(inventory),
+            BlockEntity::Furnace(furnace) => Some(&furnace.inventory),
+            BlockEntity::Sign(_) => None,
+        }
+    }
+
+    pub fn inventory_mut(&mut self) -> Option<&mut Inventory> {
+        match self {
+            BlockEntity::Chest(inventory) => Some(inventory),
+            BlockEntity::Furnace(furnace) => Some(&mut furnace.inventory),
+            BlockEntity::Sign(_) => None,
+        }
+    }
+
+    /// Whether the block entity does anything when ticked.
+    pub fn needs_tick(&self) -> bool {
+        matches!(self, BlockEntity::Furnace(_))
+    }
+
+    pub fn tick(&mut self, registry: &ItemRegistry) {
+        if let BlockEntity::Furnace(furnace) = self {
+            furnace.tick(registry);
+        }
+    }
+
+    pub fn encode(&self, bytes: &mut Vec<u8>, registry: &ItemRegistry) {
+        match self {
+            BlockEntity::Chest(inventory) => {
+                bytes.push(CHEST);
+                encode_length_prefixed(bytes, &encode_inventory(inventory, registry));
+            },
+            BlockEntity::Sign(text) => {
+                bytes.push(SIGN);
+                encode_length_prefixed(bytes, text.as_bytes());
+            },
+            BlockEntity::Furnace(furnace) => {
+                bytes.push(FURNACE);
+                encode_length_prefixed(bytes, &encode_inventory(&furnace.inventory, registry));
+                bytes.extend(furnace.progress.to_le_bytes());
+                bytes.extend(furnace.burn_time.to_le_bytes());
+            },
+        }
+    }
+
+    pub(crate) fn decode(reader: &mut Reader, registry: &ItemRegistry) -> io::Result<Self> {
+        match reader.u8()? {
+            CHEST => Ok(BlockEntity::Chest(decode_length_prefixed_inventory(reader, registry)?)),
+            SIGN => {
+                let length = reader.u32()? as usize;
+                let text = String::from_utf8(reader.bytes(length)?.to_vec())
+                    .map_err(|_| invalid_data("Sign text is not valid UTF-8".to_owned()))?;
+                Ok(BlockEntity::Sign(text))
+            },
+            FURNACE => {
+                let inventory = decode_length_prefixed_inventory(reader, registry)?;
+                if inventory.size() != 3 {
+                    return Err(invalid_data(format!("Furnace with {} slots", inventory.size())));
+                }
+                Ok(BlockEntity::Furnace(Furnace { inventory, progress: reader.u32()?, burn_time: reader.u32()? }))
+            },
+            kind => Err(invalid_data(format!("Unknown block entity kind {}", kind))),
+        }
+    }
+}
+
+impl Furnace {
+    /// Burns fuel while there is something to smelt, and moves smelted items to the output.
+    fn tick(&mut self, registry: &ItemRegistry) {
+        let result = self.inventory.get(FURNACE_INPUT).and_then(|input| smelting_result(input.item, registry));
+        let output_fits = result.is_some_and(|result| match self.inventory.get(FURNACE_OUTPUT) {
+            None => true,
+            Some(output) => output.can_merge(&result) && output.count < registry.get(output.item).max_stack,
+        });
+
+        if self.burn_time == 0 && output_fits {
+            let fuel = self.inventory.get(FURNACE_FUEL).and_then(|fuel| fuel_time(fuel.item, registry));
+            if let Some(fuel) = fuel {
+                self.inventory.take(FURNACE_FUEL, 1);
+                self.burn_time = fuel;
+            }
+        }
+        if self.burn_time == 0 {
+            self.progress = 0;
+            return;
+        }
+        self.burn_time -= 1;
+
+        if !output_fits {
+            self.progress = 0;
+            return;
+        }
+        self.progress += 1;
+        if self.progress >= SMELT_TIME {
+            self.progress = 0;
+            self.inventory.take(FURNACE_INPUT, 1);
+            self.inventory.insert_into(FURNACE_OUTPUT, result.unwrap(), registry);
+        }
+    }
+}
+
+fn smelting_result(item: ItemId, registry: &ItemRegistry) -> Option<ItemStack> {
+    let name = &registry.get(item).name;
+    let (_, result) = SMELTING.iter().find(|(input, _)| input == name)?;
+    Some(ItemStack::new(registry.id(result)?, 1))
+}
+
+fn fuel_time(item: ItemId, registry: &ItemRegistry) -> Option<u32> {
+    let name = &registry.get(item).name;
+    FUEL.iter().find(|(fuel, _)| fuel == name).map(|(_, time)| *time)
+}
+
+fn encode_length_prefixed(bytes: &mut Vec<u8>, data: &[u8]) {
+    bytes.extend((data.len() as u32).to_le_bytes());
+    bytes.extend(data);
+}
+
+fn decode_length_prefixed_inventory(reader: &mut Reader, registry: &ItemRegistry) -> io::Result<Inventory> {
+    let length = reader.u32()? as usize;
+    decode_inventory(reader.bytes(length)?, registry)
+}
+
+#[cfg(test)]
+mod tests {
+    use nalgebra::Vector3;
+
+    use crate::world::{block::BlockRegistry, chunk::{Chunk, CHUNK_VOLUME}, storage::{decode_chunk, encode_chunk}, voxel::Voxel, Spawn, World};
+
+    use super::*;
+
+    fn world_with(block: &str, position: Vector3<i32>) -> World {
+        let registry = BlockRegistry::with_defaults();
+        let items = ItemRegistry::with_defaults(&registry);
+        let mut world = World::new(registry, items, 0);
+        world.insert_chunk(Vector3::zeros(), Chunk::new(vec![None; CHUNK_VOLUME]));
+        let block = world.registry().id(block).unwrap();
+        world.set_voxel(position, Some(Voxel::new(block)));
+        world
+    }
+
+    fn stack(world: &World, item: &str, count: u32) -> ItemStack {
+        ItemStack::new(world.items().id(item).unwrap(), count)
+    }
+
+    #[test]
+    fn breaking_the_block_removes_its_block_entity_and_drops_the_contents() {
+        let position = Vector3::new(1, 1, 1);
+        let mut world = world_with("chest", position);
+        let sticks = stack(&world, "stick", 5);
+        world.block_entity_mut(position).unwrap().inventory_mut().unwrap().set(3, Some(sticks));
+
+        world.break_voxel(position);
+        assert_eq!(world.block_entity(position), None);
+        let dropped: Vec<ItemStack> = world.take_spawns().into_iter().filter_map(|spawn| match spawn {
+            Spawn::DroppedItem { stack, .. } => Some(stack),
+            _ => None,
+        }).collect();
+        assert_eq!(dropped, vec![stack(&world, "chest", 1), sticks]);
+    }
+
+    #[test]
+    fn furnaces_smelt_while_they_have_fuel() {
+        let position = Vector3::new(1, 1, 1);
+        let mut world = world_with("furnace", position);
+        let (sand, log) = (stack(&world, "sand", 2), stack(&world, "log", 1));
+        let inventory = world.block_entity_mut(position).unwrap().inventory_mut().unwrap();
+        inventory.set(FURNACE_INPUT, Some(sand));
+        inventory.set(FURNACE_FUEL, Some(log));
+
+        for _ in 0..SMELT_TIME {
+            world.tick();
+        }
+        let Some(BlockEntity::Furnace(furnace)) = world.block_entity(position) else {
+            panic!("no furnace");
+        };
+        assert_eq!(furnace.inventory.get(FURNACE_OUTPUT), Some(&stack(&world, "glass", 1)));
+        assert_eq!(furnace.inventory.get(FURNACE_INPUT), Some(&stack(&world, "sand", 1)));
+        assert_eq!(furnace.inventory.get(FURNACE_FUEL), None);
+        assert_eq!(furnace.burn_time, 100);
+
+        // The log burns out halfway through the second item.
+        for _ in 0..SMELT_TIME {
+            world.tick();
+        }
+        let Some(BlockEntity::Furnace(furnace)) = world.block_entity(position) else {
+            panic!("no furnace");
+        };
+        assert_eq!(furnace.inventory.get(FURNACE_OUTPUT), Some(&stack(&world, "glass", 1)));
+        assert_eq!((furnace.burn_time, furnace.progress), (0, 0));
+    }
+
+    #[test]
+    fn block_entities_are_saved_with_the_chunk() {
+        let registry = BlockRegistry::with_defaults();
+        let items = ItemRegistry::with_defaults(&registry);
+        let mut chunk = Chunk::new(vec![None; CHUNK_VOLUME]);
+        for (x, name) in ["chest", "sign", "furnace"].into_iter().enumerate() {
+            chunk.set_voxel(Vector3::new(x as i32, 0, 0), Some(Voxel::new(registry.id(name).unwrap())), &registry);
+        }
+        let stick = ItemStack::new(items.id("stick").unwrap(), 3);
+        chunk.block_entity_mut(Vector3::new(0, 0, 0)).unwrap().inventory_mut().unwrap().set(4, Some(stick));
+        *chunk.block_entity_mut(Vector3::new(1, 0, 0)).unwrap() = BlockEntity::Sign("Hello there".to_owned());
+        if let Some(BlockEntity::Furnace(furnace)) = chunk.block_entity_mut(Vector3::new(2, 0, 0)) {
+            furnace.inventory.set(FURNACE_FUEL, Some(stick));
+            furnace.progress = 42;
+            furnace.burn_time = 7;
+        }
+
+        let decoded = decode_chunk(&encode_chunk(&chunk, 0, &registry, &items), 0, &registry, &items).unwrap();
+        let mut expected: Vec<_> = chunk.block_entities().collect();
+        let mut actual: Vec<_> = decoded.block_entities().collect();
+        expected.sort_by_key(|(position, _)| position.x);
+        actual.sort_by_key(|(position, _)| position.x);
+        assert_eq!(actual, expected);
+    }
+}
@@ -1,99 +1,119 @@
-use nalgebra::{Vector3, Vector2};
+use std::collections::HashMap;
 
-use crate::{rendering::{Mesh, primitives}, asset::Texture, math::Direction};
+use nalgebra::Vector3;
 
-use super::voxel::Voxel;
+use crate::math::Direction;
+
+use super::{block::BlockRegistry, block_entity::BlockEntity, light::{LightColor, LightMap}, tick::TickScheduler, voxel::Voxel};
 
 pub const CHUNK_SIZE_X: i32 = 8;
 pub const CHUNK_SIZE_Y: i32 = 8;
 pub const CHUNK_SIZE_Z: i32 = 8;
+pub const CHUNK_VOLUME: usize = (CHUNK_SIZE_X * CHUNK_SIZE_Y * CHUNK_SIZE_Z) as usize;
+
+pub fn chunk_index(coordinates: Vector3<i32>) -> Option<usize> {
+    if coordinates.x < 0 || coordinates.x >= CHUNK_SIZE_X ||
+        coordinates.y < 0 || coordinates.y >= CHUNK_SIZE_Y ||
+        coordinates.z < 0 || coordinates.z >= CHUNK_SIZE_Z {
+        None
+    } else {
+        Some((coordinates.x + CHUNK_SIZE_X * coordinates.y + (CHUNK_SIZE_X * CHUNK_SIZE_Y) * coordinates.z) as usize)
+    }
+}
+
+pub fn chunk_coordinates(index: usize) -> Vector3<i32> {
+    let i = index as i32;
+    Vector3::new(
+        i % CHUNK_SIZE_X,
+        (i % (CHUNK_SIZE_X*CHUNK_SIZE_Y)) / CHUNK_SIZE_X,
+        i / (CHUNK_SIZE_X*CHUNK_SIZE_Y),
+    )
+}
 
 pub struct Chunk {
     pub chunk_data: Vec<Option<Voxel>>,
+    light: LightMap,
+    scheduled_ticks: TickScheduler,
+    block_entities: HashMap<Vector3<i32>, BlockEntity>,
 }
 
 impl Chunk {
     pub fn new(data: Vec<Option<Voxel>>) -> Self {
-        Self { chunk_data: data }
+        Self { chunk_data: data, light: LightMap::new(), scheduled_ticks: TickScheduler::new(), block_entities: HashMap::new() }
     }
 
     pub fn get_voxel(&self, coordinates: Vector3<i32>) -> Option<&Option<Voxel>> {
-        if coordinates.x < 0 || coordinates.x >= CHUNK_SIZE_X ||
-            coordinates.y < 0 || coordinates.y >= CHUNK_SIZE_Y ||
-            coordinates.z < 0 || coordinates.z >= CHUNK_SIZE_Z {
-            None
-        } else {
-            let i = coordinates.x + CHUNK_SIZE_X * coordinates.y + (CHUNK_SIZE_X * CHUNK_SIZE_Y) * coordinates.z;
-            self.chunk_data.get(i as usize)
+        chunk_index(coordinates).and_then(|i| self.chunk_data.get(i))
+    }
+
+    /// Replaces the voxel at `coordinates` and relights the affected area. When the block
+    /// changes, its block entity is replaced by a new one for the new block.
+    /// Returns the previous voxel, or `None` when the coordinates lie outside the chunk.
+    pub fn set_voxel(&mut self, coordinates: Vector3<i32>, voxel: Option<Voxel>, registry: &BlockRegistry) -> Option<Option<Voxel>> {
+        let i = chunk_index(coordinates)?;
+        let previous = std::mem::replace(&mut self.chunk_data[i], voxel);
+        if previous.map(|voxel| voxel.block) != voxel.map(|voxel| voxel.block) {
+            self.block_entities.remove(&coordinates);
+            if let Some(create) = voxel.and_then(|voxel| registry.get(voxel.block).block_entity) {
+                self.block_entities.insert(coordinates, create());
+            }
         }
+        self.light.update(&self.chunk_data, registry, coordinates);
+        Some(previous)
     }
 
-    pub fn get_neighbour(&self, coordinates: Vector3<i32>, direction: &Direction) -> Option<&Option<Voxel>> {
-        let neighbour_coordinates: Vector3<i32> = coordinates + direction.facing();
-        self.get_voxel(neighbour_coordinates)
+    pub fn block_entity(&self, coordinates: Vector3<i32>) -> Option<&BlockEntity> {
+        self.block_entities.get(&coordinates)
+    }
+
+    pub fn block_entity_mut(&mut self, coordinates: Vector3<i32>) -> Option<&mut BlockEntity> {
+        self.block_entities.get_mut(&coordinates)
+    }
+
+    pub fn block_entities(&self) -> impl Iterator<Item = (&Vector3<i32>, &BlockEntity)> {
+        self.block_entities.iter()
+    }
+
+    pub fn block_entities_mut(&mut self) -> impl Iterator<Item = (&Vector3<i32>, &mut BlockEntity)> {
+        self.block_entities.iter_mut()
     }
 
-    pub fn generate_mesh(&self, texture: Texture) -> Mesh {
-        let cube_vertices: [Vector3<f32>; 8] = [
-            Vector3::new(1.0, 0.0, 1.0), // 0. Left bottom back
-        Vector3::new(0.0, 0.0, 1.0), // 1. Right bottom back
-        Vector3::new(1.0, 1.0, 1.0), // 2. Left top back
-        Vector3::new(0.0, 1.0, 1.0), // 3. Right top back
-        Vector3::new(1.0, 0.0, 0.0), // 4. Left bottom front
-        Vector3::new(0.0, 0.0, 0.0), // 5. Right bottom front
-        Vector3::new(1.0, 1.0, 0.0), // 6. Left top front
-        Vector3::new(0.0, 1.0, 0.0), // 7. Right top front
-        ];
-        let cube_indices: [(Direction, [usize; 4]); 6] = [
-            (Direction::Left, [0, 4, 2, 6]),
-            (Direction::Right, [5, 1, 7, 3]),
-            (Direction::Up, [6, 7, 2, 3]),
-            (Direction::Down, [0, 1, 4, 5]),
-            (Direction::Back, [1, 0, 3, 2]),
-            (Direction::Front, [4, 5, 6, 7]),
-        ];
-        let index_pattern: [u32; 6] = [0, 1, 2, 2, 1, 3];
-        let base_uvs: [Vector2<f32>; 4] = [
-            Vector2::new(0.0, 1.0), // Bottom left
-            Vector2::new(1.0, 1.0), // Bottom right
-            Vector2::new(0.0, 0.0), // Top left
-            Vector2::new(1.0, 0.0), // Top right
-        ];
-        let mut indices: Vec<u32> = Vec::new();
-        let mut vertices: Vec<Vector3<f32>> = Vec::new();
-        let mut uvs: Vec<Vector2<f32>> = Vec::new();
-        let mut face_i = 0;
-
-        for i in 0..(CHUNK_SIZE_X*CHUNK_SIZE_Y*CHUNK_SIZE_Z) {
-            if self.chunk_data[i as usize].is_none() {
+    pub fn insert_block_entity(&mut self, coordinates: Vector3<i32>, block_entity: BlockEntity) {
+        if chunk_index(coordinates).is_some() {
+            self.block_entities.insert(coordinates, block_entity);
+        }
+    }
+
+    /// Creates block entities for voxels whose block has one but that are missing it, such
+    /// as in freshly generated chunks.
+    pub fn create_block_entities(&mut self, registry: &BlockRegistry) {
+        for (i, voxel) in self.chunk_data.iter().enumerate() {
+            let Some(create) = voxel.and_then(|voxel| registry.get(voxel.block).block_entity) else {
                 continue;
-            }
-            let x = i % CHUNK_SIZE_X;
-            let y = (i % (CHUNK_SIZE_X*CHUNK_SIZE_Y)) / CHUNK_SIZE_X;
-            let z = i / (CHUNK_SIZE_X*CHUNK_SIZE_Y);
-            let displacement = Vector3::new(x as f32, y as f32, z as f32);
-
-            for (direction, vertex_pattern) in &cube_indices {
-                let neighbour = self.get_neighbour(Vector3::new(x, y, z), direction);
-                if neighbour.is_some_and(|neighbour| neighbour.is_some()) {
-                    continue;
-                }
-                vertices.extend(vertex_pattern.map(|vertex_i| {
-                    cube_vertices[vertex_i] + displacement
-                }));
-                indices.extend(index_pattern.map(|index_i| index_i + 4*face_i));
-                uvs.extend(base_uvs);
-                face_i += 1;
-            }
+            };
+            self.block_entities.entry(chunk_coordinates(i)).or_insert_with(create);
         }
-        
-        Mesh::new(
-            indices.len() as i32,
-            &indices,
-            &vertices,
-            None, 
-            Some(&uvs), 
-            Some(texture),
-        )
+    }
+
+    pub fn get_light(&self, coordinates: Vector3<i32>) -> Option<LightColor> {
+        self.light.get(coordinates)
+    }
+
+    pub fn calculate_light(&mut self, registry: &BlockRegistry) {
+        self.light.calculate(&self.chunk_data, registry);
+    }
+
+    /// Ticks scheduled for voxels in this chunk, by world tick and local position.
+    pub fn scheduled_ticks(&self) -> &TickScheduler {
+        &self.scheduled_ticks
+    }
+
+    pub fn scheduled_ticks_mut(&mut self) -> &mut TickScheduler {
+        &mut self.scheduled_ticks
+    }
+
+    pub fn get_neighbour(&self, coordinates: Vector3<i32>, direction: &Direction) -> Option<&Option<Voxel>> {
+        let neighbour_coordinates: Vector3<i32> = coordinates + direction.facing();
+        self.get_voxel(neighbour_coordinates)
     }
 }
@@ -0,0 +1,137 @@
+use nalgebra::Vector3;
+
+use crate::{math::Direction, physics::{is_solid, PhysicsBody}};
+
+use super::{map::{Spawn, World}, voxel::Voxel};
+
+/// Ticks between a gravity block losing its support and it starting to fall.
+const FALL_DELAY: u64 = 2;
+
+/// Component of a gravity affected block that left the voxel grid and is falling.
+pub struct FallingBlock {
+    pub voxel: Voxel,
+}
+
+/// The body of a block falling from the voxel at `position`.
+pub fn falling_body(position: Vector3<i32>) -> PhysicsBody {
+    let bottom_centre = position.cast::<f32>() + Vector3::new(0.5, 0.0, 0.5);
+    // Slightly narrower than a voxel, so it slides down shafts of exactly one block wide.
+    PhysicsBody::new(bottom_centre, Vector3::new(0.98, 0.98, 0.98))
+}
+
+/// The voxel a falling block occupies, based on the centre of its body.
+pub fn voxel_position(body: &PhysicsBody) -> Vector3<i32> {
+    (body.position + Vector3::y() * 0.5).map(|c| c.floor() as i32)
+}
+
+/// Neighbour handler for gravity blocks: schedules the fall when the voxel below stops
+/// supporting it.
+pub fn check_support(world: &mut World, position: Vector3<i32>, _changed: Vector3<i32>) {
+    if !is_supported(world, position) {
+        world.schedule_tick(position, FALL_DELAY);
+    }
+}
+
+/// Scheduled tick for gravity blocks: turns the voxel into a falling block entity if it
+/// is still unsupported.
+pub fn start_falling(world: &mut World, position: Vector3<i32>) {
+    if is_supported(world, position) {
+        return;
+    }
+    let Some(Some(voxel)) = world.set_voxel(position, None) else {
+        return;
+    };
+    world.spawn(Spawn::FallingBlock { voxel, position });
+}
+
+/// Whether a landed block can be placed back at `position`. Otherwise the block has no
+/// room and drops as an item.
+pub fn can_land_in(world: &World, position: Vector3<i32>) -> bool {
+    world.is_replaceable(position)
+}
+
+fn is_supported(world: &World, position: Vector3<i32>) -> bool {
+    is_solid(world, position + Direction::Down.facing())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{ecs::{components::DroppedItem, systems, Entities, SystemContext}, item::ItemRegistry, simulation::TICK_DELTA, world::{block::BlockRegistry, chunk::{Chunk, CHUNK_VOLUME}}};
+
+    use super::*;
+
+    const SAND: Vector3<i32> = Vector3::new(2, 4, 2);
+    const SUPPORT: Vector3<i32> = Vector3::new(2, 3, 2);
+    const LANDING: Vector3<i32> = Vector3::new(2, 1, 2);
+
+    /// A one chunk world with a cobblestone floor at `y = 0` and sand standing on a
+    /// cobblestone pillar above it.
+    fn sand_world() -> World {
+        let registry = BlockRegistry::with_defaults();
+        let items = ItemRegistry::with_defaults(&registry);
+        let mut world = World::new(registry, items, 0);
+        world.insert_chunk(Vector3::zeros(), Chunk::new(vec![None; CHUNK_VOLUME]));
+        let cobblestone = Some(Voxel::new(world.registry().id("cobblestone").unwrap()));
+        for x in 0..4 {
+            for z in 0..4 {
+                world.set_voxel(Vector3::new(x, 0, z), cobblestone);
+            }
+        }
+        for y in 1..=3 {
+            world.set_voxel(Vector3::new(2, y, 2), cobblestone);
+        }
+        world.set_voxel(SAND, Some(Voxel::new(world.registry().id("sand").unwrap())));
+        world
+    }
+
+    /// Runs the world and the entity systems like a simulation does, until no block is
+    /// falling anymore.
+    fn run_until_landed(world: &mut World, entities: &mut Entities) {
+        let schedule = systems::default_schedule();
+        let mut fell = false;
+        for _ in 0..200 {
+            world.tick();
+            schedule.run(&mut SystemContext { entities, world, delta: TICK_DELTA });
+            let falling = entities.iter::<FallingBlock>().count();
+            fell |= falling > 0;
+            if fell && falling == 0 {
+                return;
+            }
+        }
+        panic!("the sand never landed");
+    }
+
+    #[test]
+    fn unsupported_sand_falls_and_lands_as_a_voxel() {
+        let mut world = sand_world();
+        let mut entities = Entities::new();
+        let sand = world.get_voxel(SAND).copied().flatten();
+        for y in 1..=3 {
+            world.set_voxel(Vector3::new(2, y, 2), None);
+        }
+        assert_eq!(world.get_voxel(SAND), Some(&sand));
+
+        run_until_landed(&mut world, &mut entities);
+        assert_eq!(world.get_voxel(SAND), Some(&None));
+        assert_eq!(world.get_voxel(LANDING), Some(&sand));
+        assert!(entities.iter::<DroppedItem>().next().is_none());
+    }
+
+    #[test]
+    fn sand_landing_on_a_taken_voxel_drops_as_an_item() {
+        let mut world = sand_world();
+        let mut entities = Entities::new();
+        let torch = Some(Voxel::new(world.registry().id("torch").unwrap()));
+        world.set_voxel(LANDING, torch);
+        world.set_voxel(Vector3::new(2, 2, 2), None);
+        world.set_voxel(SUPPORT, None);
+
+        run_until_landed(&mut world, &mut entities);
+        assert_eq!(world.get_voxel(SAND), Some(&None));
+        assert_eq!(world.get_voxel(LANDING), Some(&torch));
+        let sand_item = world.items().id("sand").unwrap();
+        let dropped: Vec<_> = entities.iter::<DroppedItem>().map(|(_, dropped)| dropped.stack).collect();
+        assert_eq!(dropped.len(), 1);
+        assert_eq!((dropped[0].item, dropped[0].count), (sand_item, 1));
+    }
+}
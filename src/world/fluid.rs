@@ -0,0 +1,323 @@
+use nalgebra::Vector3;
+
+use crate::math::Direction;
+
+use super::{block::BlockId, map::World, voxel::Voxel};
+
+/// Level of a source block. Flowing fluid counts up from here, one level per voxel.
+pub const SOURCE_LEVEL: u8 = 0;
+pub const MAX_LEVEL: u8 = 7;
+
+const LEVEL_MASK: u8 = 0b0111;
+/// Set for fluid that is fed from directly above.
+const FALLING: u8 = 0b1000;
+
+const HORIZONTAL: [Direction; 4] = [Direction::Left, Direction::Right, Direction::Back, Direction::Front];
+
+pub struct FluidProperties {
+    /// Highest level the fluid spreads to, at most `MAX_LEVEL`.
+    pub flow_distance: u8,
+    /// Ticks between a change next to the fluid and the fluid reacting to it.
+    pub tick_delay: u64,
+    /// Whether flowing fluid between two sources turns into a source itself.
+    pub renews: bool,
+}
+
+pub fn source(block: BlockId) -> Voxel {
+    Voxel::with_state(block, SOURCE_LEVEL)
+}
+
+pub fn flowing(block: BlockId, level: u8) -> Voxel {
+    Voxel::with_state(block, level.min(MAX_LEVEL))
+}
+
+pub fn falling(block: BlockId) -> Voxel {
+    Voxel::with_state(block, FALLING)
+}
+
+pub fn level(voxel: &Voxel) -> u8 {
+    voxel.state & LEVEL_MASK
+}
+
+pub fn is_falling(voxel: &Voxel) -> bool {
+    voxel.state & FALLING != 0
+}
+
+pub fn is_source(voxel: &Voxel) -> bool {
+    voxel.state == SOURCE_LEVEL
+}
+
+/// Height of the fluid surface inside its voxel, between 0 and 1.
+pub fn surface_height(voxel: &Voxel) -> f32 {
+    if is_falling(voxel) {
+        1.0
+    } else {
+        (MAX_LEVEL + 1 - level(voxel)) as f32 / (MAX_LEVEL + 2) as f32
+    }
+}
+
+/// Neighbour handler for fluids: reacts to the change after the fluid's tick delay.
+pub fn neighbour_changed(world: &mut World, position: Vector3<i32>, _changed: Vector3<i32>) {
+    let Some(delay) = world.block_type(position).and_then(|block| block.fluid.as_ref()).map(|fluid| fluid.tick_delay) else {
+        return;
+    };
+    world.schedule_tick(position, delay);
+}
+
+/// Runs a scheduled update for the fluid at `position`: recalculates its level from the
+/// surrounding fluid and lets it flow into neighbouring air.
+pub fn update(world: &mut World, position: Vector3<i32>) {
+    let Some(&Some(voxel)) = world.get_voxel(position) else {
+        return;
+    };
+    let Some(fluid) = &world.registry().get(voxel.block).fluid else {
+        return;
+    };
+    let (flow_distance, renews) = (fluid.flow_distance, fluid.renews);
+
+    let mut voxel = voxel;
+    if !is_source(&voxel) {
+        match fed_state(world, position, voxel.block, flow_distance, renews) {
+            None => {
+                world.set_voxel(position, None);
+                return;
+            },
+            Some(state) if state != voxel.state => {
+                voxel.state = state;
+                world.set_voxel(position, Some(voxel));
+            },
+            _ => {},
+        }
+    }
+    spread(world, position, &voxel, flow_distance);
+}
+
+/// The state a non-source fluid voxel should have given its neighbours, or `None` when
+/// nothing feeds it anymore.
+fn fed_state(world: &World, position: Vector3<i32>, block: BlockId, flow_distance: u8, renews: bool) -> Option<u8> {
+    if same_fluid(world, position + Direction::Up.facing(), block).is_some() {
+        return Some(FALLING);
+    }
+
+    let mut sources = 0;
+    let mut lowest_level = None;
+    for direction in HORIZONTAL {
+        let Some(neighbour) = same_fluid(world, position + direction.facing(), block) else {
+            continue;
+        };
+        if is_source(&neighbour) {
+            sources += 1;
+        }
+        // Falling fluid spreads out as if it were a source.
+        let neighbour_level = if is_falling(&neighbour) { SOURCE_LEVEL } else { level(&neighbour) };
+        lowest_level = Some(lowest_level.map_or(neighbour_level, |lowest: u8| lowest.min(neighbour_level)));
+    }
+
+    if renews && sources >= 2 {
+        let below = position + Direction::Down.facing();
+        let supported = same_fluid(world, below, block).is_some_and(|below| is_source(&below))
+            || world.get_voxel(below).is_some_and(|below| below.is_some_and(|below| world.registry().get(below.block).opaque));
+        if supported {
+            return Some(SOURCE_LEVEL);
+        }
+    }
+
+    let level = lowest_level? + 1;
+    (level <= flow_distance).then_some(level)
+}
+
+fn spread(world: &mut World, position: Vector3<i32>, voxel: &Voxel, flow_distance: u8) {
+    let below = position + Direction::Down.facing();
+    if is_air(world, below) {
+        world.set_voxel(below, Some(falling(voxel.block)));
+        return;
+    }
+    // Fluid that is flowing down only spreads sideways from the bottom of the fall,
+    // sources keep spreading on top of other fluid.
+    if same_fluid(world, below, voxel.block).is_some_and(|below| !is_source(&below)) {
+        return;
+    }
+
+    let next_level = if is_falling(voxel) { SOURCE_LEVEL + 1 } else { level(voxel) + 1 };
+    if next_level > flow_distance {
+        return;
+    }
+    for direction in HORIZONTAL {
+        let neighbour = position + direction.facing();
+        if is_air(world, neighbour) {
+            world.set_voxel(neighbour, Some(flowing(voxel.block, next_level)));
+        }
+    }
+}
+
+fn same_fluid(world: &World, position: Vector3<i32>, block: BlockId) -> Option<Voxel> {
+    world.get_voxel(position)
+        .and_then(|voxel| *voxel)
+        .filter(|voxel| voxel.block == block)
+}
+
+/// Air inside a loaded chunk. Fluid never flows into unloaded chunks.
+fn is_air(world: &World, position: Vector3<i32>) -> bool {
+    world.get_voxel(position).is_some_and(|voxel| voxel.is_none())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{item::ItemRegistry, world::{block::BlockRegistry, chunk::{Chunk, CHUNK_SIZE_X, CHUNK_SIZE_Z, CHUNK_VOLUME}, map::to_world_coordinates}};
+
+    use super::*;
+
+    /// Water spreads one level every `tick_delay` ticks, so this is enough for any flow to
+    /// settle.
+    const SETTLE_TICKS: u64 = 200;
+
+    /// A 3 by 3 chunk world with a cobblestone floor at `y = 0` and air above it.
+    fn floor_world() -> World {
+        let registry = BlockRegistry::with_defaults();
+        let items = ItemRegistry::with_defaults(&registry);
+        let floor = Some(Voxel::new(registry.id("cobblestone").unwrap()));
+        let mut world = World::new(registry, items, 0);
+        for chunk_x in -1..=1 {
+            for chunk_z in -1..=1 {
+                let position = Vector3::new(chunk_x, 0, chunk_z);
+                let mut chunk = Chunk::new(vec![None; CHUNK_VOLUME]);
+                for x in 0..CHUNK_SIZE_X {
+                    for z in 0..CHUNK_SIZE_Z {
+                        chunk.set_voxel(Vector3::new(x, 0, z), floor, world.registry());
+                    }
+                }
+                world.insert_chunk(position, chunk);
+            }
+        }
+        world
+    }
+
+    fn water(world: &World) -> BlockId {
+        world.registry().id("water").unwrap()
+    }
+
+    fn run(world: &mut World, ticks: u64) {
+        for _ in 0..ticks {
+            world.tick();
+        }
+    }
+
+    fn fluid_at(world: &World, position: Vector3<i32>) -> Option<Voxel> {
+        same_fluid(world, position, water(world))
+    }
+
+    fn fluid_count(world: &World) -> usize {
+        let water = water(world);
+        world.chunks()
+            .flat_map(|(_, chunk)| chunk.chunk_data.iter())
+            .filter(|voxel| voxel.is_some_and(|voxel| voxel.block == water))
+            .count()
+    }
+
+    #[test]
+    fn spreads_one_level_per_voxel_up_to_the_flow_distance() {
+        let mut world = floor_world();
+        let origin = Vector3::new(0, 1, 0);
+        world.set_voxel(origin, Some(source(water(&world))));
+        run(&mut world, SETTLE_TICKS);
+
+        assert!(is_source(&fluid_at(&world, origin).unwrap()));
+        for distance in 1..=7 {
+            let voxel = fluid_at(&world, origin + Vector3::new(distance, 0, 0)).unwrap();
+            assert_eq!(level(&voxel), distance as u8);
+        }
+        assert_eq!(level(&fluid_at(&world, origin + Vector3::new(-3, 0, 4)).unwrap()), 7);
+        assert_eq!(fluid_at(&world, origin + Vector3::new(8, 0, 0)), None);
+        assert_eq!(fluid_at(&world, origin + Vector3::new(4, 0, -4)), None);
+        // Every voxel within the flow distance, in a diamond around the source.
+        assert_eq!(fluid_count(&world), 1 + 2 * 7 * 8);
+    }
+
+    #[test]
+    fn falls_over_a_drop_and_spreads_at_the_bottom() {
+        let mut world = floor_world();
+        let cobblestone = Some(Voxel::new(world.registry().id("cobblestone").unwrap()));
+        for y in 1..=3 {
+            world.set_voxel(Vector3::new(0, y, 0), cobblestone);
+        }
+        world.set_voxel(Vector3::new(0, 4, 0), Some(source(water(&world))));
+        run(&mut world, SETTLE_TICKS);
+
+        let edge = fluid_at(&world, Vector3::new(1, 4, 0)).unwrap();
+        assert_eq!(level(&edge), 1);
+        assert!(!is_falling(&edge));
+        assert_eq!(fluid_at(&world, Vector3::new(2, 4, 0)), None);
+        for y in 1..=3 {
+            assert!(is_falling(&fluid_at(&world, Vector3::new(1, y, 0)).unwrap()));
+        }
+        // The bottom of the fall spreads like a source.
+        assert_eq!(level(&fluid_at(&world, Vector3::new(2, 1, 0)).unwrap()), 1);
+        assert_eq!(level(&fluid_at(&world, Vector3::new(8, 1, 0)).unwrap()), 7);
+    }
+
+    #[test]
+    fn dries_up_after_the_source_is_removed() {
+        let mut world = floor_world();
+        let origin = Vector3::new(0, 1, 0);
+        world.set_voxel(origin, Some(source(water(&world))));
+        run(&mut world, SETTLE_TICKS);
+        assert!(fluid_count(&world) > 1);
+
+        world.set_voxel(origin, None);
+        run(&mut world, SETTLE_TICKS);
+        assert_eq!(fluid_count(&world), 0);
+    }
+
+    #[test]
+    fn flows_across_chunk_borders() {
+        let mut world = floor_world();
+        let origin = to_world_coordinates(Vector3::zeros(), Vector3::new(CHUNK_SIZE_X - 1, 1, 0));
+        world.set_voxel(origin, Some(source(water(&world))));
+        run(&mut world, SETTLE_TICKS);
+
+        assert_eq!(level(&fluid_at(&world, origin + Vector3::new(1, 0, 0)).unwrap()), 1);
+        assert_eq!(level(&fluid_at(&world, origin + Vector3::new(7, 0, 0)).unwrap()), 7);
+    }
+
+    #[test]
+    fn surface_slopes_continue_across_chunk_borders() {
+        let mut world = floor_world();
+        let origin = to_world_coordinates(Vector3::zeros(), Vector3::new(CHUNK_SIZE_X - 2, 1, 2));
+        world.set_voxel(origin, Some(source(water(&world))));
+        run(&mut world, SETTLE_TICKS);
+
+        // The last voxel of one chunk and the first of the next share two corners.
+        let (inside, outside) = (origin + Vector3::new(1, 0, 0), origin + Vector3::new(2, 0, 0));
+        let inside_heights = world.fluid_corner_heights(inside, &fluid_at(&world, inside).unwrap());
+        let outside_heights = world.fluid_corner_heights(outside, &fluid_at(&world, outside).unwrap());
+        assert_eq!([inside_heights[1], inside_heights[3]], [outside_heights[0], outside_heights[2]]);
+        assert!(outside_heights[0] > outside_heights[1]);
+    }
+
+    #[test]
+    fn flowing_water_between_two_sources_becomes_a_source() {
+        let mut world = floor_world();
+        let (left, middle, right) = (Vector3::new(0, 1, 0), Vector3::new(1, 1, 0), Vector3::new(2, 1, 0));
+        world.set_voxel(left, Some(source(water(&world))));
+        world.set_voxel(right, Some(source(water(&world))));
+        run(&mut world, SETTLE_TICKS);
+        assert!(is_source(&fluid_at(&world, middle).unwrap()));
+
+        // The new source keeps the water from drying up once the others are gone.
+        world.set_voxel(left, None);
+        world.set_voxel(right, None);
+        run(&mut world, SETTLE_TICKS);
+        assert!(is_source(&fluid_at(&world, middle).unwrap()));
+        assert_eq!(level(&fluid_at(&world, left).unwrap()), 1);
+
+        // Lava does not renew.
+        let mut world = floor_world();
+        let lava = world.registry().id("lava").unwrap();
+        world.set_voxel(left, Some(source(lava)));
+        world.set_voxel(right, Some(source(lava)));
+        run(&mut world, SETTLE_TICKS);
+        let flowing = same_fluid(&world, middle, lava).unwrap();
+        assert!(!is_source(&flowing));
+        assert_eq!(level(&flowing), 1);
+    }
+}
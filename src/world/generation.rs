@@ -0,0 +1,28 @@
+use nalgebra::Vector3;
+
+use super::{chunk::{chunk_coordinates, Chunk, CHUNK_SIZE_X, CHUNK_SIZE_Y, CHUNK_SIZE_Z, CHUNK_VOLUME}, fluid, map::{to_world_coordinates, World}, voxel::Voxel};
+
+/// Blocks placed at the centre of generated chunks, picked by chunk position.
+const EMITTERS: [&str; 3] = ["torch", "lava", "lamp"];
+
+/// Fills a chunk with the flat test terrain: cobblestone up to half the chunk height,
+/// water puddles on every other chunk and a light emitter in the middle.
+pub fn generate_chunk(world: &mut World, position: Vector3<i32>) {
+    let cobblestone = world.registry().id("cobblestone").unwrap();
+    let water = world.registry().id("water").unwrap();
+    let voxels = (0..CHUNK_VOLUME).map(|i| {
+        let y = chunk_coordinates(i).y;
+        if y < CHUNK_SIZE_Y / 2 {
+            Some(Voxel::new(cobblestone))
+        } else if y == CHUNK_SIZE_Y / 2 && (position.x + position.z) % 2 == 0 {
+            Some(fluid::source(water))
+        } else {
+            None
+        }
+    }).collect();
+    world.insert_chunk(position, Chunk::new(voxels));
+
+    let emitter = world.registry().id(EMITTERS[(position.x + position.z).rem_euclid(EMITTERS.len() as i32) as usize]).unwrap();
+    let centre = to_world_coordinates(position, Vector3::new(CHUNK_SIZE_X / 2, CHUNK_SIZE_Y / 2, CHUNK_SIZE_Z / 2));
+    world.set_voxel(centre, Some(Voxel::new(emitter)));
+}
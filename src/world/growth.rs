@@ -0,0 +1,66 @@
+use nalgebra::Vector3;
+
+use crate::math::Direction;
+
+use super::{map::World, voxel::Voxel};
+
+/// Leaves further than this from a log decay.
+const LEAF_SUPPORT_RANGE: i32 = 4;
+pub const MAX_CROP_STAGE: u8 = 7;
+
+/// Random tick for grass: dies when covered, otherwise spreads onto nearby uncovered dirt.
+pub fn spread_grass(world: &mut World, position: Vector3<i32>) {
+    let (Some(dirt), Some(grass)) = (world.registry().id("dirt"), world.registry().id("grass")) else {
+        return;
+    };
+    if is_covered(world, position) {
+        world.set_voxel(position, Some(Voxel::new(dirt)));
+        return;
+    }
+
+    let offset = Vector3::new(
+        world.random().below(3) as i32 - 1,
+        world.random().below(5) as i32 - 3,
+        world.random().below(3) as i32 - 1,
+    );
+    let target = position + offset;
+    let target_is_dirt = world.get_voxel(target).is_some_and(|voxel| voxel.is_some_and(|voxel| voxel.block == dirt));
+    if target_is_dirt && !is_covered(world, target) {
+        world.set_voxel(target, Some(Voxel::new(grass)));
+    }
+}
+
+/// Random tick for leaves: removes them when no log is within `LEAF_SUPPORT_RANGE`.
+pub fn decay_leaves(world: &mut World, position: Vector3<i32>) {
+    let Some(log) = world.registry().id("log") else {
+        return;
+    };
+    let range = -LEAF_SUPPORT_RANGE..=LEAF_SUPPORT_RANGE;
+    for x in range.clone() {
+        for y in range.clone() {
+            for z in range.clone() {
+                let voxel = world.get_voxel(position + Vector3::new(x, y, z));
+                if voxel.is_some_and(|voxel| voxel.is_some_and(|voxel| voxel.block == log)) {
+                    return;
+                }
+            }
+        }
+    }
+    world.set_voxel(position, None);
+}
+
+/// Random tick for crops: advances the growth stage stored in the voxel state.
+pub fn grow_crop(world: &mut World, position: Vector3<i32>) {
+    let Some(&Some(mut voxel)) = world.get_voxel(position) else {
+        return;
+    };
+    if voxel.state < MAX_CROP_STAGE {
+        voxel.state += 1;
+        world.set_voxel(position, Some(voxel));
+    }
+}
+
+fn is_covered(world: &World, position: Vector3<i32>) -> bool {
+    world.get_voxel(position + Direction::Up.facing())
+        .is_some_and(|above| above.is_some_and(|above| world.registry().get(above.block).opaque))
+}
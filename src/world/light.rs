@@ -0,0 +1,224 @@
+use std::collections::VecDeque;
+
+use nalgebra::Vector3;
+
+use crate::math::Direction;
+
+use super::{block::BlockRegistry, chunk::{chunk_coordinates, chunk_index, CHUNK_VOLUME}, voxel::Voxel};
+
+pub const MAX_LIGHT: u8 = 15;
+
+/// Red, green and blue light levels, each in `0..=MAX_LIGHT`.
+pub type LightColor = [u8; 3];
+
+/// Block light of a single chunk. Every colour channel is flooded by its own
+/// breadth-first search, losing one level per voxel travelled.
+pub struct LightMap {
+    levels: Vec<LightColor>,
+}
+
+impl LightMap {
+    pub fn new() -> Self {
+        Self { levels: vec![[0; 3]; CHUNK_VOLUME] }
+    }
+
+    pub fn get(&self, coordinates: Vector3<i32>) -> Option<LightColor> {
+        chunk_index(coordinates).map(|i| self.levels[i])
+    }
+
+    /// Recalculates all light from scratch by flooding out of every emitter.
+    pub fn calculate(&mut self, voxels: &[Option<Voxel>], registry: &BlockRegistry) {
+        self.levels.fill([0; 3]);
+        for channel in 0..3 {
+            let mut queue = VecDeque::new();
+            for (i, voxel) in voxels.iter().enumerate() {
+                let emission = emission(voxel, registry, channel);
+                if emission > 0 {
+                    self.levels[i][channel] = emission;
+                    queue.push_back(i);
+                }
+            }
+            self.propagate(voxels, registry, channel, queue);
+        }
+    }
+
+    /// Updates the light after the voxel at `coordinates` has been replaced.
+    /// `voxels` must already contain the new voxel.
+    pub fn update(&mut self, voxels: &[Option<Voxel>], registry: &BlockRegistry, coordinates: Vector3<i32>) {
+        let Some(index) = chunk_index(coordinates) else {
+            return;
+        };
+        for channel in 0..3 {
+            let mut queue = self.remove(voxels, registry, channel, index);
+
+            let emission = emission(&voxels[index], registry, channel);
+            if emission > self.levels[index][channel] {
+                self.levels[index][channel] = emission;
+                queue.push_back(index);
+            }
+            if !is_opaque(&voxels[index], registry) {
+                // Let the surrounding light flow back into the changed voxel.
+                for direction in Direction::ALL {
+                    if let Some(neighbour) = chunk_index(coordinates + direction.facing()) {
+                        if self.levels[neighbour][channel] > 0 {
+                            queue.push_back(neighbour);
+                        }
+                    }
+                }
+            }
+            self.propagate(voxels, registry, channel, queue);
+        }
+    }
+
+    /// Darkens every voxel that was lit through `index` and returns the
+    /// voxels that border the darkened region and still need to spread light.
+    fn remove(&mut self, voxels: &[Option<Voxel>], registry: &BlockRegistry, channel: usize, index: usize) -> VecDeque<usize> {
+        let mut relight = VecDeque::new();
+        let level = self.levels[index][channel];
+        if level == 0 {
+            return relight;
+        }
+        self.levels[index][channel] = 0;
+
+        let mut queue = VecDeque::from([(index, level)]);
+        let mut emitters = Vec::new();
+        while let Some((i, level)) = queue.pop_front() {
+            for direction in Direction::ALL {
+                let Some(neighbour) = chunk_index(chunk_coordinates(i) + direction.facing()) else {
+                    continue;
+                };
+                let neighbour_level = self.levels[neighbour][channel];
+                if neighbour_level != 0 && neighbour_level < level {
+                    self.levels[neighbour][channel] = 0;
+                    queue.push_back((neighbour, neighbour_level));
+                    if emission(&voxels[neighbour], registry, channel) > 0 {
+                        emitters.push(neighbour);
+                    }
+                } else if neighbour_level >= level {
+                    relight.push_back(neighbour);
+                }
+            }
+        }
+
+        for i in emitters {
+            self.levels[i][channel] = emission(&voxels[i], registry, channel);
+            relight.push_back(i);
+        }
+        relight
+    }
+
+    fn propagate(&mut self, voxels: &[Option<Voxel>], registry: &BlockRegistry, channel: usize, mut queue: VecDeque<usize>) {
+        while let Some(i) = queue.pop_front() {
+            let level = self.levels[i][channel];
+            if level <= 1 {
+                continue;
+            }
+            for direction in Direction::ALL {
+                let Some(neighbour) = chunk_index(chunk_coordinates(i) + direction.facing()) else {
+                    continue;
+                };
+                if is_opaque(&voxels[neighbour], registry) {
+                    continue;
+                }
+                if self.levels[neighbour][channel] + 1 < level {
+                    self.levels[neighbour][channel] = level - 1;
+                    queue.push_back(neighbour);
+                }
+            }
+        }
+    }
+}
+
+impl Default for LightMap {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn emission(voxel: &Option<Voxel>, registry: &BlockRegistry, channel: usize) -> u8 {
+    voxel.map_or(0, |voxel| registry.get(voxel.block).emission[channel])
+}
+
+fn is_opaque(voxel: &Option<Voxel>, registry: &BlockRegistry) -> bool {
+    voxel.is_some_and(|voxel| registry.get(voxel.block).opaque)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::world::{block::BlockRegistry, chunk::{Chunk, CHUNK_SIZE_X, CHUNK_SIZE_Y, CHUNK_SIZE_Z, CHUNK_VOLUME}};
+
+    use super::*;
+
+    const TORCH: [u8; 3] = [14, 11, 7];
+
+    fn torch(registry: &BlockRegistry) -> Option<Voxel> {
+        Some(Voxel::new(registry.id("torch").unwrap()))
+    }
+
+    fn empty_chunk() -> Chunk {
+        Chunk::new(vec![None; CHUNK_VOLUME])
+    }
+
+    /// The torch's light, one level less per voxel travelled in every channel.
+    fn expected(distance: i32) -> LightColor {
+        TORCH.map(|level| (level as i32 - distance).max(0) as u8)
+    }
+
+    #[test]
+    fn light_falls_off_with_distance_per_channel() {
+        let registry = BlockRegistry::with_defaults();
+        let mut chunk = empty_chunk();
+        chunk.set_voxel(Vector3::zeros(), torch(&registry), &registry);
+
+        assert_eq!(chunk.get_light(Vector3::zeros()), Some(TORCH));
+        assert_eq!(chunk.get_light(Vector3::new(1, 0, 0)), Some(expected(1)));
+        assert_eq!(chunk.get_light(Vector3::new(3, 0, 0)), Some([11, 8, 4]));
+        assert_eq!(chunk.get_light(Vector3::new(2, 2, 2)), Some(expected(6)));
+        assert_eq!(chunk.get_light(Vector3::new(7, 7, 7)), Some([0, 0, 0]));
+    }
+
+    #[test]
+    fn update_matches_calculating_from_scratch() {
+        let registry = BlockRegistry::with_defaults();
+        let mut chunk = empty_chunk();
+        chunk.set_voxel(Vector3::new(2, 3, 4), torch(&registry), &registry);
+        chunk.set_voxel(Vector3::new(6, 1, 1), Some(Voxel::new(registry.id("lava").unwrap())), &registry);
+        let updated: Vec<_> = (0..CHUNK_VOLUME).map(|i| chunk.get_light(chunk_coordinates(i))).collect();
+
+        chunk.calculate_light(&registry);
+        let calculated: Vec<_> = (0..CHUNK_VOLUME).map(|i| chunk.get_light(chunk_coordinates(i))).collect();
+        assert_eq!(updated, calculated);
+    }
+
+    #[test]
+    fn breaking_the_emitter_removes_its_light() {
+        let registry = BlockRegistry::with_defaults();
+        let mut chunk = empty_chunk();
+        chunk.set_voxel(Vector3::new(4, 4, 4), torch(&registry), &registry);
+        chunk.set_voxel(Vector3::new(4, 4, 4), None, &registry);
+
+        for i in 0..CHUNK_VOLUME {
+            assert_eq!(chunk.get_light(chunk_coordinates(i)), Some([0, 0, 0]));
+        }
+    }
+
+    #[test]
+    fn opaque_blocks_stop_light() {
+        let registry = BlockRegistry::with_defaults();
+        let wall = Some(Voxel::new(registry.id("cobblestone").unwrap()));
+        let mut chunk = empty_chunk();
+        chunk.set_voxel(Vector3::zeros(), torch(&registry), &registry);
+        assert_eq!(chunk.get_light(Vector3::new(3, 0, 0)), Some([11, 8, 4]));
+
+        // A wall across the whole chunk leaves no way around it.
+        for y in 0..CHUNK_SIZE_Y {
+            for z in 0..CHUNK_SIZE_Z {
+                chunk.set_voxel(Vector3::new(2, y, z), wall, &registry);
+            }
+        }
+        assert_eq!(chunk.get_light(Vector3::new(1, 0, 0)), Some(expected(1)));
+        for x in 2..CHUNK_SIZE_X {
+            assert_eq!(chunk.get_light(Vector3::new(x, 0, 0)), Some([0, 0, 0]));
+        }
+    }
+}
@@ -0,0 +1,419 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+
+use nalgebra::Vector3;
+
+use crate::{item::{Inventory, ItemKind, ItemRegistry, ItemStack}, math::{Direction, Random}};
+
+use super::{block::{BlockId, BlockRegistry, BlockType}, block_entity::BlockEntity, fluid, chunk::{chunk_coordinates, Chunk, CHUNK_SIZE_X, CHUNK_SIZE_Y, CHUNK_SIZE_Z, CHUNK_VOLUME}, voxel::Voxel};
+
+/// Number of voxels per loaded chunk that receive a random tick every world tick.
+pub const RANDOM_TICKS_PER_CHUNK: u32 = 3;
+/// How many voxel changes can be caused by each other through neighbour updates before
+/// further updates are dropped.
+pub const MAX_UPDATE_DEPTH: u32 = 64;
+
+struct NeighbourUpdate {
+    notified: Vector3<i32>,
+    changed: Vector3<i32>,
+    depth: u32,
+}
+
+/// An entity requested by the world, such as a block that starts falling. The world has
+/// no access to entities, so requests are queued until the entity systems collect them.
+#[derive(Clone, Copy, Debug)]
+pub enum Spawn {
+    FallingBlock { voxel: Voxel, position: Vector3<i32> },
+    DroppedItem { stack: ItemStack, position: Vector3<f32> },
+}
+
+/// Splits a world position into the position of its chunk and the position inside that chunk.
+pub fn to_chunk_coordinates(position: Vector3<i32>) -> (Vector3<i32>, Vector3<i32>) {
+    let size = Vector3::new(CHUNK_SIZE_X, CHUNK_SIZE_Y, CHUNK_SIZE_Z);
+    (
+        position.zip_map(&size, |p, s| p.div_euclid(s)),
+        position.zip_map(&size, |p, s| p.rem_euclid(s)),
+    )
+}
+
+pub fn to_world_coordinates(chunk_position: Vector3<i32>, local: Vector3<i32>) -> Vector3<i32> {
+    chunk_position.component_mul(&Vector3::new(CHUNK_SIZE_X, CHUNK_SIZE_Y, CHUNK_SIZE_Z)) + local
+}
+
+/// Offsets from -1 to 1 along every axis, the voxel or chunk itself included.
+fn neighbourhood() -> impl Iterator<Item = Vector3<i32>> {
+    (-1..=1).flat_map(|y| (-1..=1).flat_map(move |z| (-1..=1).map(move |x| Vector3::new(x, y, z))))
+}
+
+/// All loaded chunks together with the simulation state that spans them.
+pub struct World {
+    registry: BlockRegistry,
+    items: ItemRegistry,
+    chunks: HashMap<Vector3<i32>, Chunk>,
+    dirty_chunks: HashSet<Vector3<i32>>,
+    changed_voxels: HashSet<Vector3<i32>>,
+    spawns: Vec<Spawn>,
+    neighbour_updates: VecDeque<NeighbourUpdate>,
+    update_depth: u32,
+    random: Random,
+    ticks: u64,
+    spawn_point: Vector3<f32>,
+}
+
+impl World {
+    pub fn new(registry: BlockRegistry, items: ItemRegistry, seed: u64) -> Self {
+        Self {
+            registry,
+            items,
+            chunks: HashMap::new(),
+            dirty_chunks: HashSet::new(),
+            changed_voxels: HashSet::new(),
+            spawns: Vec::new(),
+            neighbour_updates: VecDeque::new(),
+            update_depth: 0,
+            random: Random::new(seed),
+            ticks: 0,
+            spawn_point: Vector3::zeros(),
+        }
+    }
+
+    pub fn registry(&self) -> &BlockRegistry {
+        &self.registry
+    }
+
+    pub fn items(&self) -> &ItemRegistry {
+        &self.items
+    }
+
+    pub fn ticks(&self) -> u64 {
+        self.ticks
+    }
+
+    /// Where players appear when they join or respawn.
+    pub fn spawn_point(&self) -> Vector3<f32> {
+        self.spawn_point
+    }
+
+    pub fn set_spawn_point(&mut self, spawn_point: Vector3<f32>) {
+        self.spawn_point = spawn_point;
+    }
+
+    pub fn random(&mut self) -> &mut Random {
+        &mut self.random
+    }
+
+    pub fn insert_chunk(&mut self, position: Vector3<i32>, mut chunk: Chunk) {
+        chunk.calculate_light(&self.registry);
+        chunk.create_block_entities(&self.registry);
+        self.chunks.insert(position, chunk);
+        self.mark_chunk_dirty(position);
+    }
+
+    /// Unloads a chunk. It counts as dirty afterwards, so its mesh is dropped.
+    pub fn remove_chunk(&mut self, position: Vector3<i32>) -> Option<Chunk> {
+        self.changed_voxels.retain(|voxel| to_chunk_coordinates(*voxel).0 != position);
+        let chunk = self.chunks.remove(&position);
+        self.mark_chunk_dirty(position);
+        chunk
+    }
+
+    /// Marks a chunk and its loaded neighbours dirty, as their meshes look across the
+    /// borders between them.
+    fn mark_chunk_dirty(&mut self, position: Vector3<i32>) {
+        self.dirty_chunks.insert(position);
+        for offset in neighbourhood() {
+            if self.chunks.contains_key(&(position + offset)) {
+                self.dirty_chunks.insert(position + offset);
+            }
+        }
+    }
+
+    pub fn chunk(&self, position: Vector3<i32>) -> Option<&Chunk> {
+        self.chunks.get(&position)
+    }
+
+    pub fn chunks(&self) -> impl Iterator<Item = (&Vector3<i32>, &Chunk)> {
+        self.chunks.iter()
+    }
+
+    /// Positions of all loaded chunks in a fixed order, so simulation does not depend on
+    /// the iteration order of the chunk map.
+    pub fn chunk_positions(&self) -> Vec<Vector3<i32>> {
+        let mut positions: Vec<Vector3<i32>> = self.chunks.keys().copied().collect();
+        positions.sort_by_key(|position| (position.x, position.y, position.z));
+        positions
+    }
+
+    /// Returns the positions of chunks that changed or were removed since the last call,
+    /// so their meshes can be rebuilt or dropped.
+    pub fn take_dirty_chunks(&mut self) -> Vec<Vector3<i32>> {
+        self.dirty_chunks.drain().collect()
+    }
+
+    /// Returns the positions of voxels that changed since the last call, so the changes
+    /// can be sent to clients.
+    pub fn take_changed_voxels(&mut self) -> Vec<Vector3<i32>> {
+        self.changed_voxels.drain().collect()
+    }
+
+    /// Looks up a voxel by world position. `None` means the chunk is not loaded.
+    pub fn get_voxel(&self, position: Vector3<i32>) -> Option<&Option<Voxel>> {
+        let (chunk_position, local) = to_chunk_coordinates(position);
+        self.chunks.get(&chunk_position)?.get_voxel(local)
+    }
+
+    pub fn get_neighbour(&self, position: Vector3<i32>, direction: &Direction) -> Option<&Option<Voxel>> {
+        self.get_voxel(position + direction.facing())
+    }
+
+    /// Surface heights of the fluid voxel at `position` at its four top corners, indexed
+    /// by `x + 2*z`. Each corner is the average of the fluid voxels around it, which
+    /// slopes the surface towards lower levels, also across chunk borders. Corners next to
+    /// falling fluid reach the top of the voxel.
+    pub fn fluid_corner_heights(&self, position: Vector3<i32>, voxel: &Voxel) -> [f32; 4] {
+        let mut heights = [0.0; 4];
+        for (corner_i, height) in heights.iter_mut().enumerate() {
+            let corner = Vector3::new((corner_i % 2) as i32, 0, (corner_i / 2) as i32);
+            let mut total = 0.0;
+            let mut count = 0;
+            for (dx, dz) in [(0, 0), (1, 0), (0, 1), (1, 1)] {
+                let position = position + corner - Vector3::new(dx, 0, dz);
+                let Some(&Some(other)) = self.get_voxel(position) else {
+                    continue;
+                };
+                if other.block != voxel.block {
+                    continue;
+                }
+                let above = self.get_neighbour(position, &Direction::Up);
+                if above.is_some_and(|above| above.is_some_and(|above| above.block == voxel.block)) {
+                    total = 1.0;
+                    count = 1;
+                    break;
+                }
+                total += fluid::surface_height(&other);
+                count += 1;
+            }
+            *height = if count > 0 { total / count as f32 } else { fluid::surface_height(voxel) };
+        }
+        heights
+    }
+
+    pub fn block_type(&self, position: Vector3<i32>) -> Option<&BlockType> {
+        let voxel = (*self.get_voxel(position)?)?;
+        Some(self.registry.get(voxel.block))
+    }
+
+    /// Replaces the voxel at a world position and returns the previous voxel, or `None`
+    /// when the chunk is not loaded. The changed voxel and its six neighbours are notified
+    /// through their neighbour changed handlers.
+    pub fn set_voxel(&mut self, position: Vector3<i32>, voxel: Option<Voxel>) -> Option<Option<Voxel>> {
+        let previous = self.replace_voxel(position, voxel)?;
+        self.notify_neighbours(position);
+        Some(previous)
+    }
+
+    /// Like `set_voxel`, without notifying neighbours. Used for changes that already ran
+    /// their block logic elsewhere, such as on the server a client is connected to.
+    pub fn set_voxel_without_updates(&mut self, position: Vector3<i32>, voxel: Option<Voxel>) -> Option<Option<Voxel>> {
+        self.replace_voxel(position, voxel)
+    }
+
+    fn replace_voxel(&mut self, position: Vector3<i32>, voxel: Option<Voxel>) -> Option<Option<Voxel>> {
+        let (chunk_position, local) = to_chunk_coordinates(position);
+        let previous = self.chunks.get_mut(&chunk_position)?.set_voxel(local, voxel, &self.registry)?;
+        // Voxels on a border also show in the meshes of the chunks next to it.
+        for offset in neighbourhood() {
+            let neighbour = to_chunk_coordinates(position + offset).0;
+            if self.chunks.contains_key(&neighbour) {
+                self.dirty_chunks.insert(neighbour);
+            }
+        }
+        if previous != voxel {
+            self.changed_voxels.insert(position);
+        }
+        Some(previous)
+    }
+
+    pub fn block_entity(&self, position: Vector3<i32>) -> Option<&BlockEntity> {
+        let (chunk_position, local) = to_chunk_coordinates(position);
+        self.chunks.get(&chunk_position)?.block_entity(local)
+    }
+
+    pub fn block_entity_mut(&mut self, position: Vector3<i32>) -> Option<&mut BlockEntity> {
+        let (chunk_position, local) = to_chunk_coordinates(position);
+        self.chunks.get_mut(&chunk_position)?.block_entity_mut(local)
+    }
+
+    /// Whether placing a block at `position` is allowed. Air and fluids are replaced.
+    pub fn is_replaceable(&self, position: Vector3<i32>) -> bool {
+        match self.get_voxel(position) {
+            Some(None) => true,
+            Some(Some(voxel)) => self.registry.get(voxel.block).fluid.is_some(),
+            None => false,
+        }
+    }
+
+    /// Removes the voxel at `position` and drops the item of its block, together with the
+    /// contents of its block entity.
+    pub fn break_voxel(&mut self, position: Vector3<i32>) -> Option<Voxel> {
+        let contents: Vec<ItemStack> = self.block_entity(position)
+            .and_then(|block_entity| block_entity.inventory())
+            .map_or(Vec::new(), |inventory| inventory.slots().iter().flatten().copied().collect());
+        let voxel = self.set_voxel(position, None)??;
+        let drop_position = position.cast::<f32>() + Vector3::new(0.5, 0.0, 0.5);
+        let block_item = self.items.block_item(voxel.block).map(|item| ItemStack::new(item, 1));
+        for stack in block_item.into_iter().chain(contents) {
+            self.spawn(Spawn::DroppedItem { stack, position: drop_position });
+        }
+        Some(voxel)
+    }
+
+    /// Places a block at `position` if it is replaceable.
+    pub fn place_block(&mut self, position: Vector3<i32>, block: BlockId) -> bool {
+        if !self.is_replaceable(position) {
+            return false;
+        }
+        self.set_voxel(position, Some(Voxel::new(block)));
+        true
+    }
+
+    /// Places the block of the block item in an inventory slot, using up one of the items.
+    /// Returns false when the slot holds no block item or `position` is not replaceable.
+    pub fn place_item(&mut self, inventory: &mut Inventory, slot: usize, position: Vector3<i32>) -> bool {
+        let Some(ItemKind::Block(block)) = inventory.get(slot).map(|stack| self.items.get(stack.item).kind) else {
+            return false;
+        };
+        if !self.place_block(position, block) {
+            return false;
+        }
+        inventory.take(slot, 1);
+        true
+    }
+
+    pub fn spawn(&mut self, spawn: Spawn) {
+        self.spawns.push(spawn);
+    }
+
+    /// Returns the entities requested since the last call.
+    pub fn take_spawns(&mut self) -> Vec<Spawn> {
+        std::mem::take(&mut self.spawns)
+    }
+
+    /// Schedules a tick for the block at `position` in `delay` ticks. The tick is stored
+    /// with the chunk and dropped when the chunk is not loaded.
+    pub fn schedule_tick(&mut self, position: Vector3<i32>, delay: u64) {
+        let (chunk_position, local) = to_chunk_coordinates(position);
+        if let Some(chunk) = self.chunks.get_mut(&chunk_position) {
+            chunk.scheduled_ticks_mut().schedule(local, self.ticks + delay);
+        }
+    }
+
+    /// Advances the simulation by one tick, running the scheduled ticks that are due, the
+    /// random ticks and the block entities.
+    pub fn tick(&mut self) {
+        self.ticks += 1;
+        self.run_scheduled_ticks();
+        self.run_random_ticks();
+        self.tick_block_entities();
+    }
+
+    /// Queues the neighbour changed handlers of the voxel at `position` and its neighbours.
+    /// Voxels changed by those handlers queue their own updates one level deeper, until
+    /// `MAX_UPDATE_DEPTH` is reached. The queue is processed breadth first by the outermost
+    /// call, so handlers never recurse into each other.
+    fn notify_neighbours(&mut self, position: Vector3<i32>) {
+        let depth = self.update_depth + 1;
+        if depth > MAX_UPDATE_DEPTH {
+            return;
+        }
+        let processing = !self.neighbour_updates.is_empty() || self.update_depth > 0;
+        self.neighbour_updates.push_back(NeighbourUpdate { notified: position, changed: position, depth });
+        for direction in Direction::ALL {
+            self.neighbour_updates.push_back(NeighbourUpdate { notified: position + direction.facing(), changed: position, depth });
+        }
+        if processing {
+            return;
+        }
+
+        while let Some(update) = self.neighbour_updates.pop_front() {
+            self.update_depth = update.depth;
+            if let Some(handler) = self.block_type(update.notified).and_then(|block| block.on_neighbour_changed) {
+                handler(self, update.notified, update.changed);
+            }
+        }
+        self.update_depth = 0;
+    }
+
+    fn run_scheduled_ticks(&mut self) {
+        let mut due = Vec::new();
+        for chunk_position in self.chunk_positions() {
+            let chunk = self.chunks.get_mut(&chunk_position).unwrap();
+            due.extend(chunk.scheduled_ticks_mut()
+                .take_due(self.ticks)
+                .into_iter()
+                .map(|local| to_world_coordinates(chunk_position, local)));
+        }
+        for position in due {
+            if let Some(handler) = self.block_type(position).and_then(|block| block.on_scheduled_tick) {
+                handler(self, position);
+            }
+        }
+    }
+
+    fn tick_block_entities(&mut self) {
+        for chunk in self.chunks.values_mut() {
+            for (_, block_entity) in chunk.block_entities_mut().filter(|(_, block_entity)| block_entity.needs_tick()) {
+                block_entity.tick(&self.items);
+            }
+        }
+    }
+
+    fn run_random_ticks(&mut self) {
+        for chunk_position in self.chunk_positions() {
+            for _ in 0..RANDOM_TICKS_PER_CHUNK {
+                let local = chunk_coordinates(self.random.below(CHUNK_VOLUME as u32) as usize);
+                let position = to_world_coordinates(chunk_position, local);
+                if let Some(handler) = self.block_type(position).and_then(|block| block.on_random_tick) {
+                    handler(self, position);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FIRST: Vector3<i32> = Vector3::new(2, 2, 2);
+    const SECOND: Vector3<i32> = Vector3::new(3, 2, 2);
+
+    /// Neighbour handler that changes whichever neighbour just changed, so two of these
+    /// next to each other keep changing each other forever.
+    fn change_back(world: &mut World, position: Vector3<i32>, changed: Vector3<i32>) {
+        if changed == position {
+            return;
+        }
+        if let Some(Some(voxel)) = world.get_voxel(changed).copied() {
+            world.set_voxel(changed, Some(Voxel::with_state(voxel.block, voxel.state + 1)));
+        }
+    }
+
+    #[test]
+    fn neighbour_updates_stop_at_the_maximum_depth() {
+        let mut registry = BlockRegistry::with_defaults();
+        let relay = registry.register(BlockType::new("relay").with_neighbour_changed(change_back));
+        let items = ItemRegistry::with_defaults(&registry);
+        let mut world = World::new(registry, items, 0);
+        world.insert_chunk(Vector3::zeros(), Chunk::new(vec![None; CHUNK_VOLUME]));
+
+        world.set_voxel(SECOND, Some(Voxel::new(relay)));
+        // Every level of the cascade changes the first relay once, up to the maximum depth.
+        world.set_voxel(FIRST, Some(Voxel::new(relay)));
+        assert_eq!(world.get_voxel(FIRST), Some(&Some(Voxel::with_state(relay, MAX_UPDATE_DEPTH as u8))));
+        assert_eq!(world.get_voxel(SECOND), Some(&Some(Voxel::new(relay))));
+
+        // The depth starts over for the next change.
+        world.set_voxel(FIRST, Some(Voxel::new(relay)));
+        assert_eq!(world.get_voxel(FIRST), Some(&Some(Voxel::with_state(relay, MAX_UPDATE_DEPTH as u8))));
+    }
+}
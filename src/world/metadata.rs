@@ -0,0 +1,122 @@
+use std::{fmt, fs, io, path::Path};
+
+use nalgebra::Vector3;
+
+use crate::encoding::invalid_data;
+
+use super::time::WorldClock;
+
+/// Distance fog, blending geometry into the sky colour the further away it is.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum FogMode {
+    /// Fog grows linearly from nothing at `start` to opaque at `end`.
+    Linear { start: f32, end: f32 },
+    /// Visibility drops by `exp(-(density * distance)^2)`.
+    Exponential { density: f32 },
+}
+
+impl FogMode {
+    /// Parses the `linear:<start>:<end>` and `exponential:<density>` notations.
+    pub fn parse(value: &str) -> Option<Self> {
+        let mut parts = value.split(':');
+        let mode = match parts.next()? {
+            "linear" => FogMode::Linear {
+                start: parts.next()?.parse().ok()?,
+                end: parts.next()?.parse().ok()?,
+            },
+            "exponential" => FogMode::Exponential {
+                density: parts.next()?.parse().ok()?,
+            },
+            _ => return None,
+        };
+        parts.next().is_none().then_some(mode)
+    }
+}
+
+impl fmt::Display for FogMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FogMode::Linear { start, end } => write!(f, "linear:{}:{}", start, end),
+            FogMode::Exponential { density } => write!(f, "exponential:{}", density),
+        }
+    }
+}
+
+/// World wide state that is not stored in chunks. Saved as a plain `key=value` file.
+pub struct WorldMetadata {
+    pub name: String,
+    pub seed: u64,
+    pub time: u64,
+    pub time_paused: bool,
+    pub fog: Option<FogMode>,
+    pub spawn_point: Vector3<f32>,
+}
+
+impl WorldMetadata {
+    pub fn new(name: &str) -> Self {
+        let clock = WorldClock::default();
+        Self {
+            name: name.to_owned(),
+            seed: 0,
+            time: clock.ticks(),
+            time_paused: clock.is_paused(),
+            fog: Some(FogMode::Linear { start: 16.0, end: 40.0 }),
+            spawn_point: Vector3::new(2.5, 5.0, 2.5),
+        }
+    }
+
+    pub fn clock(&self) -> WorldClock {
+        let mut clock = WorldClock::new(self.time);
+        clock.set_paused(self.time_paused);
+        clock
+    }
+
+    pub fn store_clock(&mut self, clock: &WorldClock) {
+        self.time = clock.ticks();
+        self.time_paused = clock.is_paused();
+    }
+
+    pub fn load(path: &Path) -> io::Result<Self> {
+        let mut metadata = Self::new("");
+        for line in fs::read_to_string(path)?.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let Some((key, value)) = line.split_once('=') else {
+                return Err(invalid_data(format!("Malformed line '{}'", line)));
+            };
+            let value = value.trim();
+            match key.trim() {
+                "name" => metadata.name = value.to_owned(),
+                "seed" => metadata.seed = value.parse().map_err(|_| invalid_data(format!("Invalid seed '{}'", value)))?,
+                "time" => metadata.time = value.parse().map_err(|_| invalid_data(format!("Invalid time '{}'", value)))?,
+                "time_paused" => metadata.time_paused = value.parse().map_err(|_| invalid_data(format!("Invalid time_paused '{}'", value)))?,
+                "fog" if value == "none" => metadata.fog = None,
+                "spawn_point" => metadata.spawn_point = parse_vector(value).ok_or_else(|| invalid_data(format!("Invalid spawn_point '{}'", value)))?,
+                "fog" => metadata.fog = Some(FogMode::parse(value).ok_or_else(|| invalid_data(format!("Invalid fog '{}'", value)))?),
+                _ => {},
+            }
+        }
+        Ok(metadata)
+    }
+
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let fog = self.fog.map_or("none".to_owned(), |fog| fog.to_string());
+        fs::write(path, format!(
+            "name={}\nseed={}\ntime={}\ntime_paused={}\nfog={}\nspawn_point={} {} {}\n",
+            self.name, self.seed, self.time, self.time_paused, fog,
+            self.spawn_point.x, self.spawn_point.y, self.spawn_point.z,
+        ))
+    }
+}
+
+/// Parses three numbers separated by whitespace.
+fn parse_vector(value: &str) -> Option<Vector3<f32>> {
+    let mut parts = value.split_whitespace().map(|part| part.parse().ok());
+    let vector = Vector3::new(parts.next()??, parts.next()??, parts.next()??);
+    parts.next().is_none().then_some(vector)
+}
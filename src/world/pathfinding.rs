@@ -0,0 +1,288 @@
+use std::{cmp::Reverse, collections::{BinaryHeap, HashMap}};
+
+use nalgebra::Vector3;
+
+use crate::{math::Direction, physics::is_solid};
+
+use super::map::World;
+
+const HORIZONTAL: [Direction; 4] = [Direction::Left, Direction::Right, Direction::Back, Direction::Front];
+
+/// Cost of moving one voxel sideways. Other costs are relative to it.
+const MOVE_COST: u32 = 10;
+const STEP_UP_COST: u32 = 5;
+const FALL_COST: u32 = 2;
+const JUMP_COST: u32 = 10;
+
+/// How a mob moves, used to decide which voxels it can walk between.
+#[derive(Clone, Copy, Debug)]
+pub struct PathSettings {
+    /// Height of the mob in voxels, the air needed above every voxel it stands in.
+    pub height: i32,
+    /// Highest ledge the mob can step or jump up onto.
+    pub step_height: i32,
+    /// Widest gap the mob can jump across at the same height.
+    pub jump_distance: i32,
+    /// Deepest drop the mob is willing to walk off.
+    pub max_fall: i32,
+    /// Voxels that may be expanded before the search gives up, so unreachable targets
+    /// fail fast.
+    pub node_budget: usize,
+}
+
+impl Default for PathSettings {
+    fn default() -> Self {
+        Self { height: 2, step_height: 1, jump_distance: 1, max_fall: 3, node_budget: 2048 }
+    }
+}
+
+/// Whether a mob can stand with its feet in `position`: a solid voxel below and enough
+/// air above.
+pub fn is_walkable(world: &World, position: Vector3<i32>, settings: &PathSettings) -> bool {
+    is_solid(world, position - Vector3::y()) && is_clear(world, position, settings.height)
+}
+
+/// Finds a path between the voxels the mob's feet are in using A*. The returned
+/// waypoints start with `start` and end with `goal`. Returns `None` when `goal` cannot
+/// be stood in, or no path was found within the node budget.
+pub fn find_path(world: &World, start: Vector3<i32>, goal: Vector3<i32>, settings: &PathSettings) -> Option<Vec<Vector3<i32>>> {
+    if !is_walkable(world, goal, settings) {
+        return None;
+    }
+
+    let mut open = BinaryHeap::new();
+    let mut costs = HashMap::from([(start, 0)]);
+    let mut came_from = HashMap::new();
+    open.push(Reverse((estimate(start, goal), 0, key(start))));
+
+    let mut expanded = 0;
+    while let Some(Reverse((_, cost, position))) = open.pop() {
+        let position = Vector3::from(position);
+        if position == goal {
+            return Some(reconstruct(&came_from, goal));
+        }
+        // Skip entries that were superseded by a cheaper route.
+        if costs.get(&position).is_some_and(|&best| best < cost) {
+            continue;
+        }
+        expanded += 1;
+        if expanded > settings.node_budget {
+            return None;
+        }
+
+        for (next, move_cost) in moves(world, position, settings) {
+            let next_cost = cost + move_cost;
+            if costs.get(&next).is_some_and(|&best| best <= next_cost) {
+                continue;
+            }
+            costs.insert(next, next_cost);
+            came_from.insert(next, position);
+            open.push(Reverse((next_cost + estimate(next, goal), next_cost, key(next))));
+        }
+    }
+    None
+}
+
+/// The voxels reachable from `position` in a single move, with the cost of each move.
+fn moves(world: &World, position: Vector3<i32>, settings: &PathSettings) -> Vec<(Vector3<i32>, u32)> {
+    let mut moves = Vec::new();
+    for direction in HORIZONTAL {
+        let facing = direction.facing();
+        let next = position + facing;
+
+        if is_walkable(world, next, settings) {
+            moves.push((next, MOVE_COST));
+            continue;
+        }
+
+        if is_clear(world, next, settings.height) {
+            // Walk off the ledge and fall down onto the first floor below.
+            let mut fall = 1;
+            while fall <= settings.max_fall && is_clear(world, next - Vector3::y() * fall, 1) {
+                let landing = next - Vector3::y() * fall;
+                if is_walkable(world, landing, settings) {
+                    moves.push((landing, MOVE_COST + FALL_COST * fall as u32));
+                    break;
+                }
+                fall += 1;
+            }
+
+            // Jump over a gap, landing at the same height.
+            for distance in 2..=settings.jump_distance + 1 {
+                let landing = position + facing * distance;
+                if !is_clear(world, position + facing * (distance - 1), settings.height) {
+                    break;
+                }
+                if is_walkable(world, landing, settings) {
+                    moves.push((landing, MOVE_COST * distance as u32 + JUMP_COST));
+                    break;
+                }
+            }
+            continue;
+        }
+
+        // Step up onto a ledge, which needs headroom above the current voxel too.
+        for rise in 1..=settings.step_height {
+            if !is_clear(world, position + Vector3::y() * settings.height, rise) {
+                break;
+            }
+            let landing = next + Vector3::y() * rise;
+            if is_walkable(world, landing, settings) {
+                moves.push((landing, MOVE_COST + STEP_UP_COST * rise as u32));
+                break;
+            }
+        }
+    }
+    moves
+}
+
+/// Whether `height` voxels starting at `position` and going up are free to move through.
+fn is_clear(world: &World, position: Vector3<i32>, height: i32) -> bool {
+    (0..height).all(|y| !is_solid(world, position + Vector3::y() * y))
+}
+
+/// Lower bound of the cost to `goal`. Every move goes at least one voxel sideways, so
+/// only the horizontal distance counts.
+fn estimate(position: Vector3<i32>, goal: Vector3<i32>) -> u32 {
+    ((position.x - goal.x).unsigned_abs() + (position.z - goal.z).unsigned_abs()) * MOVE_COST
+}
+
+fn key(position: Vector3<i32>) -> [i32; 3] {
+    [position.x, position.y, position.z]
+}
+
+fn reconstruct(came_from: &HashMap<Vector3<i32>, Vector3<i32>>, goal: Vector3<i32>) -> Vec<Vector3<i32>> {
+    let mut path = vec![goal];
+    let mut position = goal;
+    while let Some(&previous) = came_from.get(&position) {
+        path.push(previous);
+        position = previous;
+    }
+    path.reverse();
+    path
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{item::ItemRegistry, world::{block::BlockRegistry, chunk::{Chunk, CHUNK_SIZE_X, CHUNK_SIZE_Z, CHUNK_VOLUME}, voxel::Voxel}};
+
+    use super::*;
+
+    /// `chunks` empty chunks in a row along x. Everything outside of them counts as solid.
+    fn empty_world(chunks: i32) -> World {
+        let registry = BlockRegistry::with_defaults();
+        let items = ItemRegistry::with_defaults(&registry);
+        let mut world = World::new(registry, items, 0);
+        for x in 0..chunks {
+            world.insert_chunk(Vector3::new(x, 0, 0), Chunk::new(vec![None; CHUNK_VOLUME]));
+        }
+        world
+    }
+
+    /// Fills the box between `min` and `max`, both included and spanning the whole chunk
+    /// along z, with cobblestone.
+    fn fill(world: &mut World, min: Vector3<i32>, max: Vector3<i32>) {
+        let cobblestone = Some(Voxel::new(world.registry().id("cobblestone").unwrap()));
+        for x in min.x..=max.x {
+            for y in min.y..=max.y {
+                for z in 0..CHUNK_SIZE_Z {
+                    world.set_voxel_without_updates(Vector3::new(x, y, z), cobblestone);
+                }
+            }
+        }
+    }
+
+    /// A world with a floor at `y = 0`, so mobs stand at `y = 1`.
+    fn flat_world(chunks: i32) -> World {
+        let mut world = empty_world(chunks);
+        fill(&mut world, Vector3::new(0, 0, 0), Vector3::new(chunks * CHUNK_SIZE_X - 1, 0, 0));
+        world
+    }
+
+    fn settings() -> PathSettings {
+        PathSettings::default()
+    }
+
+    /// Whether every waypoint is one move from the previous one: a voxel sideways,
+    /// possibly up or down, or a jump of two voxels at the same height.
+    fn is_connected(path: &[Vector3<i32>]) -> bool {
+        path.windows(2).all(|pair| {
+            let offset = pair[1] - pair[0];
+            let sideways = offset.x.abs() + offset.z.abs();
+            sideways == 1 || (sideways == 2 && offset.y == 0)
+        })
+    }
+
+    #[test]
+    fn steps_up_one_block_but_not_two() {
+        let mut world = flat_world(1);
+        fill(&mut world, Vector3::new(3, 1, 0), Vector3::new(7, 1, 0));
+        let path = find_path(&world, Vector3::new(0, 1, 0), Vector3::new(5, 2, 0), &settings()).unwrap();
+        assert!(is_connected(&path));
+        assert!(path.windows(2).any(|pair| pair[1] - pair[0] == Vector3::new(1, 1, 0)));
+
+        fill(&mut world, Vector3::new(3, 2, 0), Vector3::new(7, 2, 0));
+        assert_eq!(find_path(&world, Vector3::new(0, 1, 0), Vector3::new(5, 3, 0), &settings()), None);
+    }
+
+    #[test]
+    fn jumps_over_a_one_block_gap() {
+        // A pit deeper than `max_fall` runs across the whole chunk at x = 3.
+        let mut world = empty_world(1);
+        fill(&mut world, Vector3::new(0, 0, 0), Vector3::new(2, 5, 0));
+        fill(&mut world, Vector3::new(4, 0, 0), Vector3::new(7, 5, 0));
+        let (start, goal) = (Vector3::new(0, 6, 0), Vector3::new(6, 6, 0));
+
+        let path = find_path(&world, start, goal, &settings()).unwrap();
+        assert!(is_connected(&path));
+        assert!(path.windows(2).any(|pair| pair[1] - pair[0] == Vector3::new(2, 0, 0)));
+        assert!(path.iter().all(|waypoint| waypoint.x != 3));
+
+        let no_jumping = PathSettings { jump_distance: 0, ..settings() };
+        assert_eq!(find_path(&world, start, goal, &no_jumping), None);
+    }
+
+    #[test]
+    fn only_walks_off_ledges_up_to_max_fall() {
+        let mut world = empty_world(1);
+        fill(&mut world, Vector3::new(0, 0, 0), Vector3::new(3, 5, 0));
+        fill(&mut world, Vector3::new(4, 0, 0), Vector3::new(7, 2, 0));
+        let (start, goal) = (Vector3::new(0, 6, 0), Vector3::new(6, 3, 0));
+
+        let path = find_path(&world, start, goal, &PathSettings { max_fall: 3, ..settings() }).unwrap();
+        assert!(is_connected(&path));
+        assert!(path.windows(2).any(|pair| pair[1] - pair[0] == Vector3::new(1, -3, 0)));
+        assert_eq!(find_path(&world, start, goal, &PathSettings { max_fall: 2, ..settings() }), None);
+    }
+
+    #[test]
+    fn crosses_chunk_borders() {
+        let world = flat_world(2);
+        let (start, goal) = (Vector3::new(1, 1, 1), Vector3::new(2 * CHUNK_SIZE_X - 3, 1, 1));
+        let path = find_path(&world, start, goal, &settings()).unwrap();
+        assert_eq!(path.first(), Some(&start));
+        assert_eq!(path.last(), Some(&goal));
+        // Straight along the floor, without detours.
+        assert_eq!(path.len() as i32, goal.x - start.x + 1);
+        assert!(is_connected(&path));
+    }
+
+    #[test]
+    fn tall_mobs_do_not_fit_through_low_tunnels() {
+        let mut world = flat_world(1);
+        fill(&mut world, Vector3::new(3, 2, 0), Vector3::new(4, 7, 0));
+        let (start, goal) = (Vector3::new(0, 1, 0), Vector3::new(6, 1, 0));
+
+        assert_eq!(find_path(&world, start, goal, &PathSettings { height: 2, ..settings() }), None);
+        let path = find_path(&world, start, goal, &PathSettings { height: 1, ..settings() }).unwrap();
+        assert!(path.contains(&Vector3::new(3, 1, 0)));
+    }
+
+    #[test]
+    fn gives_up_when_the_node_budget_runs_out() {
+        let world = flat_world(2);
+        let (start, goal) = (Vector3::new(0, 1, 0), Vector3::new(2 * CHUNK_SIZE_X - 1, 1, CHUNK_SIZE_Z - 1));
+        assert!(find_path(&world, start, goal, &settings()).is_some());
+        assert_eq!(find_path(&world, start, goal, &PathSettings { node_budget: 10, ..settings() }), None);
+    }
+}
@@ -0,0 +1,216 @@
+use std::{fs, io, path::{Path, PathBuf}};
+
+use nalgebra::Vector3;
+
+use crate::{encoding::{invalid_data, Reader}, item::ItemRegistry};
+
+use super::{block::{BlockId, BlockRegistry}, block_entity::BlockEntity, chunk::{chunk_coordinates, chunk_index, Chunk, CHUNK_VOLUME}, voxel::Voxel};
+
+const CHUNK_FORMAT_VERSION: u8 = 1;
+
+/// Saves chunks as one binary file each, named after the chunk position.
+pub struct ChunkStorage {
+    directory: PathBuf,
+}
+
+impl ChunkStorage {
+    pub fn new(directory: &Path) -> Self {
+        Self { directory: directory.to_owned() }
+    }
+
+    /// `now` is the current world tick, scheduled ticks are stored relative to it.
+    pub fn save(&self, position: Vector3<i32>, chunk: &Chunk, now: u64, registry: &BlockRegistry, items: &ItemRegistry) -> io::Result<()> {
+        fs::create_dir_all(&self.directory)?;
+        fs::write(self.chunk_path(position), encode_chunk(chunk, now, registry, items))
+    }
+
+    /// Loads a previously saved chunk, or returns `None` if it was never saved.
+    pub fn load(&self, position: Vector3<i32>, now: u64, registry: &BlockRegistry, items: &ItemRegistry) -> io::Result<Option<Chunk>> {
+        match fs::read(self.chunk_path(position)) {
+            Ok(bytes) => decode_chunk(&bytes, now, registry, items).map(Some),
+            Err(error) if error.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(error) => Err(error),
+        }
+    }
+
+    fn chunk_path(&self, position: Vector3<i32>) -> PathBuf {
+        self.directory.join(format!("chunk_{}_{}_{}.bin", position.x, position.y, position.z))
+    }
+}
+
+/// Encodes the voxels, scheduled ticks and block entities of a chunk. Light is not stored, it is
+/// recalculated when the chunk is loaded into a world.
+pub fn encode_chunk(chunk: &Chunk, now: u64, registry: &BlockRegistry, items: &ItemRegistry) -> Vec<u8> {
+    let mut bytes = vec![CHUNK_FORMAT_VERSION];
+    encode_voxels(&mut bytes, chunk, registry);
+
+    let ticks = chunk.scheduled_ticks();
+    bytes.extend((ticks.len() as u32).to_le_bytes());
+    for (at, position) in ticks.entries() {
+        bytes.extend((chunk_index(position).unwrap() as u16).to_le_bytes());
+        bytes.extend(at.saturating_sub(now).to_le_bytes());
+    }
+
+    bytes.extend((chunk.block_entities().count() as u32).to_le_bytes());
+    for (position, block_entity) in chunk.block_entities() {
+        bytes.extend((chunk_index(*position).unwrap() as u16).to_le_bytes());
+        block_entity.encode(&mut bytes, items);
+    }
+    bytes
+}
+
+/// Decodes a chunk written by `encode_chunk`. Blocks and items not in the registries are
+/// rejected.
+pub fn decode_chunk(bytes: &[u8], now: u64, registry: &BlockRegistry, items: &ItemRegistry) -> io::Result<Chunk> {
+    let mut reader = Reader::new(bytes);
+    let version = reader.u8()?;
+    if version != CHUNK_FORMAT_VERSION {
+        return Err(invalid_data(format!("Unsupported chunk format version {}", version)));
+    }
+    let mut chunk = decode_voxels(&mut reader, registry)?;
+
+    let tick_count = reader.u32()?;
+    for _ in 0..tick_count {
+        let index = reader.u16()? as usize;
+        let delay = reader.u64()?;
+        if index >= CHUNK_VOLUME {
+            return Err(invalid_data(format!("Scheduled tick outside of chunk at index {}", index)));
+        }
+        chunk.scheduled_ticks_mut().schedule(chunk_coordinates(index), now + delay);
+    }
+
+    let block_entity_count = reader.u32()?;
+    for _ in 0..block_entity_count {
+        let index = reader.u16()? as usize;
+        if index >= CHUNK_VOLUME {
+            return Err(invalid_data(format!("Block entity outside of chunk at index {}", index)));
+        }
+        chunk.insert_block_entity(chunk_coordinates(index), BlockEntity::decode(&mut reader, items)?);
+    }
+    Ok(chunk)
+}
+
+/// Encodes only the voxels of a chunk, which is all a client needs to show it. Block
+/// entity contents and scheduled ticks stay on the server.
+pub fn encode_chunk_voxels(chunk: &Chunk, registry: &BlockRegistry) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    encode_voxels(&mut bytes, chunk, registry);
+    bytes
+}
+
+/// Decodes a chunk written by `encode_chunk_voxels`, with empty block entities.
+pub fn decode_chunk_voxels(bytes: &[u8], registry: &BlockRegistry) -> io::Result<Chunk> {
+    let mut reader = Reader::new(bytes);
+    let chunk = decode_voxels(&mut reader, registry)?;
+    if !reader.is_empty() {
+        return Err(invalid_data("Trailing bytes after the chunk voxels".to_owned()));
+    }
+    Ok(chunk)
+}
+
+/// Writes a palette of the names of the blocks in the chunk, then every voxel as an index
+/// into it and its state. Storing names keeps saves independent of the order blocks are
+/// registered in.
+fn encode_voxels(bytes: &mut Vec<u8>, chunk: &Chunk, registry: &BlockRegistry) {
+    let mut palette: Vec<BlockId> = Vec::new();
+    let mut voxels = Vec::with_capacity(CHUNK_VOLUME);
+    for voxel in &chunk.chunk_data {
+        // Palette indices are shifted by one so zero can mean air.
+        let index = voxel.map_or(0, |voxel| match palette.iter().position(|block| *block == voxel.block) {
+            Some(index) => index + 1,
+            None => {
+                palette.push(voxel.block);
+                palette.len()
+            },
+        });
+        voxels.push((index as u16, voxel.map_or(0, |voxel| voxel.state)));
+    }
+
+    bytes.extend((palette.len() as u16).to_le_bytes());
+    for block in palette {
+        let name = &registry.get(block).name;
+        bytes.extend((name.len() as u16).to_le_bytes());
+        bytes.extend(name.as_bytes());
+    }
+    for (index, state) in voxels {
+        bytes.extend(index.to_le_bytes());
+        bytes.push(state);
+    }
+}
+
+fn decode_voxels(reader: &mut Reader, registry: &BlockRegistry) -> io::Result<Chunk> {
+    let palette_size = reader.u16()?;
+    let mut palette = Vec::with_capacity(palette_size as usize);
+    for _ in 0..palette_size {
+        let length = reader.u16()? as usize;
+        let name = std::str::from_utf8(reader.bytes(length)?)
+            .map_err(|_| invalid_data("Block name is not valid UTF-8".to_owned()))?;
+        palette.push(registry.id(name).ok_or_else(|| invalid_data(format!("Unknown block '{}'", name)))?);
+    }
+
+    let mut voxels = Vec::with_capacity(CHUNK_VOLUME);
+    for _ in 0..CHUNK_VOLUME {
+        let index = reader.u16()? as usize;
+        let state = reader.u8()?;
+        if index == 0 {
+            voxels.push(None);
+            continue;
+        }
+        let block = *palette.get(index - 1).ok_or_else(|| invalid_data(format!("Block palette index {} out of range", index)))?;
+        voxels.push(Some(Voxel::with_state(block, state)));
+    }
+    Ok(Chunk::new(voxels))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{item::ItemStack, world::block::BlockType};
+
+    use super::*;
+
+    #[test]
+    fn saved_blocks_survive_a_different_registration_order() {
+        let registry = BlockRegistry::with_defaults();
+        let items = ItemRegistry::with_defaults(&registry);
+        let mut chunk = Chunk::new(vec![None; CHUNK_VOLUME]);
+        let (water, cobblestone) = (registry.id("water").unwrap(), registry.id("cobblestone").unwrap());
+        chunk.set_voxel(Vector3::new(1, 2, 3), Some(Voxel::with_state(water, 3)), &registry);
+        chunk.set_voxel(Vector3::new(4, 0, 0), Some(Voxel::new(cobblestone)), &registry);
+        chunk.set_voxel(Vector3::new(5, 0, 0), Some(Voxel::new(cobblestone)), &registry);
+        let bytes = encode_chunk(&chunk, 0, &registry, &items);
+        assert_eq!(decode_chunk(&bytes, 0, &registry, &items).unwrap().chunk_data, chunk.chunk_data);
+
+        let mut reordered = BlockRegistry::new();
+        let cobblestone = reordered.register(BlockType::new("cobblestone"));
+        let water = reordered.register(BlockType::new("water"));
+        let decoded = decode_chunk(&bytes, 0, &reordered, &items).unwrap();
+        assert_eq!(decoded.get_voxel(Vector3::new(1, 2, 3)), Some(&Some(Voxel::with_state(water, 3))));
+        assert_eq!(decoded.get_voxel(Vector3::new(5, 0, 0)), Some(&Some(Voxel::new(cobblestone))));
+        assert_eq!(decoded.get_voxel(Vector3::new(6, 0, 0)), Some(&None));
+
+        let mut missing = BlockRegistry::new();
+        missing.register(BlockType::new("cobblestone"));
+        let error = decode_chunk(&bytes, 0, &missing, &items).err().unwrap();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn voxels_for_clients_leave_out_block_entities_and_ticks() {
+        let registry = BlockRegistry::with_defaults();
+        let items = ItemRegistry::with_defaults(&registry);
+        let mut chunk = Chunk::new(vec![None; CHUNK_VOLUME]);
+        let position = Vector3::new(2, 3, 4);
+        chunk.set_voxel(position, Some(Voxel::new(registry.id("chest").unwrap())), &registry);
+        let inventory = chunk.block_entity_mut(position).unwrap().inventory_mut().unwrap();
+        inventory.set(0, Some(ItemStack::new(items.id("stick").unwrap(), 3)));
+        chunk.scheduled_ticks_mut().schedule(position, 10);
+
+        let bytes = encode_chunk_voxels(&chunk, &registry);
+        assert!(bytes.len() < encode_chunk(&chunk, 0, &registry, &items).len());
+        let decoded = decode_chunk_voxels(&bytes, &registry).unwrap();
+        assert_eq!(decoded.chunk_data, chunk.chunk_data);
+        assert_eq!(decoded.block_entities().count(), 0);
+        assert!(decoded.scheduled_ticks().is_empty());
+        assert!(decode_chunk_voxels(&[bytes.as_slice(), &[0]].concat(), &registry).is_err());
+    }
+}
@@ -0,0 +1,13 @@
+use nalgebra::Vector3;
+
+use crate::{math::Direction, physics::is_solid};
+
+use super::map::World;
+
+/// Neighbour handler for blocks that rest on the voxel below them, such as torches and
+/// crops. Breaks the block once that voxel is gone.
+pub fn break_if_unsupported(world: &mut World, position: Vector3<i32>, _changed: Vector3<i32>) {
+    if !is_solid(world, position + Direction::Down.facing()) {
+        world.break_voxel(position);
+    }
+}
@@ -0,0 +1,116 @@
+use std::collections::{BTreeMap, HashSet};
+
+use nalgebra::Vector3;
+
+/// Keeps track of voxels that asked to be updated at a later world tick. Each chunk owns
+/// one for the voxels inside it, using positions local to the chunk.
+pub struct TickScheduler {
+    scheduled: BTreeMap<u64, Vec<Vector3<i32>>>,
+    pending: HashSet<Vector3<i32>>,
+}
+
+impl TickScheduler {
+    pub fn new() -> Self {
+        Self { scheduled: BTreeMap::new(), pending: HashSet::new() }
+    }
+
+    /// Schedules an update of `position` at tick `at`. Ignored when the position
+    /// already has an update pending.
+    pub fn schedule(&mut self, position: Vector3<i32>, at: u64) {
+        if self.pending.insert(position) {
+            self.scheduled.entry(at).or_default().push(position);
+        }
+    }
+
+    pub fn is_scheduled(&self, position: &Vector3<i32>) -> bool {
+        self.pending.contains(position)
+    }
+
+    /// All pending updates as `(tick, position)`, ordered by tick.
+    pub fn entries(&self) -> impl Iterator<Item = (u64, Vector3<i32>)> + '_ {
+        self.scheduled
+            .iter()
+            .flat_map(|(&at, positions)| positions.iter().map(move |&position| (at, position)))
+    }
+
+    pub fn len(&self) -> usize {
+        self.pending.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pending.is_empty()
+    }
+
+    /// Removes and returns every position due at or before tick `now`, oldest first.
+    pub fn take_due(&mut self, now: u64) -> Vec<Vector3<i32>> {
+        let later = self.scheduled.split_off(&(now + 1));
+        let due: Vec<Vector3<i32>> = std::mem::replace(&mut self.scheduled, later)
+            .into_values()
+            .flatten()
+            .collect();
+        for position in &due {
+            self.pending.remove(position);
+        }
+        due
+    }
+}
+
+impl Default for TickScheduler {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{item::ItemRegistry, world::{block::BlockRegistry, chunk::{Chunk, CHUNK_VOLUME}, storage::{decode_chunk, encode_chunk}}};
+
+    use super::*;
+
+    #[test]
+    fn takes_due_positions_oldest_first() {
+        let mut ticks = TickScheduler::new();
+        ticks.schedule(Vector3::new(0, 0, 3), 30);
+        ticks.schedule(Vector3::new(0, 0, 1), 10);
+        ticks.schedule(Vector3::new(0, 0, 2), 20);
+        ticks.schedule(Vector3::new(0, 0, 4), 20);
+
+        assert!(ticks.take_due(9).is_empty());
+        assert_eq!(ticks.take_due(20), vec![Vector3::new(0, 0, 1), Vector3::new(0, 0, 2), Vector3::new(0, 0, 4)]);
+        assert_eq!(ticks.len(), 1);
+        assert_eq!(ticks.take_due(100), vec![Vector3::new(0, 0, 3)]);
+        assert!(ticks.is_empty());
+    }
+
+    #[test]
+    fn ignores_positions_that_are_already_scheduled() {
+        let mut ticks = TickScheduler::new();
+        let position = Vector3::new(1, 2, 3);
+        ticks.schedule(position, 10);
+        ticks.schedule(position, 5);
+        assert_eq!(ticks.len(), 1);
+        assert!(ticks.take_due(5).is_empty());
+        assert_eq!(ticks.take_due(10), vec![position]);
+
+        // Once it ran, the position can be scheduled again.
+        assert!(!ticks.is_scheduled(&position));
+        ticks.schedule(position, 12);
+        assert!(ticks.is_scheduled(&position));
+    }
+
+    #[test]
+    fn scheduled_ticks_are_saved_with_the_chunk() {
+        let registry = BlockRegistry::with_defaults();
+        let items = ItemRegistry::with_defaults(&registry);
+        let mut chunk = Chunk::new(vec![None; CHUNK_VOLUME]);
+        chunk.scheduled_ticks_mut().schedule(Vector3::new(1, 2, 3), 105);
+        chunk.scheduled_ticks_mut().schedule(Vector3::new(7, 7, 7), 140);
+
+        // Saved at tick 100 and loaded at tick 1000, the ticks keep their delays.
+        let bytes = encode_chunk(&chunk, 100, &registry, &items);
+        let mut loaded = decode_chunk(&bytes, 1000, &registry, &items).unwrap();
+        let entries: Vec<(u64, Vector3<i32>)> = loaded.scheduled_ticks().entries().collect();
+        assert_eq!(entries, vec![(1005, Vector3::new(1, 2, 3)), (1040, Vector3::new(7, 7, 7))]);
+        assert_eq!(loaded.scheduled_ticks_mut().take_due(1005), vec![Vector3::new(1, 2, 3)]);
+    }
+}
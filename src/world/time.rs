@@ -0,0 +1,160 @@
+use std::f32::consts::PI;
+
+use nalgebra::Vector3;
+
+pub const TICKS_PER_DAY: u64 = 24000;
+pub const TICKS_PER_SECOND: u64 = 20;
+
+pub const SUNRISE: u64 = 0;
+pub const NOON: u64 = TICKS_PER_DAY / 4;
+pub const SUNSET: u64 = TICKS_PER_DAY / 2;
+pub const MIDNIGHT: u64 = 3 * TICKS_PER_DAY / 4;
+
+const MIN_SKYLIGHT: f32 = 0.1;
+
+/// Counts world ticks. A day starts at sunrise, with the sun rising along positive X
+/// and setting along negative X.
+pub struct WorldClock {
+    ticks: u64,
+    paused: bool,
+}
+
+impl WorldClock {
+    pub fn new(ticks: u64) -> Self { Self { ticks, paused: false } }
+
+    pub fn tick(&mut self) {
+        if !self.paused {
+            self.ticks += 1;
+        }
+    }
+
+    pub fn ticks(&self) -> u64 {
+        self.ticks
+    }
+
+    pub fn day(&self) -> u64 {
+        self.ticks / TICKS_PER_DAY
+    }
+
+    pub fn time_of_day(&self) -> u64 {
+        self.ticks % TICKS_PER_DAY
+    }
+
+    /// Moves to `time_of_day` without going back to a previous day.
+    pub fn set_time_of_day(&mut self, time_of_day: u64) {
+        let time_of_day = time_of_day % TICKS_PER_DAY;
+        if time_of_day < self.time_of_day() {
+            self.ticks += TICKS_PER_DAY;
+        }
+        self.ticks = self.day() * TICKS_PER_DAY + time_of_day;
+    }
+
+    pub fn set_ticks(&mut self, ticks: u64) {
+        self.ticks = ticks;
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    pub fn set_paused(&mut self, paused: bool) {
+        self.paused = paused;
+    }
+
+    /// Angle of the sun above the eastern horizon in radians, going from 0 at sunrise
+    /// through PI/2 at noon to PI at sunset.
+    pub fn sun_angle(&self) -> f32 {
+        self.time_of_day() as f32 / TICKS_PER_DAY as f32 * 2.0 * PI
+    }
+
+    /// Unit vector pointing from the world towards the sun.
+    pub fn sun_direction(&self) -> Vector3<f32> {
+        let angle = self.sun_angle();
+        Vector3::new(angle.cos(), angle.sin(), 0.0)
+    }
+
+    /// How much of the sky is lit, from 0 at night to 1 during the day. The transition
+    /// happens while the sun is close to the horizon.
+    pub fn daylight(&self) -> f32 {
+        (self.sun_angle().sin() * 4.0 + 0.5).clamp(0.0, 1.0)
+    }
+
+    /// Multiplier applied to skylit geometry.
+    pub fn skylight(&self) -> f32 {
+        MIN_SKYLIGHT + (1.0 - MIN_SKYLIGHT) * self.daylight()
+    }
+
+    pub fn sky_color(&self) -> Vector3<f32> {
+        let night = Vector3::new(0.01, 0.01, 0.05);
+        let day = Vector3::new(0.47, 0.65, 1.0);
+        let sunset = Vector3::new(0.9, 0.45, 0.2);
+
+        let daylight = self.daylight();
+        let sky = night.lerp(&day, daylight);
+        // Tint the sky towards orange while the sun crosses the horizon.
+        let horizon = 1.0 - (daylight * 2.0 - 1.0).abs();
+        sky.lerp(&sunset, horizon * 0.6)
+    }
+}
+
+impl Default for WorldClock {
+    fn default() -> Self {
+        Self::new(NOON)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EPSILON: f32 = 1e-4;
+
+    fn assert_near(actual: Vector3<f32>, expected: Vector3<f32>) {
+        assert!((actual - expected).norm() < EPSILON, "{:?} is not {:?}", actual, expected);
+    }
+
+    #[test]
+    fn sun_rises_east_peaks_at_noon_and_sets_west() {
+        let mut clock = WorldClock::new(SUNRISE);
+        assert_near(clock.sun_direction(), Vector3::x());
+        assert!((clock.daylight() - 0.5).abs() < EPSILON);
+
+        clock.set_time_of_day(NOON);
+        assert_near(clock.sun_direction(), Vector3::y());
+        assert_eq!(clock.daylight(), 1.0);
+
+        clock.set_time_of_day(SUNSET);
+        assert_near(clock.sun_direction(), -Vector3::x());
+        assert!((clock.daylight() - 0.5).abs() < EPSILON);
+
+        clock.set_time_of_day(MIDNIGHT);
+        assert_near(clock.sun_direction(), -Vector3::y());
+        assert_eq!(clock.daylight(), 0.0);
+        assert_eq!(clock.skylight(), MIN_SKYLIGHT);
+    }
+
+    #[test]
+    fn setting_the_time_of_day_never_goes_back_a_day() {
+        let mut clock = WorldClock::new(3 * TICKS_PER_DAY + NOON);
+        clock.set_time_of_day(SUNSET);
+        assert_eq!((clock.day(), clock.time_of_day()), (3, SUNSET));
+
+        // An earlier time of day is reached the next day.
+        clock.set_time_of_day(SUNRISE);
+        assert_eq!((clock.day(), clock.time_of_day()), (4, SUNRISE));
+    }
+
+    #[test]
+    fn paused_clock_does_not_advance() {
+        let mut clock = WorldClock::new(100);
+        clock.set_paused(true);
+        for _ in 0..10 {
+            clock.tick();
+        }
+        assert_eq!(clock.ticks(), 100);
+
+        clock.set_paused(false);
+        clock.tick();
+        assert_eq!(clock.ticks(), 101);
+    }
+}
@@ -1,4 +1,14 @@
-#[derive(Clone, Copy, Debug)]
+use super::block::BlockId;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub struct Voxel {
-    // pub value: f32
-}
\ No newline at end of file
+    pub block: BlockId,
+    /// Block specific data, such as the level of a fluid.
+    pub state: u8,
+}
+
+impl Voxel {
+    pub fn new(block: BlockId) -> Self { Self { block, state: 0 } }
+
+    pub fn with_state(block: BlockId, state: u8) -> Self { Self { block, state } }
+}
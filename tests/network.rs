@@ -0,0 +1,146 @@
+use std::{fs, sync::{atomic::{AtomicBool, Ordering}, mpsc, Arc}, thread, time::{Duration, Instant}};
+
+use nalgebra::Vector3;
+use voxel_game::{ecs::player::{PlayerAction, PlayerInput}, network::{Client, LinkConditions, LocalNetwork}, physics::PhysicsBody, server::{Server, ServerProperties}, simulation::Simulation, world::voxel::Voxel};
+
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Ticks the client until `done` holds, failing after `TIMEOUT`.
+fn tick_until(client: &mut Client, simulation: &mut Simulation, done: impl Fn(&Simulation) -> bool) {
+    let start = Instant::now();
+    while !done(simulation) {
+        assert!(start.elapsed() < TIMEOUT, "timed out");
+        client.tick(simulation).unwrap();
+        thread::sleep(Duration::from_millis(1));
+    }
+}
+
+fn temporary_world(name: &str) -> ServerProperties {
+    let directory = std::env::temp_dir().join(format!("voxel_game_{}_{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&directory);
+    ServerProperties { world: directory, port: 0, lan_discovery: false, ..Default::default() }
+}
+
+/// Runs a server and a client in this thread over a `LocalNetwork`.
+struct LocalGame {
+    network: LocalNetwork,
+    server: Server,
+    simulation: Simulation,
+    client: Client,
+    replica: Simulation,
+}
+
+impl LocalGame {
+    fn new(properties: ServerProperties) -> Self {
+        let mut network = LocalNetwork::new();
+        let (client_end, server_end) = network.connect();
+        let simulation = Simulation::open(&properties.world);
+        let mut server = Server::bind(properties).unwrap();
+        server.add_client(server_end);
+        let client = Client::login(client_end, "Tester");
+        Self { network, server, simulation, client, replica: Simulation::replica() }
+    }
+
+    fn step(&mut self) {
+        self.client.tick(&mut self.replica).unwrap();
+        self.simulation.tick();
+        self.server.tick(&mut self.simulation);
+        self.network.advance();
+    }
+
+    fn input(&mut self) -> &mut PlayerInput {
+        let player = self.client.player().unwrap();
+        self.replica.entities.get_mut::<PlayerInput>(player).unwrap()
+    }
+
+    fn finish(mut self) {
+        self.server.shutdown(&mut self.simulation);
+        fs::remove_dir_all(&self.server.properties().world).unwrap();
+    }
+}
+
+#[test]
+fn prediction_settles_under_latency_jitter_and_loss() {
+    let mut game = LocalGame::new(temporary_world("prediction"));
+    game.network.set_conditions(LinkConditions { latency: 3, jitter: 2, loss: 0.2 });
+    for _ in 0..100 {
+        game.step();
+    }
+    assert!(game.client.player().is_some());
+
+    game.input().press(PlayerAction::Forward);
+    for _ in 0..100 {
+        game.step();
+        // At most 20 inputs wait on the server, and a round trip takes at most 10 ticks.
+        assert!(game.client.pending_inputs() <= 32, "{} inputs pending", game.client.pending_inputs());
+    }
+    game.input().release(PlayerAction::Forward);
+    for _ in 0..100 {
+        game.step();
+    }
+
+    // Once the link is perfect again, the server applies one input for every one sent,
+    // so only the inputs it still has queued stay pending.
+    game.network.set_conditions(LinkConditions::default());
+    for _ in 0..20 {
+        game.step();
+    }
+    let pending = game.client.pending_inputs();
+    for _ in 0..20 {
+        game.step();
+        assert_eq!(game.client.pending_inputs(), pending);
+        assert_eq!(game.client.prediction_error(), 0.0);
+    }
+    assert!(pending <= 22, "{} inputs pending", pending);
+    let player = game.client.player().unwrap();
+    assert!(game.replica.entities.get::<PhysicsBody>(player).unwrap().position.z < 2.0);
+    game.finish();
+}
+
+#[test]
+fn client_logs_in_receives_chunks_and_block_changes_over_tcp() {
+    let directory = std::env::temp_dir().join(format!("voxel_game_network_{}", std::process::id()));
+    let _ = fs::remove_dir_all(&directory);
+    let properties = ServerProperties {
+        world: directory.clone(),
+        port: 0,
+        lan_discovery: false,
+        operators: vec!["Tester".to_owned()],
+        ..Default::default()
+    };
+
+    // The simulation is not `Send`, so the server thread creates its own.
+    let running = Arc::new(AtomicBool::new(true));
+    let (port_sender, port) = mpsc::channel();
+    let server = thread::spawn({
+        let running = running.clone();
+        move || {
+            let mut simulation = Simulation::open(&properties.world);
+            let mut server = Server::bind(properties).unwrap();
+            port_sender.send(server.local_address().unwrap().port()).unwrap();
+            while running.load(Ordering::Relaxed) {
+                simulation.tick();
+                server.tick(&mut simulation);
+                thread::sleep(Duration::from_millis(1));
+            }
+            server.shutdown(&mut simulation);
+        }
+    });
+    let port = port.recv_timeout(TIMEOUT).unwrap();
+
+    let mut simulation = Simulation::replica();
+    let mut client = Client::connect(("127.0.0.1", port), "Tester", &mut simulation).unwrap();
+    assert!(client.player().is_some());
+
+    tick_until(&mut client, &mut simulation, |simulation| simulation.world.chunk(Vector3::zeros()).is_some());
+    let changed = Vector3::new(2, 6, 2);
+    assert_eq!(simulation.world.get_voxel(changed), Some(&None));
+
+    client.send_chat("/setblock 2 6 2 glass");
+    let glass = simulation.world.registry().id("glass").unwrap();
+    tick_until(&mut client, &mut simulation, |simulation| simulation.world.get_voxel(changed) == Some(&Some(Voxel::new(glass))));
+
+    running.store(false, Ordering::Relaxed);
+    server.join().unwrap();
+    fs::remove_dir_all(&directory).unwrap();
+}